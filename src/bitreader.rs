@@ -0,0 +1,53 @@
+use crate::error::Error;
+
+type Result<T> = std::result::Result<T, Error>;
+
+// MSB-first bit reader for codec configuration records
+pub(crate) struct BitReader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> BitReader<'a> {
+    pub(crate) fn new(data: &'a [u8]) -> Self {
+        BitReader { data, position: 0 }
+    }
+
+    pub(crate) fn remaining(&self) -> usize {
+        self.data.len() * 8 - self.position
+    }
+
+    pub(crate) fn position(&self) -> usize {
+        self.position
+    }
+
+    pub(crate) fn read_bits(&mut self, n: u32) -> Result<u64> {
+        if n > 64 {
+            return Err(Error::InvalidData("cannot read more than 64 bits at once"));
+        }
+        if n as usize > self.remaining() {
+            return Err(Error::InvalidData("not enough bits"));
+        }
+
+        let mut value = 0u64;
+        for _ in 0..n {
+            let byte = self.data[self.position / 8];
+            let bit = (byte >> (7 - self.position % 8)) & 0x1;
+            value = (value << 1) | bit as u64;
+            self.position += 1;
+        }
+        Ok(value)
+    }
+
+    pub(crate) fn read_bit(&mut self) -> Result<bool> {
+        Ok(self.read_bits(1)? == 1)
+    }
+
+    pub(crate) fn skip_bits(&mut self, n: usize) -> Result<()> {
+        if n > self.remaining() {
+            return Err(Error::InvalidData("not enough bits"));
+        }
+        self.position += n;
+        Ok(())
+    }
+}
@@ -1,3 +1,5 @@
+#![allow(clippy::mistyped_literal_suffixes)]
+
 use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
 use std::convert::TryInto;
 use std::fmt;
@@ -6,6 +8,7 @@ use std::io::{Read, Seek, SeekFrom, Write};
 use crate::error::Error;
 use crate::types::FourCC;
 
+pub(crate) mod av01;
 pub(crate) mod avc1;
 pub(crate) mod co64;
 pub(crate) mod ctts;
@@ -24,6 +27,8 @@ pub(crate) mod mdia;
 pub(crate) mod mehd;
 pub(crate) mod meta;
 pub(crate) mod mfhd;
+pub(crate) mod mfra;
+pub(crate) mod mfro;
 pub(crate) mod minf;
 pub(crate) mod moof;
 pub(crate) mod moov;
@@ -32,6 +37,7 @@ pub(crate) mod mvex;
 pub(crate) mod mvhd;
 pub(crate) mod pasp;
 pub(crate) mod phtm;
+pub(crate) mod sidx;
 pub(crate) mod smhd;
 pub(crate) mod stbl;
 pub(crate) mod stco;
@@ -42,6 +48,7 @@ pub(crate) mod stsz;
 pub(crate) mod stts;
 pub(crate) mod tfdt;
 pub(crate) mod tfhd;
+pub(crate) mod tfra;
 pub(crate) mod tkhd;
 pub(crate) mod traf;
 pub(crate) mod trak;
@@ -49,6 +56,7 @@ pub(crate) mod trex;
 pub(crate) mod trun;
 pub(crate) mod tx3g;
 pub(crate) mod udta;
+pub(crate) mod unknown;
 pub(crate) mod url;
 pub(crate) mod vmhd;
 pub(crate) mod vp09;
@@ -84,19 +92,29 @@ macro_rules! boxdef {
 
         #[derive(Debug, Clone)]
         pub enum BoxData {
-            $( $field($box) ),*
+            $( $field($box), )*
+            Unknown(UnknownBox),
         }
 
         impl BoxData {
             pub fn typ(&self) -> BoxType {
                 match &self {
-                    $( BoxData::$field(ref b) => b.typ() ),*
+                    $( BoxData::$field(ref b) => b.typ(), )*
+                    BoxData::Unknown(ref b) => b.typ(),
                 }
             }
 
             pub fn effective_size(&self) -> u64 {
                 match &self {
-                    $( BoxData::$field(ref b) => b.header_size() + b.data_size() ),*
+                    $( BoxData::$field(ref b) => b.header_size() + b.data_size(), )*
+                    BoxData::Unknown(ref b) => b.header_size() + b.data_size(),
+                }
+            }
+
+            pub fn write<W: Write>(&self, writer: &mut W, offset: u64) -> Result<u64> {
+                match &self {
+                    $( BoxData::$field(ref b) => b.write(writer, offset), )*
+                    BoxData::Unknown(ref b) => b.write(writer, offset),
                 }
             }
         }
@@ -155,22 +173,27 @@ macro_rules! boxdef {
 boxdef! {
     phtm, Phtm, PhtmBox => 0x2d_2d_2d_2d,
     ftyp, Ftyp, FtypBox => 0x66_74_79_70,
+    ftyp, Styp, StypBox => 0x73_74_79_70,
     mvhd, Mvhd, MvhdBox => 0x6d_76_68_64,
-    // mfhd, Mfhd, MfhdBox => 0x6d_66_68_64,
+    mfhd, Mfhd, MfhdBox => 0x6d_66_68_64,
     // free, Free, FreeBox => 0x66_72_65_65,
     // mdat, Mdat, MdatBox => 0x6d_64_61_74,
     moov, Moov, MoovBox => 0x6d_6f_6f_76,
-    // mvex, Mvex, MvexBox => 0x6d_76_65_78,
-    // mehd, Mehd, MehdBox => 0x6d_65_68_64,
-    // trex, Trex, TrexBox => 0x74_72_65_78,
-    // emsg, Emsg, EmsgBox => 0x65_6d_73_67,
-    // moof, Moof, MoofBox => 0x6d_6f_6f_66,
+    mvex, Mvex, MvexBox => 0x6d_76_65_78,
+    mehd, Mehd, MehdBox => 0x6d_65_68_64,
+    trex, Trex, TrexBox => 0x74_72_65_78,
+    emsg, Emsg, EmsgBox => 0x65_6d_73_67,
+    sidx, Sidx, SidxBox => 0x73_69_64_78,
+    mfra, Mfra, MfraBox => 0x6d_66_72_61,
+    tfra, Tfra, TfraBox => 0x74_66_72_61,
+    mfro, Mfro, MfroBox => 0x6d_66_72_6f,
+    moof, Moof, MoofBox => 0x6d_6f_6f_66,
     tkhd, Tkhd, TkhdBox => 0x74_6b_68_64,
-    // tfhd, Tfhd, TfhdBox => 0x74_66_68_64,
-    // tfdt, Tfdt, TfdtBox => 0x74_66_64_74,
+    tfhd, Tfhd, TfhdBox => 0x74_66_68_64,
+    tfdt, Tfdt, TfdtBox => 0x74_66_64_74,
     edts, Edts, EdtsBox => 0x65_64_74_73,
     mdia, Mdia, MdiaBox => 0x6d_64_69_61,
-    // elst, Elst, ElstBox => 0x65_6c_73_74,
+    elst, Elst, ElstBox => 0x65_6c_73_74,
     mdhd, Mdhd, MdhdBox => 0x6d_64_68_64,
     hdlr, Hdlr, HdlrBox => 0x68_64_6c_72,
     minf, Minf, MinfBox => 0x6d_69_6e_66,
@@ -183,12 +206,12 @@ boxdef! {
     stsc, Stsc, StscBox => 0x73_74_73_63,
     stsz, Stsz, StszBox => 0x73_74_73_7A,
     stco, Stco, StcoBox => 0x73_74_63_6F,
-    // co64, Co64, Co64Box => 0x63_6F_36_34,
+    co64, Co64, Co64Box => 0x63_6F_36_34,
     trak, Trak, TrakBox => 0x74_72_61_6b,
-    // traf, Traf, TrafBox => 0x74_72_61_66,
-    // trun, Trun, TrunBox => 0x74_72_75_6E,
-    // udta, Udta, UdtaBox => 0x75_64_74_61,
-    // meta, Meta, MetaBox => 0x6d_65_74_61,
+    traf, Traf, TrafBox => 0x74_72_61_66,
+    trun, Trun, TrunBox => 0x74_72_75_6E,
+    udta, Udta, UdtaBox => 0x75_64_74_61,
+    meta, Meta, MetaBox => 0x6d_65_74_61,
     dinf, Dinf, DinfBox => 0x64_69_6e_66,
     dref, Dref, DrefBox => 0x64_72_65_66,
     url, Url, UrlBox => 0x75_72_6c_20,
@@ -198,22 +221,44 @@ boxdef! {
     avc1, Avc1, Avc1Box => 0x61_76_63_31,
     avc1, AvcC, AvcCBox => 0x61_76_63_43,
     pasp, Pasp, PaspBox => 0x70_61_73_70,
-    // hev1, Hev1, Hev1Box => 0x68_65_76_31,
-    // hvcc, HvcC, HvcCBox => 0x68_76_63_43,
-    // mp4a, Mp4a, Mp4aBox => 0x6d_70_34_61,
-    // esds, Esds, EsdsBox => 0x65_73_64_73,
-    // tx3g, Tx3g, Tx3gBox => 0x74_78_33_67,
-    // vpcc, Vpcc, VpccBox => 0x76_70_63_43,
-    // vp09, Vp09, Vp09Box => 0x76_70_30_39,
-    // data, Data, DataBox => 0x64_61_74_61,
-    // ilst, Ilst, IlstBox => 0x69_6c_73_74,
-    // name, Name, NameBox => 0xa9_6e_61_6d,
-    // day, Day, DayBox => 0xa9_64_61_79,
-    // covr, Covr, CovrBox => 0x63_6f_76_72,
-    // desc, Desc, DescBox => 0x64_65_73_63,
+    av01, Av01, Av01Box => 0x61_76_30_31,
+    av01, Av1C, Av1CBox => 0x61_76_31_43,
+    hev1, Hev1, Hev1Box => 0x68_65_76_31,
+    hev1, Hvc1, Hvc1Box => 0x68_76_63_31,
+    hev1, HvcC, HvcCBox => 0x68_76_63_43,
+    mp4a, Mp4a, Mp4aBox => 0x6d_70_34_61,
+    mp4a, Esds, EsdsBox => 0x65_73_64_73,
+    tx3g, Tx3g, Tx3gBox => 0x74_78_33_67,
+    tx3g, Ftab, FtabBox => 0x66_74_61_62,
+    vpcc, Vpcc, VpccBox => 0x76_70_63_43,
+    vp09, Vp09, Vp09Box => 0x76_70_30_39,
+    data, Data, DataBox => 0x64_61_74_61,
+    ilst, Ilst, IlstBox => 0x69_6c_73_74,
     // wide, Wide, WideBox => 0x77_69_64_65,
 }
 
+pub use av01::Av1SequenceHeader;
+pub use avc1::NalUnit;
+pub use ctts::CttsEntry;
+pub use elst::ElstEntry;
+pub use emsg::{BreakDuration, SpliceCommand, SpliceDescriptor, SpliceInfoSection, SpliceInsert};
+pub use hev1::HvcCArray;
+pub use ilst::IlstItem;
+pub use mp4a::{
+    AudioSpecificConfig, DecoderConfigDescriptor, DecoderSpecificInfo, EsDescriptor,
+    SlConfigDescriptor,
+};
+pub use sidx::{SidxRange, SidxReference};
+pub use stsc::StscEntry;
+pub use stts::SttsEntry;
+pub use tfra::TfraEntry;
+pub use tkhd::Matrix;
+pub use trun::TrunEntry;
+pub use tx3g::{
+    BoxRecord, FontRecord, KaraokeEntry, StyleRecord, TextHighlight, TextKaraoke, TextSample,
+};
+pub use unknown::UnknownBox;
+
 impl fmt::Debug for BoxType {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         fmt::Display::fmt(&self, f)
@@ -254,11 +299,30 @@ pub struct BoxHeader {
     pub typ: BoxType,
     pub size: u64,
     pub offset: u64,
+    // the size is given as a 64-bit largesize, even if it fits in 32 bits
+    pub largesize: bool,
+    // the size is given as 0, the box extends to the end of its container
+    pub to_end: bool,
 }
 
 impl BoxHeader {
     pub fn new(typ: BoxType, size: u64, offset: u64) -> BoxHeader {
-        BoxHeader { typ, size, offset }
+        BoxHeader {
+            typ,
+            size,
+            offset,
+            largesize: false,
+            to_end: false,
+        }
+    }
+
+    // the length of the header itself
+    pub fn header_size(&self) -> u64 {
+        if self.largesize || self.size > u32::MAX as u64 {
+            HEADER_SIZE_LARGE
+        } else {
+            HEADER_SIZE
+        }
     }
 
     pub fn box_start(&self) -> u64 {
@@ -294,12 +358,16 @@ pub fn read_box_header<R: Read>(reader: &mut R, offset: u64) -> Result<BoxHeader
                 16..=u64::MAX => largesize,
             },
             offset,
+            largesize: true,
+            to_end: largesize == 0,
         })
     } else {
         Ok(BoxHeader {
             typ: BoxType::from(typ),
             size: size as u64,
             offset,
+            largesize: false,
+            to_end: size == 0,
         })
     }
 }
@@ -312,13 +380,14 @@ pub fn read_box_header_ext<R: Read>(reader: &mut R) -> Result<(u8, u32)> {
 
 #[allow(dead_code)]
 pub fn write_box_header<W: Write>(header: &BoxHeader, writer: &mut W) -> Result<u64> {
-    if header.size > u32::MAX as u64 {
+    let size = if header.to_end { 0 } else { header.size };
+    if header.header_size() == HEADER_SIZE_LARGE {
         writer.write_u32::<BigEndian>(1)?;
         writer.write_u32::<BigEndian>(header.typ.into())?;
-        writer.write_u64::<BigEndian>(header.size)?;
+        writer.write_u64::<BigEndian>(size)?;
         Ok(HEADER_SIZE_LARGE)
     } else {
-        writer.write_u32::<BigEndian>(header.size as u32)?;
+        writer.write_u32::<BigEndian>(size as u32)?;
         writer.write_u32::<BigEndian>(header.typ.into())?;
         Ok(HEADER_SIZE)
     }
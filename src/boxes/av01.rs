@@ -0,0 +1,460 @@
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use serde::Serialize;
+use std::io::{Read, Seek, Write};
+
+use super::avc1::visual_sample_entry;
+use super::{BoxHeader, BoxType, Ibox, ReadBox, WriteBox, HEADER_SIZE};
+use crate::bitreader::BitReader;
+use crate::error::Error;
+use crate::types::FixedPointU16;
+
+type Result<T> = std::result::Result<T, Error>;
+
+visual_sample_entry!(Av01Box, Av01);
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Av1CBox {
+    pub version: u8,
+    pub seq_profile: u8,
+    pub seq_level_idx_0: u8,
+    pub seq_tier_0: bool,
+    pub high_bitdepth: bool,
+    pub twelve_bit: bool,
+    pub monochrome: bool,
+    pub chroma_subsampling_x: bool,
+    pub chroma_subsampling_y: bool,
+    pub chroma_sample_position: u8,
+    pub initial_presentation_delay_minus_one: Option<u8>,
+    pub config_obus: Vec<u8>,
+}
+
+impl Av1CBox {
+    pub const OBU_SEQUENCE_HEADER: u8 = 1;
+
+    pub fn bit_depth(&self) -> u8 {
+        match (self.high_bitdepth, self.twelve_bit) {
+            (true, true) => 12,
+            (true, false) => 10,
+            _ => 8,
+        }
+    }
+
+    // the sequence header OBU carried in configOBUs, if any
+    pub fn sequence_header(&self) -> Result<Option<Av1SequenceHeader>> {
+        let mut data = &self.config_obus[..];
+        while !data.is_empty() {
+            let header = data[0];
+            let obu_type = (header >> 3) & 0xF;
+            let extension_flag = (header >> 2) & 0x1 == 0x1;
+            let has_size_field = (header >> 1) & 0x1 == 0x1;
+
+            let mut pos = if extension_flag { 2 } else { 1 };
+            let obu_size = if has_size_field {
+                let (size, len) = read_leb128(data.get(pos..).unwrap_or_default())?;
+                pos += len;
+                size as usize
+            } else {
+                data.len().saturating_sub(pos)
+            };
+            let payload = data
+                .get(pos..pos + obu_size)
+                .ok_or(Error::InvalidData("OBU is truncated"))?;
+
+            if obu_type == Self::OBU_SEQUENCE_HEADER {
+                return Ok(Some(Av1SequenceHeader::read(payload)?));
+            }
+            data = &data[pos + obu_size..];
+        }
+        Ok(None)
+    }
+}
+
+impl Ibox for Av1CBox {
+    fn typ(&self) -> BoxType {
+        BoxType::Av1C
+    }
+
+    fn data_size(&self) -> u64 {
+        4 + self.config_obus.len() as u64
+    }
+
+    fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(self).unwrap())
+    }
+
+    fn summary(&self) -> Result<String> {
+        let s = format!(
+            "seq_profile={} seq_level_idx_0={} seq_tier_0={} bit_depth={} monochrome={}",
+            self.seq_profile,
+            self.seq_level_idx_0,
+            self.seq_tier_0,
+            self.bit_depth(),
+            self.monochrome
+        );
+        Ok(s)
+    }
+}
+
+impl<R: Read + Seek> ReadBox<&mut R> for Av1CBox {
+    fn read(reader: &mut R, header: &BoxHeader) -> Result<Self> {
+        let params = reader.read_u8()?;
+        if params >> 7 != 0x1 {
+            return Err(Error::InvalidData("av1C marker must be 1"));
+        }
+        let version = params & 0x7F;
+        if version != 1 {
+            return Err(Error::UnsupportedBoxVersion(BoxType::Av1C, version));
+        }
+
+        let params = reader.read_u8()?;
+        let seq_profile = params >> 5;
+        let seq_level_idx_0 = params & 0x1F;
+
+        let params = reader.read_u8()?;
+        let seq_tier_0 = (params >> 7) & 0x1 == 0x1;
+        let high_bitdepth = (params >> 6) & 0x1 == 0x1;
+        let twelve_bit = (params >> 5) & 0x1 == 0x1;
+        let monochrome = (params >> 4) & 0x1 == 0x1;
+        let chroma_subsampling_x = (params >> 3) & 0x1 == 0x1;
+        let chroma_subsampling_y = (params >> 2) & 0x1 == 0x1;
+        let chroma_sample_position = params & 0x3;
+
+        let params = reader.read_u8()?;
+        let initial_presentation_delay_minus_one = if (params >> 4) & 0x1 == 0x1 {
+            Some(params & 0xF)
+        } else {
+            None
+        };
+
+        let config_obus_size = header
+            .size
+            .checked_sub(HEADER_SIZE + 4)
+            .ok_or(Error::InvalidData("box size too small"))?;
+        let mut config_obus = vec![0u8; config_obus_size as usize];
+        reader.read_exact(&mut config_obus)?;
+
+        Ok(Av1CBox {
+            version,
+            seq_profile,
+            seq_level_idx_0,
+            seq_tier_0,
+            high_bitdepth,
+            twelve_bit,
+            monochrome,
+            chroma_subsampling_x,
+            chroma_subsampling_y,
+            chroma_sample_position,
+            initial_presentation_delay_minus_one,
+            config_obus,
+        })
+    }
+}
+
+impl<W: Write> WriteBox<&mut W> for Av1CBox {
+    fn write(&self, writer: &mut W, _: u64) -> Result<u64> {
+        writer.write_u8(0x80 | self.version)?;
+        writer.write_u8((self.seq_profile << 5) | (self.seq_level_idx_0 & 0x1F))?;
+        writer.write_u8(
+            ((self.seq_tier_0 as u8) << 7)
+                | ((self.high_bitdepth as u8) << 6)
+                | ((self.twelve_bit as u8) << 5)
+                | ((self.monochrome as u8) << 4)
+                | ((self.chroma_subsampling_x as u8) << 3)
+                | ((self.chroma_subsampling_y as u8) << 2)
+                | (self.chroma_sample_position & 0x3),
+        )?;
+        match self.initial_presentation_delay_minus_one {
+            Some(delay) => writer.write_u8(0x10 | (delay & 0xF))?,
+            None => writer.write_u8(0)?,
+        }
+        writer.write_all(&self.config_obus)?;
+
+        Ok(self.data_size())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Av1SequenceHeader {
+    pub seq_profile: u8,
+    pub still_picture: bool,
+    pub reduced_still_picture_header: bool,
+    pub seq_level_idx_0: u8,
+    pub seq_tier_0: bool,
+    pub max_frame_width: u32,
+    pub max_frame_height: u32,
+    pub bit_depth: u8,
+    pub mono_chrome: bool,
+    pub color_primaries: u8,
+    pub transfer_characteristics: u8,
+    pub matrix_coefficients: u8,
+    pub color_range: bool,
+    pub subsampling_x: bool,
+    pub subsampling_y: bool,
+    pub chroma_sample_position: u8,
+    pub film_grain_params_present: bool,
+}
+
+impl Av1SequenceHeader {
+    const SELECT_SCREEN_CONTENT_TOOLS: u64 = 2;
+
+    const CP_BT_709: u8 = 1;
+    const CP_UNSPECIFIED: u8 = 2;
+    const TC_UNSPECIFIED: u8 = 2;
+    const TC_SRGB: u8 = 13;
+    const MC_IDENTITY: u8 = 0;
+    const MC_UNSPECIFIED: u8 = 2;
+
+    // sequence_header_obu() of the AV1 specification, section 5.5
+    pub fn read(payload: &[u8]) -> Result<Self> {
+        let mut reader = BitReader::new(payload);
+
+        let seq_profile = reader.read_bits(3)? as u8;
+        let still_picture = reader.read_bit()?;
+        let reduced_still_picture_header = reader.read_bit()?;
+
+        let seq_level_idx_0;
+        let mut seq_tier_0 = false;
+        if reduced_still_picture_header {
+            seq_level_idx_0 = reader.read_bits(5)? as u8;
+        } else {
+            let mut buffer_delay_length = 0;
+            let timing_info_present_flag = reader.read_bit()?;
+            let mut decoder_model_info_present_flag = false;
+            if timing_info_present_flag {
+                reader.skip_bits(32)?; // num_units_in_display_tick
+                reader.skip_bits(32)?; // time_scale
+                if reader.read_bit()? {
+                    read_uvlc(&mut reader)?; // num_ticks_per_picture_minus_1
+                }
+                decoder_model_info_present_flag = reader.read_bit()?;
+                if decoder_model_info_present_flag {
+                    buffer_delay_length = reader.read_bits(5)? as u32 + 1;
+                    reader.skip_bits(32)?; // num_units_in_decoding_tick
+                    reader.skip_bits(5)?; // buffer_removal_time_length_minus_1
+                    reader.skip_bits(5)?; // frame_presentation_time_length_minus_1
+                }
+            }
+            let initial_display_delay_present_flag = reader.read_bit()?;
+
+            let operating_points_cnt = reader.read_bits(5)? + 1;
+            let mut levels = Vec::with_capacity(operating_points_cnt as usize);
+            for _ in 0..operating_points_cnt {
+                reader.skip_bits(12)?; // operating_point_idc
+                let seq_level_idx = reader.read_bits(5)? as u8;
+                let seq_tier = seq_level_idx > 7 && reader.read_bit()?;
+                levels.push((seq_level_idx, seq_tier));
+
+                if decoder_model_info_present_flag && reader.read_bit()? {
+                    reader.read_bits(buffer_delay_length)?; // decoder_buffer_delay
+                    reader.read_bits(buffer_delay_length)?; // encoder_buffer_delay
+                    reader.skip_bits(1)?; // low_delay_mode_flag
+                }
+                if initial_display_delay_present_flag && reader.read_bit()? {
+                    reader.skip_bits(4)?; // initial_display_delay_minus_1
+                }
+            }
+            (seq_level_idx_0, seq_tier_0) = levels[0];
+        }
+
+        let frame_width_bits = reader.read_bits(4)? as u32 + 1;
+        let frame_height_bits = reader.read_bits(4)? as u32 + 1;
+        let max_frame_width = reader.read_bits(frame_width_bits)? as u32 + 1;
+        let max_frame_height = reader.read_bits(frame_height_bits)? as u32 + 1;
+
+        if !reduced_still_picture_header && reader.read_bit()? {
+            reader.skip_bits(4)?; // delta_frame_id_length_minus_2
+            reader.skip_bits(3)?; // additional_frame_id_length_minus_1
+        }
+        reader.skip_bits(1)?; // use_128x128_superblock
+        reader.skip_bits(1)?; // enable_filter_intra
+        reader.skip_bits(1)?; // enable_intra_edge_filter
+
+        if !reduced_still_picture_header {
+            reader.skip_bits(1)?; // enable_interintra_compound
+            reader.skip_bits(1)?; // enable_masked_compound
+            reader.skip_bits(1)?; // enable_warped_motion
+            reader.skip_bits(1)?; // enable_dual_filter
+            let enable_order_hint = reader.read_bit()?;
+            if enable_order_hint {
+                reader.skip_bits(1)?; // enable_jnt_comp
+                reader.skip_bits(1)?; // enable_ref_frame_mvs
+            }
+            let seq_force_screen_content_tools = if reader.read_bit()? {
+                Self::SELECT_SCREEN_CONTENT_TOOLS
+            } else {
+                reader.read_bits(1)?
+            };
+            if seq_force_screen_content_tools > 0 && !reader.read_bit()? {
+                reader.skip_bits(1)?; // seq_force_integer_mv
+            }
+            if enable_order_hint {
+                reader.skip_bits(3)?; // order_hint_bits_minus_1
+            }
+        }
+        reader.skip_bits(1)?; // enable_superres
+        reader.skip_bits(1)?; // enable_cdef
+        reader.skip_bits(1)?; // enable_restoration
+
+        // color_config()
+        let high_bitdepth = reader.read_bit()?;
+        let bit_depth = if seq_profile == 2 && high_bitdepth {
+            if reader.read_bit()? {
+                12
+            } else {
+                10
+            }
+        } else if high_bitdepth {
+            10
+        } else {
+            8
+        };
+        let mono_chrome = seq_profile != 1 && reader.read_bit()?;
+
+        let (color_primaries, transfer_characteristics, matrix_coefficients) =
+            if reader.read_bit()? {
+                (
+                    reader.read_bits(8)? as u8,
+                    reader.read_bits(8)? as u8,
+                    reader.read_bits(8)? as u8,
+                )
+            } else {
+                (
+                    Self::CP_UNSPECIFIED,
+                    Self::TC_UNSPECIFIED,
+                    Self::MC_UNSPECIFIED,
+                )
+            };
+
+        let color_range;
+        let mut subsampling_x = true;
+        let mut subsampling_y = true;
+        let mut chroma_sample_position = 0;
+        if mono_chrome {
+            color_range = reader.read_bit()?;
+        } else {
+            if color_primaries == Self::CP_BT_709
+                && transfer_characteristics == Self::TC_SRGB
+                && matrix_coefficients == Self::MC_IDENTITY
+            {
+                color_range = true;
+                subsampling_x = false;
+                subsampling_y = false;
+            } else {
+                color_range = reader.read_bit()?;
+                match seq_profile {
+                    0 => (),
+                    1 => {
+                        subsampling_x = false;
+                        subsampling_y = false;
+                    }
+                    _ => {
+                        if bit_depth == 12 {
+                            subsampling_x = reader.read_bit()?;
+                            subsampling_y = subsampling_x && reader.read_bit()?;
+                        } else {
+                            subsampling_y = false;
+                        }
+                    }
+                }
+                if subsampling_x && subsampling_y {
+                    chroma_sample_position = reader.read_bits(2)? as u8;
+                }
+            }
+            reader.skip_bits(1)?; // separate_uv_delta_q
+        }
+        let film_grain_params_present = reader.read_bit()?;
+
+        Ok(Av1SequenceHeader {
+            seq_profile,
+            still_picture,
+            reduced_still_picture_header,
+            seq_level_idx_0,
+            seq_tier_0,
+            max_frame_width,
+            max_frame_height,
+            bit_depth,
+            mono_chrome,
+            color_primaries,
+            transfer_characteristics,
+            matrix_coefficients,
+            color_range,
+            subsampling_x,
+            subsampling_y,
+            chroma_sample_position,
+            film_grain_params_present,
+        })
+    }
+}
+
+fn read_leb128(data: &[u8]) -> Result<(u64, usize)> {
+    let mut value = 0u64;
+    for (i, b) in data.iter().take(8).enumerate() {
+        value |= ((b & 0x7F) as u64) << (i * 7);
+        if b & 0x80 == 0 {
+            return Ok((value, i + 1));
+        }
+    }
+    Err(Error::InvalidData("invalid leb128 value"))
+}
+
+fn read_uvlc(reader: &mut BitReader) -> Result<u64> {
+    let mut leading_zeros = 0;
+    while !reader.read_bit()? {
+        leading_zeros += 1;
+    }
+    if leading_zeros >= 32 {
+        return Ok(u32::MAX as u64);
+    }
+    Ok(reader.read_bits(leading_zeros)? + (1 << leading_zeros) - 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    // av1C of a 960x540 8-bit 4:2:0 stream at level 3.0, followed by its
+    // sequence header OBU with obu_has_size_field set
+    const AV1C: [u8; 17] = [
+        0x81, 0x04, 0x0C, 0x00, 0x0A, 0x0B, 0x00, 0x00, 0x00, 0x24, 0xCF, 0x7F, 0x0D, 0xBF, 0xFF,
+        0x30, 0x08,
+    ];
+
+    #[test]
+    fn av1c_sequence_header() {
+        let header = BoxHeader::new(BoxType::Av1C, HEADER_SIZE + AV1C.len() as u64, 0);
+        let av1c = Av1CBox::read(&mut Cursor::new(&AV1C[..]), &header).unwrap();
+        assert_eq!(av1c.seq_profile, 0);
+        assert_eq!(av1c.seq_level_idx_0, 4);
+        assert_eq!(av1c.bit_depth(), 8);
+        assert!(av1c.chroma_subsampling_x && av1c.chroma_subsampling_y);
+
+        let sh = av1c.sequence_header().unwrap().unwrap();
+        assert_eq!(
+            sh,
+            Av1SequenceHeader {
+                seq_profile: 0,
+                still_picture: false,
+                reduced_still_picture_header: false,
+                seq_level_idx_0: 4,
+                seq_tier_0: false,
+                max_frame_width: 960,
+                max_frame_height: 540,
+                bit_depth: 8,
+                mono_chrome: false,
+                color_primaries: Av1SequenceHeader::CP_UNSPECIFIED,
+                transfer_characteristics: Av1SequenceHeader::TC_UNSPECIFIED,
+                matrix_coefficients: Av1SequenceHeader::MC_UNSPECIFIED,
+                color_range: false,
+                subsampling_x: true,
+                subsampling_y: true,
+                chroma_sample_position: 0,
+                film_grain_params_present: false,
+            }
+        );
+
+        let mut buf = Vec::new();
+        av1c.write(&mut buf, 0).unwrap();
+        assert_eq!(buf, AV1C);
+    }
+}
@@ -1,82 +1,117 @@
-use byteorder::{BigEndian, ReadBytesExt};
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
 use serde::Serialize;
 use std::io::{Read, Seek, Write};
 
-use super::{BoxHeader, BoxType, Ibox, ReadBox, WriteBox};
+use super::{BoxHeader, BoxType, Ibox, ReadBox, WriteBox, HEADER_SIZE};
 use crate::error::Error;
 use crate::types::FixedPointU16;
 
 type Result<T> = std::result::Result<T, Error>;
 
-#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
-pub struct Avc1Box {
-    pub data_reference_index: u16,
-    pub width: u16,
-    pub height: u16,
-    pub horizresolution: FixedPointU16,
-    pub vertresolution: FixedPointU16,
-    pub frame_count: u16,
-    pub depth: u16,
-}
-
-impl Ibox for Avc1Box {
-    fn typ(&self) -> BoxType {
-        BoxType::Avc1
-    }
+// VisualSampleEntry layout shared by avc1, hev1, hvc1, vp09 and av01
+macro_rules! visual_sample_entry {
+    ( $box:ident, $field:ident ) => {
+        #[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+        pub struct $box {
+            pub data_reference_index: u16,
+            pub width: u16,
+            pub height: u16,
+            pub horizresolution: FixedPointU16,
+            pub vertresolution: FixedPointU16,
+            pub frame_count: u16,
+            pub compressorname: [u8; 32],
+            pub depth: u16,
+            // QuickTime version, revision, vendor and qualities, zero in ISO files
+            pub pre_defined: [u8; 16],
+            // QuickTime data size, zero in ISO files
+            pub reserved: u32,
+            // QuickTime color table id, -1 in ISO files
+            pub color_table_id: i16,
+        }
 
-    fn data_size(&self) -> u64 {
-        8 + 70
-    }
+        impl Ibox for $box {
+            fn typ(&self) -> BoxType {
+                BoxType::$field
+            }
+
+            fn data_size(&self) -> u64 {
+                8 + 70
+            }
+
+            fn to_json(&self) -> Result<String> {
+                Ok(serde_json::to_string(self).unwrap())
+            }
+
+            fn summary(&self) -> Result<String> {
+                let s = format!(
+                    "data_reference_index={} width={} height={} frame_count={}",
+                    self.data_reference_index, self.width, self.height, self.frame_count
+                );
+                Ok(s)
+            }
+        }
 
-    fn to_json(&self) -> Result<String> {
-        Ok(serde_json::to_string(self).unwrap())
-    }
+        impl<R: Read + Seek> ReadBox<&mut R> for $box {
+            fn read(reader: &mut R, _: &BoxHeader) -> Result<Self> {
+                reader.read_u32::<BigEndian>()?; // reserved
+                reader.read_u16::<BigEndian>()?; // reserved
+                let data_reference_index = reader.read_u16::<BigEndian>()?;
+
+                let mut pre_defined = [0u8; 16];
+                reader.read_exact(&mut pre_defined)?;
+                let width = reader.read_u16::<BigEndian>()?;
+                let height = reader.read_u16::<BigEndian>()?;
+                let horizresolution = FixedPointU16::new_raw(reader.read_u32::<BigEndian>()?);
+                let vertresolution = FixedPointU16::new_raw(reader.read_u32::<BigEndian>()?);
+                let reserved = reader.read_u32::<BigEndian>()?;
+                let frame_count = reader.read_u16::<BigEndian>()?;
+                let mut compressorname = [0u8; 32];
+                reader.read_exact(&mut compressorname)?;
+                let depth = reader.read_u16::<BigEndian>()?;
+                let color_table_id = reader.read_i16::<BigEndian>()?;
+
+                Ok($box {
+                    data_reference_index,
+                    width,
+                    height,
+                    horizresolution,
+                    vertresolution,
+                    frame_count,
+                    compressorname,
+                    depth,
+                    pre_defined,
+                    reserved,
+                    color_table_id,
+                })
+            }
+        }
 
-    fn summary(&self) -> Result<String> {
-        let s = format!(
-            "data_reference_index={} width={} height={} frame_count={}",
-            self.data_reference_index, self.width, self.height, self.frame_count
-        );
-        Ok(s)
-    }
+        impl<W: Write> WriteBox<&mut W> for $box {
+            fn write(&self, writer: &mut W, _: u64) -> Result<u64> {
+                writer.write_u32::<BigEndian>(0)?; // reserved
+                writer.write_u16::<BigEndian>(0)?; // reserved
+                writer.write_u16::<BigEndian>(self.data_reference_index)?;
+
+                writer.write_all(&self.pre_defined)?;
+                writer.write_u16::<BigEndian>(self.width)?;
+                writer.write_u16::<BigEndian>(self.height)?;
+                writer.write_u32::<BigEndian>(self.horizresolution.raw_value())?;
+                writer.write_u32::<BigEndian>(self.vertresolution.raw_value())?;
+                writer.write_u32::<BigEndian>(self.reserved)?;
+                writer.write_u16::<BigEndian>(self.frame_count)?;
+                writer.write_all(&self.compressorname)?;
+                writer.write_u16::<BigEndian>(self.depth)?;
+                writer.write_i16::<BigEndian>(self.color_table_id)?;
+
+                Ok(self.data_size())
+            }
+        }
+    };
 }
 
-impl<R: Read + Seek> ReadBox<&mut R> for Avc1Box {
-    fn read(reader: &mut R, _: &BoxHeader) -> Result<Self> {
-        reader.read_u32::<BigEndian>()?; // reserved
-        reader.read_u16::<BigEndian>()?; // reserved
-        let data_reference_index = reader.read_u16::<BigEndian>()?;
-
-        reader.read_u32::<BigEndian>()?; // pre-defined, reserved
-        reader.read_u64::<BigEndian>()?; // pre-defined
-        reader.read_u32::<BigEndian>()?; // pre-defined
-        let width = reader.read_u16::<BigEndian>()?;
-        let height = reader.read_u16::<BigEndian>()?;
-        let horizresolution = FixedPointU16::new_raw(reader.read_u32::<BigEndian>()?);
-        let vertresolution = FixedPointU16::new_raw(reader.read_u32::<BigEndian>()?);
-        reader.read_u32::<BigEndian>()?; // reserved
-        let frame_count = reader.read_u16::<BigEndian>()?;
-        super::rel_skip(reader, 32)?; // compressorname
-        let depth = reader.read_u16::<BigEndian>()?;
-        reader.read_i16::<BigEndian>()?; // pre-defined
-
-        Ok(Avc1Box {
-            data_reference_index,
-            width,
-            height,
-            horizresolution,
-            vertresolution,
-            frame_count,
-            depth,
-        })
-    }
-}
+pub(crate) use visual_sample_entry;
 
-impl<W: Write> WriteBox<&mut W> for Avc1Box {
-    fn write(&self, _: &mut W, _: u64) -> Result<u64> {
-        todo!();
-    }
-}
+visual_sample_entry!(Avc1Box, Avc1);
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize)]
 pub struct AvcCBox {
@@ -87,6 +122,7 @@ pub struct AvcCBox {
     pub length_size_minus_one: u8,
     pub sequence_parameter_sets: Vec<NalUnit>,
     pub picture_parameter_sets: Vec<NalUnit>,
+    pub ext: Vec<u8>,
 }
 
 impl Ibox for AvcCBox {
@@ -100,7 +136,7 @@ impl Ibox for AvcCBox {
                 .iter()
                 .fold(7, |acc, e| acc + e.size()),
             |acc, e| acc + e.size(),
-        )
+        ) + self.ext.len() as u64
     }
 
     fn to_json(&self) -> Result<String> {
@@ -117,7 +153,7 @@ impl Ibox for AvcCBox {
 }
 
 impl<R: Read + Seek> ReadBox<&mut R> for AvcCBox {
-    fn read(reader: &mut R, _: &BoxHeader) -> Result<Self> {
+    fn read(reader: &mut R, header: &BoxHeader) -> Result<Self> {
         let configuration_version = reader.read_u8()?;
         let avc_profile_indication = reader.read_u8()?;
         let profile_compatibility = reader.read_u8()?;
@@ -136,6 +172,15 @@ impl<R: Read + Seek> ReadBox<&mut R> for AvcCBox {
             picture_parameter_sets.push(nal_unit);
         }
 
+        // profile specific extensions (chroma_format, bit_depth, sps_ext) are kept as is
+        let ext_size = header
+            .size
+            .saturating_sub(HEADER_SIZE + 7)
+            .saturating_sub(sequence_parameter_sets.iter().map(|e| e.size()).sum())
+            .saturating_sub(picture_parameter_sets.iter().map(|e| e.size()).sum());
+        let mut ext = vec![0u8; ext_size as usize];
+        reader.read_exact(&mut ext)?;
+
         Ok(AvcCBox {
             configuration_version,
             avc_profile_indication,
@@ -144,13 +189,29 @@ impl<R: Read + Seek> ReadBox<&mut R> for AvcCBox {
             length_size_minus_one,
             sequence_parameter_sets,
             picture_parameter_sets,
+            ext,
         })
     }
 }
 
 impl<W: Write> WriteBox<&mut W> for AvcCBox {
-    fn write(&self, _: &mut W, _: u64) -> Result<u64> {
-        todo!();
+    fn write(&self, writer: &mut W, _: u64) -> Result<u64> {
+        writer.write_u8(self.configuration_version)?;
+        writer.write_u8(self.avc_profile_indication)?;
+        writer.write_u8(self.profile_compatibility)?;
+        writer.write_u8(self.avc_level_indication)?;
+        writer.write_u8(0xFC | (self.length_size_minus_one & 0x3))?;
+        writer.write_u8(0xE0 | (self.sequence_parameter_sets.len() as u8 & 0x1F))?;
+        for nal_unit in self.sequence_parameter_sets.iter() {
+            nal_unit.write(writer)?;
+        }
+        writer.write_u8(self.picture_parameter_sets.len() as u8)?;
+        for nal_unit in self.picture_parameter_sets.iter() {
+            nal_unit.write(writer)?;
+        }
+        writer.write_all(&self.ext)?;
+
+        Ok(self.data_size())
     }
 }
 
@@ -160,14 +221,20 @@ pub struct NalUnit {
 }
 
 impl NalUnit {
-    fn size(&self) -> u64 {
+    pub(crate) fn size(&self) -> u64 {
         2 + self.bytes.len() as u64
     }
 
-    fn read<R: Read + Seek>(reader: &mut R) -> Result<Self> {
+    pub(crate) fn read<R: Read + Seek>(reader: &mut R) -> Result<Self> {
         let length = reader.read_u16::<BigEndian>()? as usize;
         let mut bytes = vec![0u8; length];
         reader.read_exact(&mut bytes)?;
         Ok(NalUnit { bytes })
     }
+
+    pub(crate) fn write<W: Write>(&self, writer: &mut W) -> Result<u64> {
+        writer.write_u16::<BigEndian>(self.bytes.len() as u16)?;
+        writer.write_all(&self.bytes)?;
+        Ok(self.size())
+    }
 }
@@ -0,0 +1,70 @@
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use serde::Serialize;
+use std::io::{Read, Seek, Write};
+
+use super::{BoxHeader, BoxType, Ibox, ReadBox, WriteBox, HEADER_SIZE};
+use crate::error::Error;
+
+type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Co64Box {
+    pub version: u8,
+    pub flags: u32,
+    pub chunk_offsets: Vec<u64>,
+}
+
+impl Ibox for Co64Box {
+    fn typ(&self) -> BoxType {
+        BoxType::Co64
+    }
+
+    fn header_size(&self) -> u64 {
+        HEADER_SIZE + 4
+    }
+
+    fn data_size(&self) -> u64 {
+        4 + (8 * self.chunk_offsets.len()) as u64
+    }
+
+    fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(self).unwrap())
+    }
+
+    fn summary(&self) -> Result<String> {
+        let s = format!("chunk_offsets={}", self.chunk_offsets.len());
+        Ok(s)
+    }
+}
+
+impl<R: Read + Seek> ReadBox<&mut R> for Co64Box {
+    fn read(reader: &mut R, _: &BoxHeader) -> Result<Self> {
+        let (version, flags) = super::read_box_header_ext(reader)?;
+
+        let entry_count = reader.read_u32::<BigEndian>()?;
+        let mut chunk_offsets = Vec::with_capacity(entry_count as usize);
+        for _ in 0..entry_count {
+            let chunk_offset = reader.read_u64::<BigEndian>()?;
+            chunk_offsets.push(chunk_offset);
+        }
+
+        Ok(Co64Box {
+            version,
+            flags,
+            chunk_offsets,
+        })
+    }
+}
+
+impl<W: Write> WriteBox<&mut W> for Co64Box {
+    fn write(&self, writer: &mut W, _: u64) -> Result<u64> {
+        super::write_box_header_ext(writer, self.version, self.flags)?;
+
+        writer.write_u32::<BigEndian>(self.chunk_offsets.len() as u32)?;
+        for chunk_offset in self.chunk_offsets.iter() {
+            writer.write_u64::<BigEndian>(*chunk_offset)?;
+        }
+
+        Ok(self.header_size() - HEADER_SIZE + self.data_size())
+    }
+}
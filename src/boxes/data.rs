@@ -0,0 +1,141 @@
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use serde::Serialize;
+use std::io::{Read, Seek, Write};
+
+use super::{BoxHeader, BoxType, Ibox, ReadBox, WriteBox, HEADER_SIZE};
+use crate::error::Error;
+
+type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DataBox {
+    pub data_type: u32,
+    pub locale: u32,
+    pub value: Vec<u8>,
+}
+
+impl DataBox {
+    // well-known types of the iTunes metadata data atom
+    pub const TYPE_BINARY: u32 = 0;
+    pub const TYPE_UTF8: u32 = 1;
+    pub const TYPE_UTF16: u32 = 2;
+    pub const TYPE_JPEG: u32 = 13;
+    pub const TYPE_PNG: u32 = 14;
+    pub const TYPE_BE_SIGNED: u32 = 21;
+    pub const TYPE_BE_UNSIGNED: u32 = 22;
+    pub const TYPE_BMP: u32 = 27;
+
+    pub fn new(data_type: u32, value: Vec<u8>) -> Self {
+        DataBox {
+            data_type,
+            locale: 0,
+            value,
+        }
+    }
+
+    pub fn utf8(value: &str) -> Self {
+        Self::new(Self::TYPE_UTF8, value.as_bytes().to_vec())
+    }
+
+    // a big-endian signed integer of the given width in bytes
+    pub fn signed(value: i64, width: usize) -> Self {
+        let bytes = value.to_be_bytes();
+        Self::new(Self::TYPE_BE_SIGNED, bytes[8 - width.min(8)..].to_vec())
+    }
+
+    pub fn as_str(&self) -> Option<String> {
+        match self.data_type {
+            Self::TYPE_UTF8 => String::from_utf8(self.value.clone()).ok(),
+            Self::TYPE_UTF16 => {
+                let units: Vec<u16> = self
+                    .value
+                    .chunks_exact(2)
+                    .map(|c| u16::from_be_bytes([c[0], c[1]]))
+                    .collect();
+                String::from_utf16(&units).ok()
+            }
+            _ => None,
+        }
+    }
+
+    pub fn as_integer(&self) -> Option<i64> {
+        if self.value.is_empty() || self.value.len() > 8 {
+            return None;
+        }
+        match self.data_type {
+            Self::TYPE_BE_SIGNED => {
+                let fill = if self.value[0] & 0x80 != 0 { 0xFF } else { 0 };
+                let mut bytes = [fill; 8];
+                bytes[8 - self.value.len()..].copy_from_slice(&self.value);
+                Some(i64::from_be_bytes(bytes))
+            }
+            Self::TYPE_BE_UNSIGNED | Self::TYPE_BINARY => {
+                let mut bytes = [0u8; 8];
+                bytes[8 - self.value.len()..].copy_from_slice(&self.value);
+                Some(i64::from_be_bytes(bytes))
+            }
+            _ => None,
+        }
+    }
+
+    pub fn is_image(&self) -> bool {
+        matches!(
+            self.data_type,
+            Self::TYPE_JPEG | Self::TYPE_PNG | Self::TYPE_BMP
+        )
+    }
+}
+
+impl Ibox for DataBox {
+    fn typ(&self) -> BoxType {
+        BoxType::Data
+    }
+
+    fn data_size(&self) -> u64 {
+        8 + self.value.len() as u64
+    }
+
+    fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(self).unwrap())
+    }
+
+    fn summary(&self) -> Result<String> {
+        let s = format!(
+            "data_type={} locale={} value_size={}",
+            self.data_type,
+            self.locale,
+            self.value.len()
+        );
+        Ok(s)
+    }
+}
+
+impl<R: Read + Seek> ReadBox<&mut R> for DataBox {
+    fn read(reader: &mut R, header: &BoxHeader) -> Result<Self> {
+        let data_type = reader.read_u32::<BigEndian>()?;
+        let locale = reader.read_u32::<BigEndian>()?;
+
+        let value_size = header
+            .size
+            .checked_sub(HEADER_SIZE + 8)
+            .ok_or(Error::InvalidData("data size too small"))?;
+        let mut value = vec![0u8; value_size as usize];
+        reader.read_exact(&mut value)?;
+
+        Ok(DataBox {
+            data_type,
+            locale,
+            value,
+        })
+    }
+}
+
+impl<W: Write> WriteBox<&mut W> for DataBox {
+    fn write(&self, writer: &mut W, _: u64) -> Result<u64> {
+        writer.write_u32::<BigEndian>(self.data_type)?;
+        writer.write_u32::<BigEndian>(self.locale)?;
+        writer.write_all(&self.value)?;
+
+        Ok(self.data_size())
+    }
+}
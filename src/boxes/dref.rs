@@ -1,4 +1,4 @@
-use byteorder::{BigEndian, ReadBytesExt};
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
 use serde::Serialize;
 use std::io::{Read, Seek, Write};
 
@@ -14,6 +14,17 @@ pub struct DrefBox {
     entry_count: u32,
 }
 
+impl DrefBox {
+    // entries follow as children of the box
+    pub fn new(entry_count: u32) -> Self {
+        DrefBox {
+            version: 0,
+            flags: 0,
+            entry_count,
+        }
+    }
+}
+
 impl Ibox for DrefBox {
     fn typ(&self) -> BoxType {
         BoxType::Dref
@@ -52,7 +63,11 @@ impl<R: Read + Seek> ReadBox<&mut R> for DrefBox {
 }
 
 impl<W: Write> WriteBox<&mut W> for DrefBox {
-    fn write(&self, _: &mut W, _: u64) -> Result<u64> {
-        todo!();
+    fn write(&self, writer: &mut W, _: u64) -> Result<u64> {
+        super::write_box_header_ext(writer, self.version, self.flags)?;
+
+        writer.write_u32::<BigEndian>(self.entry_count)?;
+
+        Ok(self.header_size() - HEADER_SIZE + self.data_size())
     }
 }
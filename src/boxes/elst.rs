@@ -0,0 +1,117 @@
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use serde::Serialize;
+use std::io::{Read, Seek, Write};
+
+use super::{BoxHeader, BoxType, Ibox, ReadBox, WriteBox, HEADER_SIZE};
+use crate::error::Error;
+
+type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ElstBox {
+    pub version: u8,
+    pub flags: u32,
+    pub entries: Vec<ElstEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ElstEntry {
+    pub segment_duration: u64,
+    pub media_time: i64,
+    pub media_rate_integer: i16,
+    pub media_rate_fraction: i16,
+}
+
+impl ElstEntry {
+    pub fn is_empty_edit(&self) -> bool {
+        self.media_time == -1
+    }
+
+    pub fn is_dwell(&self) -> bool {
+        self.media_rate_integer == 0 && self.media_rate_fraction == 0
+    }
+}
+
+impl Ibox for ElstBox {
+    fn typ(&self) -> BoxType {
+        BoxType::Elst
+    }
+
+    fn header_size(&self) -> u64 {
+        HEADER_SIZE + 4
+    }
+
+    fn data_size(&self) -> u64 {
+        let entry_size = if self.version == 1 { 20 } else { 12 };
+        4 + (entry_size * self.entries.len()) as u64
+    }
+
+    fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(self).unwrap())
+    }
+
+    fn summary(&self) -> Result<String> {
+        let s = format!("entries={}", self.entries.len());
+        Ok(s)
+    }
+}
+
+impl<R: Read + Seek> ReadBox<&mut R> for ElstBox {
+    fn read(reader: &mut R, _: &BoxHeader) -> Result<Self> {
+        let (version, flags) = super::read_box_header_ext(reader)?;
+
+        let entry_count = reader.read_u32::<BigEndian>()?;
+        let mut entries = Vec::with_capacity(entry_count as usize);
+        for _ in 0..entry_count {
+            let (segment_duration, media_time) = if version == 1 {
+                (
+                    reader.read_u64::<BigEndian>()?,
+                    reader.read_i64::<BigEndian>()?,
+                )
+            } else if version == 0 {
+                (
+                    reader.read_u32::<BigEndian>()? as u64,
+                    reader.read_i32::<BigEndian>()? as i64,
+                )
+            } else {
+                return Err(Error::InvalidData("version must be 0 or 1"));
+            };
+            let entry = ElstEntry {
+                segment_duration,
+                media_time,
+                media_rate_integer: reader.read_i16::<BigEndian>()?,
+                media_rate_fraction: reader.read_i16::<BigEndian>()?,
+            };
+            entries.push(entry);
+        }
+
+        Ok(ElstBox {
+            version,
+            flags,
+            entries,
+        })
+    }
+}
+
+impl<W: Write> WriteBox<&mut W> for ElstBox {
+    fn write(&self, writer: &mut W, _: u64) -> Result<u64> {
+        super::write_box_header_ext(writer, self.version, self.flags)?;
+
+        writer.write_u32::<BigEndian>(self.entries.len() as u32)?;
+        for entry in self.entries.iter() {
+            if self.version == 1 {
+                writer.write_u64::<BigEndian>(entry.segment_duration)?;
+                writer.write_i64::<BigEndian>(entry.media_time)?;
+            } else if self.version == 0 {
+                writer.write_u32::<BigEndian>(entry.segment_duration as u32)?;
+                writer.write_i32::<BigEndian>(entry.media_time as i32)?;
+            } else {
+                return Err(Error::InvalidData("version must be 0 or 1"));
+            }
+            writer.write_i16::<BigEndian>(entry.media_rate_integer)?;
+            writer.write_i16::<BigEndian>(entry.media_rate_fraction)?;
+        }
+
+        Ok(self.header_size() - HEADER_SIZE + self.data_size())
+    }
+}
@@ -0,0 +1,503 @@
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use serde::Serialize;
+use std::io::{Read, Seek, Write};
+
+use super::{BoxHeader, BoxType, Ibox, ReadBox, WriteBox, HEADER_SIZE};
+use crate::bitreader::BitReader;
+use crate::error::Error;
+
+type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct EmsgBox {
+    pub version: u8,
+    pub flags: u32,
+    pub scheme_id_uri: String,
+    pub value: String,
+    pub timescale: u32,
+    // version 0 only, relative to the earliest presentation time of the segment
+    pub presentation_time_delta: Option<u32>,
+    // version 1 only
+    pub presentation_time: Option<u64>,
+    pub event_duration: u32,
+    pub id: u32,
+    pub message_data: Vec<u8>,
+}
+
+impl EmsgBox {
+    pub const SCHEME_SCTE35: &'static str = "urn:scte:scte35:2013:bin";
+
+    // None when the scheme is not SCTE-35
+    pub fn splice_info_section(&self) -> Result<Option<SpliceInfoSection>> {
+        if self.scheme_id_uri != Self::SCHEME_SCTE35 {
+            return Ok(None);
+        }
+        SpliceInfoSection::read(&self.message_data).map(Some)
+    }
+}
+
+impl Ibox for EmsgBox {
+    fn typ(&self) -> BoxType {
+        BoxType::Emsg
+    }
+
+    fn header_size(&self) -> u64 {
+        HEADER_SIZE + 4
+    }
+
+    fn data_size(&self) -> u64 {
+        let time_size = if self.version == 1 { 8 } else { 4 };
+        let strings_size = self.scheme_id_uri.len() as u64 + 1 + self.value.len() as u64 + 1;
+        4 + time_size + 4 + 4 + strings_size + self.message_data.len() as u64
+    }
+
+    fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(self).unwrap())
+    }
+
+    fn summary(&self) -> Result<String> {
+        let s = format!(
+            "scheme_id_uri={} value={} timescale={} presentation_time={} event_duration={} id={}",
+            self.scheme_id_uri,
+            self.value,
+            self.timescale,
+            self.presentation_time
+                .unwrap_or(self.presentation_time_delta.unwrap_or(0) as u64),
+            self.event_duration,
+            self.id
+        );
+        Ok(s)
+    }
+}
+
+impl<R: Read + Seek> ReadBox<&mut R> for EmsgBox {
+    fn read(reader: &mut R, header: &BoxHeader) -> Result<Self> {
+        let (version, flags) = super::read_box_header_ext(reader)?;
+        let end = header.offset + header.size;
+
+        let (
+            scheme_id_uri,
+            value,
+            timescale,
+            presentation_time_delta,
+            presentation_time,
+            event_duration,
+            id,
+        ) = if version == 1 {
+            let timescale = reader.read_u32::<BigEndian>()?;
+            let presentation_time = reader.read_u64::<BigEndian>()?;
+            let event_duration = reader.read_u32::<BigEndian>()?;
+            let id = reader.read_u32::<BigEndian>()?;
+            let scheme_id_uri = read_null_terminated_string(reader, end)?;
+            let value = read_null_terminated_string(reader, end)?;
+            (
+                scheme_id_uri,
+                value,
+                timescale,
+                None,
+                Some(presentation_time),
+                event_duration,
+                id,
+            )
+        } else if version == 0 {
+            let scheme_id_uri = read_null_terminated_string(reader, end)?;
+            let value = read_null_terminated_string(reader, end)?;
+            let timescale = reader.read_u32::<BigEndian>()?;
+            let presentation_time_delta = reader.read_u32::<BigEndian>()?;
+            let event_duration = reader.read_u32::<BigEndian>()?;
+            let id = reader.read_u32::<BigEndian>()?;
+            (
+                scheme_id_uri,
+                value,
+                timescale,
+                Some(presentation_time_delta),
+                None,
+                event_duration,
+                id,
+            )
+        } else {
+            return Err(Error::InvalidData("version must be 0 or 1"));
+        };
+
+        let message_data_size = end
+            .checked_sub(reader.stream_position()?)
+            .ok_or(Error::InvalidData("emsg size too small"))?;
+        let mut message_data = vec![0u8; message_data_size as usize];
+        reader.read_exact(&mut message_data)?;
+
+        Ok(EmsgBox {
+            version,
+            flags,
+            scheme_id_uri,
+            value,
+            timescale,
+            presentation_time_delta,
+            presentation_time,
+            event_duration,
+            id,
+            message_data,
+        })
+    }
+}
+
+impl<W: Write> WriteBox<&mut W> for EmsgBox {
+    fn write(&self, writer: &mut W, _: u64) -> Result<u64> {
+        super::write_box_header_ext(writer, self.version, self.flags)?;
+
+        if self.version == 1 {
+            writer.write_u32::<BigEndian>(self.timescale)?;
+            writer.write_u64::<BigEndian>(self.presentation_time.unwrap_or(0))?;
+            writer.write_u32::<BigEndian>(self.event_duration)?;
+            writer.write_u32::<BigEndian>(self.id)?;
+            write_null_terminated_string(writer, &self.scheme_id_uri)?;
+            write_null_terminated_string(writer, &self.value)?;
+        } else if self.version == 0 {
+            write_null_terminated_string(writer, &self.scheme_id_uri)?;
+            write_null_terminated_string(writer, &self.value)?;
+            writer.write_u32::<BigEndian>(self.timescale)?;
+            writer.write_u32::<BigEndian>(self.presentation_time_delta.unwrap_or(0))?;
+            writer.write_u32::<BigEndian>(self.event_duration)?;
+            writer.write_u32::<BigEndian>(self.id)?;
+        } else {
+            return Err(Error::InvalidData("version must be 0 or 1"));
+        }
+        writer.write_all(&self.message_data)?;
+
+        Ok(self.header_size() - HEADER_SIZE + self.data_size())
+    }
+}
+
+fn read_null_terminated_string<R: Read + Seek>(reader: &mut R, end: u64) -> Result<String> {
+    let mut bytes = Vec::new();
+    loop {
+        if reader.stream_position()? >= end {
+            return Err(Error::InvalidData("string is not null-terminated"));
+        }
+        match reader.read_u8()? {
+            0 => break,
+            b => bytes.push(b),
+        }
+    }
+    Ok(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+fn write_null_terminated_string<W: Write>(writer: &mut W, value: &str) -> Result<()> {
+    writer.write_all(value.as_bytes())?;
+    writer.write_u8(0)?;
+    Ok(())
+}
+
+// splice_info_section() of ANSI/SCTE 35
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SpliceInfoSection {
+    pub protocol_version: u8,
+    pub pts_adjustment: u64,
+    pub cw_index: u8,
+    pub tier: u16,
+    pub splice_command: SpliceCommand,
+    pub descriptors: Vec<SpliceDescriptor>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub enum SpliceCommand {
+    SpliceNull,
+    SpliceInsert(SpliceInsert),
+    // pts_time of splice_time(), None when the time is not specified
+    TimeSignal(Option<u64>),
+    // splice_schedule, bandwidth_reservation and private_command are kept as is
+    Other(u8, Vec<u8>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SpliceInsert {
+    pub splice_event_id: u32,
+    pub splice_event_cancel_indicator: bool,
+    pub out_of_network_indicator: bool,
+    pub program_splice_flag: bool,
+    pub splice_immediate_flag: bool,
+    pub splice_time: Option<u64>,
+    // component_tag and its splice time when program_splice_flag is not set
+    pub components: Vec<(u8, Option<u64>)>,
+    pub break_duration: Option<BreakDuration>,
+    pub unique_program_id: u16,
+    pub avail_num: u8,
+    pub avails_expected: u8,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct BreakDuration {
+    pub auto_return: bool,
+    pub duration: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SpliceDescriptor {
+    pub splice_descriptor_tag: u8,
+    pub identifier: u32,
+    pub data: Vec<u8>,
+}
+
+impl SpliceInfoSection {
+    pub const TABLE_ID: u8 = 0xFC;
+
+    pub const SPLICE_NULL: u8 = 0x00;
+    pub const SPLICE_INSERT: u8 = 0x05;
+    pub const TIME_SIGNAL: u8 = 0x06;
+
+    pub fn read(data: &[u8]) -> Result<Self> {
+        let mut reader = BitReader::new(data);
+
+        if reader.read_bits(8)? as u8 != Self::TABLE_ID {
+            return Err(Error::InvalidData(
+                "table_id of splice_info_section must be 0xFC",
+            ));
+        }
+        reader.skip_bits(1)?; // section_syntax_indicator
+        reader.skip_bits(1)?; // private_indicator
+        reader.skip_bits(2)?; // sap_type
+        let section_length = reader.read_bits(12)? as usize;
+        if section_length + 3 > data.len() {
+            return Err(Error::InvalidData("splice_info_section is truncated"));
+        }
+        let protocol_version = reader.read_bits(8)? as u8;
+        if reader.read_bit()? {
+            return Err(Error::InvalidData(
+                "encrypted splice_info_section is not supported",
+            ));
+        }
+        reader.skip_bits(6)?; // encryption_algorithm
+        let pts_adjustment = reader.read_bits(33)?;
+        let cw_index = reader.read_bits(8)? as u8;
+        let tier = reader.read_bits(12)? as u16;
+        let splice_command_length = reader.read_bits(12)? as usize;
+        let splice_command_type = reader.read_bits(8)? as u8;
+
+        let command_start = reader.position();
+        let splice_command = match splice_command_type {
+            Self::SPLICE_NULL => SpliceCommand::SpliceNull,
+            Self::SPLICE_INSERT => SpliceCommand::SpliceInsert(read_splice_insert(&mut reader)?),
+            Self::TIME_SIGNAL => SpliceCommand::TimeSignal(read_splice_time(&mut reader)?),
+            _ => {
+                // 0xFFF is the legacy value for an unspecified length
+                if splice_command_length == 0xFFF {
+                    return Err(Error::InvalidData("splice_command_length is unspecified"));
+                }
+                let start = command_start / 8;
+                let bytes = data
+                    .get(start..start + splice_command_length)
+                    .ok_or(Error::InvalidData("splice command is truncated"))?;
+                SpliceCommand::Other(splice_command_type, bytes.to_vec())
+            }
+        };
+        if splice_command_length != 0xFFF {
+            let command_end = command_start + splice_command_length * 8;
+            let consumed = reader.position();
+            if consumed > command_end {
+                return Err(Error::InvalidData("splice command overruns its length"));
+            }
+            reader.skip_bits(command_end - consumed)?;
+        }
+
+        let descriptor_loop_length = reader.read_bits(16)? as usize;
+        let mut descriptors = Vec::new();
+        let mut remain = descriptor_loop_length;
+        while remain >= 2 {
+            let splice_descriptor_tag = reader.read_bits(8)? as u8;
+            let descriptor_length = reader.read_bits(8)? as usize;
+            if descriptor_length < 4 || descriptor_length + 2 > remain {
+                return Err(Error::InvalidData("invalid splice descriptor length"));
+            }
+            let identifier = reader.read_bits(32)? as u32;
+            let mut data = Vec::with_capacity(descriptor_length - 4);
+            for _ in 4..descriptor_length {
+                data.push(reader.read_bits(8)? as u8);
+            }
+            descriptors.push(SpliceDescriptor {
+                splice_descriptor_tag,
+                identifier,
+                data,
+            });
+            remain -= descriptor_length + 2;
+        }
+
+        Ok(SpliceInfoSection {
+            protocol_version,
+            pts_adjustment,
+            cw_index,
+            tier,
+            splice_command,
+            descriptors,
+        })
+    }
+
+    // applies pts_adjustment to a pts_time of the splice command
+    pub fn adjusted_pts(&self, pts_time: u64) -> u64 {
+        (pts_time + self.pts_adjustment) & 0x1_FFFF_FFFF
+    }
+}
+
+fn read_splice_time(reader: &mut BitReader) -> Result<Option<u64>> {
+    if reader.read_bit()? {
+        reader.skip_bits(6)?; // reserved
+        Ok(Some(reader.read_bits(33)?))
+    } else {
+        reader.skip_bits(7)?; // reserved
+        Ok(None)
+    }
+}
+
+fn read_splice_insert(reader: &mut BitReader) -> Result<SpliceInsert> {
+    let mut splice_insert = SpliceInsert {
+        splice_event_id: reader.read_bits(32)? as u32,
+        splice_event_cancel_indicator: reader.read_bit()?,
+        out_of_network_indicator: false,
+        program_splice_flag: false,
+        splice_immediate_flag: false,
+        splice_time: None,
+        components: Vec::new(),
+        break_duration: None,
+        unique_program_id: 0,
+        avail_num: 0,
+        avails_expected: 0,
+    };
+    reader.skip_bits(7)?; // reserved
+    if splice_insert.splice_event_cancel_indicator {
+        return Ok(splice_insert);
+    }
+
+    splice_insert.out_of_network_indicator = reader.read_bit()?;
+    splice_insert.program_splice_flag = reader.read_bit()?;
+    let duration_flag = reader.read_bit()?;
+    splice_insert.splice_immediate_flag = reader.read_bit()?;
+    reader.skip_bits(4)?; // event_id_compliance_flag, reserved
+
+    if splice_insert.program_splice_flag && !splice_insert.splice_immediate_flag {
+        splice_insert.splice_time = read_splice_time(reader)?;
+    }
+    if !splice_insert.program_splice_flag {
+        let component_count = reader.read_bits(8)?;
+        for _ in 0..component_count {
+            let component_tag = reader.read_bits(8)? as u8;
+            let splice_time = if splice_insert.splice_immediate_flag {
+                None
+            } else {
+                read_splice_time(reader)?
+            };
+            splice_insert.components.push((component_tag, splice_time));
+        }
+    }
+    if duration_flag {
+        let auto_return = reader.read_bit()?;
+        reader.skip_bits(6)?; // reserved
+        splice_insert.break_duration = Some(BreakDuration {
+            auto_return,
+            duration: reader.read_bits(33)?,
+        });
+    }
+    splice_insert.unique_program_id = reader.read_bits(16)? as u16;
+    splice_insert.avail_num = reader.read_bits(8)? as u8;
+    splice_insert.avails_expected = reader.read_bits(8)? as u8;
+
+    Ok(splice_insert)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CUEI: u32 = 0x43_55_45_49;
+
+    // time_signal with a segmentation_descriptor, from the SCTE 35 samples
+    const TIME_SIGNAL: [u8; 55] = [
+        0xFC, 0x30, 0x34, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xFF, 0xFF, 0xF0, 0x05, 0x06, 0xFE,
+        0x72, 0xBD, 0x00, 0x50, 0x00, 0x1E, 0x02, 0x1C, 0x43, 0x55, 0x45, 0x49, 0x48, 0x00, 0x00,
+        0x8E, 0x7F, 0xCF, 0x00, 0x01, 0xA5, 0x99, 0xB0, 0x08, 0x08, 0x00, 0x00, 0x00, 0x00, 0x2C,
+        0xA0, 0xA1, 0x8A, 0x34, 0x02, 0x00, 0x9A, 0xC9, 0xD1, 0x7E,
+    ];
+
+    // splice_insert out of network with a break duration and an
+    // avail_descriptor, from the SCTE 35 samples
+    const SPLICE_INSERT: [u8; 50] = [
+        0xFC, 0x30, 0x2F, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xFF, 0xFF, 0xF0, 0x14, 0x05, 0x48,
+        0x00, 0x00, 0x8F, 0x7F, 0xEF, 0xFE, 0x73, 0x69, 0xC0, 0x2E, 0xFE, 0x00, 0x52, 0xCC, 0xF5,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x0A, 0x00, 0x08, 0x43, 0x55, 0x45, 0x49, 0x00, 0x00, 0x01,
+        0x35, 0x62, 0xDB, 0xA3, 0x0A,
+    ];
+
+    #[test]
+    fn splice_info_section_time_signal() {
+        let section = SpliceInfoSection::read(&TIME_SIGNAL).unwrap();
+        assert_eq!(section.protocol_version, 0);
+        assert_eq!(section.pts_adjustment, 0);
+        assert_eq!(section.tier, 0xFFF);
+        assert_eq!(
+            section.splice_command,
+            SpliceCommand::TimeSignal(Some(0x0_72BD_0050))
+        );
+        assert_eq!(section.descriptors.len(), 1);
+        let descriptor = &section.descriptors[0];
+        assert_eq!(descriptor.splice_descriptor_tag, 0x02);
+        assert_eq!(descriptor.identifier, CUEI);
+        assert_eq!(descriptor.data, TIME_SIGNAL[27..51]);
+    }
+
+    #[test]
+    fn splice_info_section_splice_insert() {
+        let section = SpliceInfoSection::read(&SPLICE_INSERT).unwrap();
+        assert_eq!(section.tier, 0xFFF);
+        assert_eq!(
+            section.splice_command,
+            SpliceCommand::SpliceInsert(SpliceInsert {
+                splice_event_id: 0x4800_008F,
+                splice_event_cancel_indicator: false,
+                out_of_network_indicator: true,
+                program_splice_flag: true,
+                splice_immediate_flag: false,
+                splice_time: Some(0x0_7369_C02E),
+                components: Vec::new(),
+                break_duration: Some(BreakDuration {
+                    auto_return: true,
+                    duration: 0x0_0052_CCF5,
+                }),
+                unique_program_id: 0,
+                avail_num: 0,
+                avails_expected: 0,
+            })
+        );
+        assert_eq!(
+            section.descriptors,
+            vec![SpliceDescriptor {
+                splice_descriptor_tag: 0x00,
+                identifier: CUEI,
+                data: vec![0x00, 0x00, 0x01, 0x35],
+            }]
+        );
+    }
+
+    #[test]
+    fn splice_info_section_adjusted_pts() {
+        let mut section = SpliceInfoSection::read(&TIME_SIGNAL).unwrap();
+        section.pts_adjustment = 0x1_FFFF_FFFF;
+        assert_eq!(section.adjusted_pts(0x0_72BD_0050), 0x0_72BD_004F);
+    }
+
+    #[test]
+    fn emsg_strings_which_are_not_utf8() {
+        // version 1, after an 8 byte header
+        let mut data = vec![0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0];
+        data.extend_from_slice(&90000u32.to_be_bytes());
+        data.extend_from_slice(&180000u64.to_be_bytes());
+        data.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0, 7]);
+        data.extend_from_slice(b"urn:example\0caf\xE9\0");
+        data.extend_from_slice(b"message");
+        let header = BoxHeader::new(BoxType::Emsg, data.len() as u64, 0);
+        let mut reader = std::io::Cursor::new(&data);
+        reader.set_position(HEADER_SIZE);
+        let emsg = EmsgBox::read(&mut reader, &header).unwrap();
+        assert_eq!(emsg.scheme_id_uri, "urn:example");
+        assert_eq!(emsg.value, "caf\u{FFFD}");
+        assert_eq!(emsg.presentation_time, Some(180000));
+        assert_eq!(emsg.id, 7);
+        assert_eq!(emsg.message_data, b"message");
+    }
+}
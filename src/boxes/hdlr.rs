@@ -1,4 +1,4 @@
-use byteorder::{BigEndian, ReadBytesExt};
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
 use serde::Serialize;
 use std::io::{Read, Seek, Write};
 
@@ -12,8 +12,15 @@ type Result<T> = std::result::Result<T, Error>;
 pub struct HdlrBox {
     pub version: u8,
     pub flags: u32,
+    // QuickTime component type, zero in ISO files
+    pub pre_defined: u32,
     pub handler_type: FourCC,
+    // QuickTime component manufacturer, flags and flags mask, zero in ISO files
+    pub reserved: [u8; 12],
     pub name: String,
+    // the name as read when it is not a null-terminated UTF-8 string, such as
+    // a QuickTime Pascal string; it is written back instead of name
+    pub raw_name: Option<Vec<u8>>,
 }
 
 impl Ibox for HdlrBox {
@@ -26,8 +33,11 @@ impl Ibox for HdlrBox {
     }
 
     fn data_size(&self) -> u64 {
-        // pre_defined(4) + handler_type(4) + reserved(4*3) + name.len() + null-terminated(1)
-        20 + self.name.len() as u64 + 1
+        // pre_defined(4) + handler_type(4) + reserved(4*3) + name
+        20 + match &self.raw_name {
+            Some(raw_name) => raw_name.len() as u64,
+            None => self.name.len() as u64 + 1,
+        }
     }
 
     fn to_json(&self) -> Result<String> {
@@ -44,39 +54,96 @@ impl<R: Read + Seek> ReadBox<&mut R> for HdlrBox {
     fn read(reader: &mut R, header: &BoxHeader) -> Result<Self> {
         let (version, flags) = super::read_box_header_ext(reader)?;
 
-        reader.read_u32::<BigEndian>()?; // pre-defined
+        let pre_defined = reader.read_u32::<BigEndian>()?;
         let handler = reader.read_u32::<BigEndian>()?;
 
-        super::rel_skip(reader, 12)?; // reserved
+        let mut reserved = [0u8; 12];
+        reader.read_exact(&mut reserved)?;
 
         let buf_size = header
             .size
-            .checked_sub(8 + 4 + 20 + 1)
+            .checked_sub(8 + 4 + 20)
             .ok_or(Error::InvalidData("hdlr size too small"))?;
         let mut buf = vec![0u8; buf_size as usize];
         reader.read_exact(&mut buf)?;
 
-        let handler_string = match String::from_utf8(buf) {
-            Ok(t) => {
-                if t.len() != buf_size as usize {
-                    return Err(Error::InvalidData("string too small"));
-                }
-                t
+        let (name, raw_name) = match buf.split_last() {
+            Some((0, name)) if std::str::from_utf8(name).is_ok() => {
+                (String::from_utf8(name.to_vec()).unwrap(), None)
             }
-            _ => String::from("null"),
+            _ => (String::from_utf8_lossy(&buf).into_owned(), Some(buf)),
         };
 
         Ok(HdlrBox {
             version,
             flags,
+            pre_defined,
             handler_type: From::from(handler),
-            name: handler_string,
+            reserved,
+            name,
+            raw_name,
         })
     }
 }
 
 impl<W: Write> WriteBox<&mut W> for HdlrBox {
-    fn write(&self, _: &mut W, _: u64) -> Result<u64> {
-        todo!();
+    fn write(&self, writer: &mut W, _: u64) -> Result<u64> {
+        super::write_box_header_ext(writer, self.version, self.flags)?;
+
+        writer.write_u32::<BigEndian>(self.pre_defined)?;
+        writer.write_u32::<BigEndian>(self.handler_type.into())?;
+
+        writer.write_all(&self.reserved)?;
+
+        match &self.raw_name {
+            Some(raw_name) => writer.write_all(raw_name)?,
+            None => {
+                writer.write_all(self.name.as_bytes())?;
+                writer.write_u8(0)?; // null-terminated
+            }
+        }
+
+        Ok(self.header_size() - HEADER_SIZE + self.data_size())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn round_trip(data: &[u8]) -> HdlrBox {
+        let header = BoxHeader::new(BoxType::Hdlr, HEADER_SIZE + data.len() as u64, 0);
+        let hdlr = HdlrBox::read(&mut Cursor::new(data), &header).unwrap();
+        let mut buf = Vec::new();
+        let size = hdlr.write(&mut buf, 0).unwrap();
+        assert_eq!(buf, data);
+        assert_eq!(size, data.len() as u64);
+        hdlr
+    }
+
+    #[test]
+    fn hdlr_iso() {
+        let mut data = vec![0u8; 8];
+        data.extend_from_slice(b"vide");
+        data.extend_from_slice(&[0; 12]);
+        data.extend_from_slice(b"VideoHandler\0");
+        let hdlr = round_trip(&data);
+        assert_eq!(hdlr.handler_type, FourCC { value: *b"vide" });
+        assert_eq!(hdlr.name, "VideoHandler");
+        assert_eq!(hdlr.raw_name, None);
+    }
+
+    #[test]
+    fn hdlr_quicktime() {
+        // component type, manufacturer and a Pascal string name
+        let mut data = vec![0u8; 4];
+        data.extend_from_slice(b"mhlrvideappl");
+        data.extend_from_slice(&[0, 0, 0, 0, 0, 0x01, 0x00, 0x2F]);
+        data.extend_from_slice(b"\x0cVideoHandler");
+        let hdlr = round_trip(&data);
+        assert_eq!(hdlr.pre_defined, u32::from_be_bytes(*b"mhlr"));
+        assert_eq!(&hdlr.reserved[..4], b"appl");
+        assert_eq!(hdlr.raw_name.as_deref(), Some(&b"\x0cVideoHandler"[..]));
     }
 }
@@ -0,0 +1,206 @@
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use serde::Serialize;
+use std::io::{Read, Seek, Write};
+
+use super::avc1::{visual_sample_entry, NalUnit};
+use super::{BoxHeader, BoxType, Ibox, ReadBox, WriteBox};
+use crate::error::Error;
+use crate::types::FixedPointU16;
+
+type Result<T> = std::result::Result<T, Error>;
+
+visual_sample_entry!(Hev1Box, Hev1);
+visual_sample_entry!(Hvc1Box, Hvc1);
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct HvcCBox {
+    pub configuration_version: u8,
+    pub general_profile_space: u8,
+    pub general_tier_flag: bool,
+    pub general_profile_idc: u8,
+    pub general_profile_compatibility_flags: u32,
+    pub general_constraint_indicator_flags: u64,
+    pub general_level_idc: u8,
+    pub min_spatial_segmentation_idc: u16,
+    pub parallelism_type: u8,
+    pub chroma_format_idc: u8,
+    pub bit_depth_luma_minus8: u8,
+    pub bit_depth_chroma_minus8: u8,
+    pub avg_frame_rate: u16,
+    pub constant_frame_rate: u8,
+    pub num_temporal_layers: u8,
+    pub temporal_id_nested: bool,
+    pub length_size_minus_one: u8,
+    pub arrays: Vec<HvcCArray>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct HvcCArray {
+    pub completeness: bool,
+    pub nal_unit_type: u8,
+    pub nalus: Vec<NalUnit>,
+}
+
+impl HvcCArray {
+    fn size(&self) -> u64 {
+        3 + self.nalus.iter().map(|e| e.size()).sum::<u64>()
+    }
+}
+
+impl HvcCBox {
+    pub const NAL_UNIT_TYPE_VPS: u8 = 32;
+    pub const NAL_UNIT_TYPE_SPS: u8 = 33;
+    pub const NAL_UNIT_TYPE_PPS: u8 = 34;
+    pub const NAL_UNIT_TYPE_PREFIX_SEI: u8 = 39;
+    pub const NAL_UNIT_TYPE_SUFFIX_SEI: u8 = 40;
+
+    pub fn nal_units(&self, nal_unit_type: u8) -> Vec<&NalUnit> {
+        self.arrays
+            .iter()
+            .filter(|a| a.nal_unit_type == nal_unit_type)
+            .flat_map(|a| a.nalus.iter())
+            .collect()
+    }
+
+    pub fn vps(&self) -> Vec<&NalUnit> {
+        self.nal_units(HvcCBox::NAL_UNIT_TYPE_VPS)
+    }
+
+    pub fn sps(&self) -> Vec<&NalUnit> {
+        self.nal_units(HvcCBox::NAL_UNIT_TYPE_SPS)
+    }
+
+    pub fn pps(&self) -> Vec<&NalUnit> {
+        self.nal_units(HvcCBox::NAL_UNIT_TYPE_PPS)
+    }
+
+    pub fn sei(&self) -> Vec<&NalUnit> {
+        let mut sei = self.nal_units(HvcCBox::NAL_UNIT_TYPE_PREFIX_SEI);
+        sei.extend(self.nal_units(HvcCBox::NAL_UNIT_TYPE_SUFFIX_SEI));
+        sei
+    }
+}
+
+impl Ibox for HvcCBox {
+    fn typ(&self) -> BoxType {
+        BoxType::HvcC
+    }
+
+    fn data_size(&self) -> u64 {
+        self.arrays.iter().fold(23, |acc, e| acc + e.size())
+    }
+
+    fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(self).unwrap())
+    }
+
+    fn summary(&self) -> Result<String> {
+        let s = format!(
+            "general_profile_idc={} general_tier_flag={} general_level_idc={} chroma_format_idc={} arrays={}",
+            self.general_profile_idc,
+            self.general_tier_flag,
+            self.general_level_idc,
+            self.chroma_format_idc,
+            self.arrays.len()
+        );
+        Ok(s)
+    }
+}
+
+impl<R: Read + Seek> ReadBox<&mut R> for HvcCBox {
+    fn read(reader: &mut R, _: &BoxHeader) -> Result<Self> {
+        let configuration_version = reader.read_u8()?;
+        let params = reader.read_u8()?;
+        let general_profile_space = params >> 6;
+        let general_tier_flag = (params >> 5) & 0x1 == 0x1;
+        let general_profile_idc = params & 0x1F;
+        let general_profile_compatibility_flags = reader.read_u32::<BigEndian>()?;
+        let general_constraint_indicator_flags = reader.read_u48::<BigEndian>()?;
+        let general_level_idc = reader.read_u8()?;
+        let min_spatial_segmentation_idc = reader.read_u16::<BigEndian>()? & 0x0FFF;
+        let parallelism_type = reader.read_u8()? & 0x3;
+        let chroma_format_idc = reader.read_u8()? & 0x3;
+        let bit_depth_luma_minus8 = reader.read_u8()? & 0x7;
+        let bit_depth_chroma_minus8 = reader.read_u8()? & 0x7;
+        let avg_frame_rate = reader.read_u16::<BigEndian>()?;
+        let params = reader.read_u8()?;
+        let constant_frame_rate = params >> 6;
+        let num_temporal_layers = (params >> 3) & 0x7;
+        let temporal_id_nested = (params >> 2) & 0x1 == 0x1;
+        let length_size_minus_one = params & 0x3;
+
+        let num_of_arrays = reader.read_u8()?;
+        let mut arrays = Vec::with_capacity(num_of_arrays as usize);
+        for _ in 0..num_of_arrays {
+            let params = reader.read_u8()?;
+            let num_nalus = reader.read_u16::<BigEndian>()?;
+            let mut nalus = Vec::with_capacity(num_nalus as usize);
+            for _ in 0..num_nalus {
+                nalus.push(NalUnit::read(reader)?);
+            }
+            arrays.push(HvcCArray {
+                completeness: params >> 7 == 0x1,
+                nal_unit_type: params & 0x3F,
+                nalus,
+            });
+        }
+
+        Ok(HvcCBox {
+            configuration_version,
+            general_profile_space,
+            general_tier_flag,
+            general_profile_idc,
+            general_profile_compatibility_flags,
+            general_constraint_indicator_flags,
+            general_level_idc,
+            min_spatial_segmentation_idc,
+            parallelism_type,
+            chroma_format_idc,
+            bit_depth_luma_minus8,
+            bit_depth_chroma_minus8,
+            avg_frame_rate,
+            constant_frame_rate,
+            num_temporal_layers,
+            temporal_id_nested,
+            length_size_minus_one,
+            arrays,
+        })
+    }
+}
+
+impl<W: Write> WriteBox<&mut W> for HvcCBox {
+    fn write(&self, writer: &mut W, _: u64) -> Result<u64> {
+        writer.write_u8(self.configuration_version)?;
+        writer.write_u8(
+            (self.general_profile_space << 6)
+                | ((self.general_tier_flag as u8) << 5)
+                | (self.general_profile_idc & 0x1F),
+        )?;
+        writer.write_u32::<BigEndian>(self.general_profile_compatibility_flags)?;
+        writer.write_u48::<BigEndian>(self.general_constraint_indicator_flags)?;
+        writer.write_u8(self.general_level_idc)?;
+        writer.write_u16::<BigEndian>(0xF000 | self.min_spatial_segmentation_idc)?;
+        writer.write_u8(0xFC | self.parallelism_type)?;
+        writer.write_u8(0xFC | self.chroma_format_idc)?;
+        writer.write_u8(0xF8 | self.bit_depth_luma_minus8)?;
+        writer.write_u8(0xF8 | self.bit_depth_chroma_minus8)?;
+        writer.write_u16::<BigEndian>(self.avg_frame_rate)?;
+        writer.write_u8(
+            (self.constant_frame_rate << 6)
+                | ((self.num_temporal_layers & 0x7) << 3)
+                | ((self.temporal_id_nested as u8) << 2)
+                | (self.length_size_minus_one & 0x3),
+        )?;
+
+        writer.write_u8(self.arrays.len() as u8)?;
+        for array in self.arrays.iter() {
+            writer.write_u8(((array.completeness as u8) << 7) | (array.nal_unit_type & 0x3F))?;
+            writer.write_u16::<BigEndian>(array.nalus.len() as u16)?;
+            for nal_unit in array.nalus.iter() {
+                nal_unit.write(writer)?;
+            }
+        }
+
+        Ok(self.data_size())
+    }
+}
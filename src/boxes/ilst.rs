@@ -0,0 +1,326 @@
+use byteorder::{BigEndian, WriteBytesExt};
+use serde::Serialize;
+use std::io::{Read, Seek, Write};
+
+use super::{BoxHeader, BoxType, DataBox, Ibox, ReadBox, WriteBox, HEADER_SIZE};
+use crate::error::Error;
+use crate::types::FourCC;
+
+type Result<T> = std::result::Result<T, Error>;
+
+// items are keyed by their box type, so they are kept in the ilst box itself
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct IlstBox {
+    pub items: Vec<IlstItem>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct IlstItem {
+    pub key: FourCC,
+    // only freeform (----) items have mean and name
+    pub mean: Option<String>,
+    pub name: Option<String>,
+    pub data: Vec<DataBox>,
+    // other boxes in the item are kept as is
+    pub ext: Vec<u8>,
+}
+
+impl IlstItem {
+    const MEAN: u32 = 0x6d_65_61_6e;
+    const NAME: u32 = 0x6e_61_6d_65;
+
+    pub fn new(key: FourCC, data: DataBox) -> Self {
+        IlstItem {
+            key,
+            mean: None,
+            name: None,
+            data: vec![data],
+            ext: Vec::new(),
+        }
+    }
+
+    pub fn freeform(mean: &str, name: &str, data: DataBox) -> Self {
+        IlstItem {
+            key: IlstBox::KEY_FREEFORM,
+            mean: Some(String::from(mean)),
+            name: Some(String::from(name)),
+            data: vec![data],
+            ext: Vec::new(),
+        }
+    }
+
+    fn size(&self) -> u64 {
+        let mut size = HEADER_SIZE;
+        if let Some(mean) = &self.mean {
+            size += HEADER_SIZE + 4 + mean.len() as u64;
+        }
+        if let Some(name) = &self.name {
+            size += HEADER_SIZE + 4 + name.len() as u64;
+        }
+        self.data
+            .iter()
+            .fold(size, |acc, d| acc + d.header_size() + d.data_size())
+            + self.ext.len() as u64
+    }
+
+    fn read<R: Read + Seek>(reader: &mut R, header: &BoxHeader) -> Result<Self> {
+        let mut item = IlstItem {
+            key: FourCC::from(u32::from(header.typ)),
+            mean: None,
+            name: None,
+            data: Vec::new(),
+            ext: Vec::new(),
+        };
+
+        let end = header.offset + header.size;
+        let mut current = reader.stream_position()?;
+        while current + HEADER_SIZE <= end {
+            let child = super::read_box_header(reader, current)?;
+            if child.size < HEADER_SIZE || current + child.size > end {
+                return Err(Error::InvalidData("ilst item contains an invalid box"));
+            }
+            match u32::from(child.typ) {
+                Self::MEAN => item.mean = Some(read_string(reader, &child)?),
+                Self::NAME => item.name = Some(read_string(reader, &child)?),
+                _ if child.typ == BoxType::Data => item.data.push(DataBox::read(reader, &child)?),
+                _ => {
+                    super::abs_skip(reader, current)?;
+                    let mut buf = vec![0u8; child.size as usize];
+                    reader.read_exact(&mut buf)?;
+                    item.ext.extend_from_slice(&buf);
+                }
+            }
+            current += child.size;
+            super::abs_skip(reader, current)?;
+        }
+        super::abs_skip(reader, end)?;
+
+        Ok(item)
+    }
+
+    fn write<W: Write>(&self, writer: &mut W) -> Result<u64> {
+        let header = BoxHeader::new(BoxType::from(u32::from(self.key)), self.size(), 0);
+        super::write_box_header(&header, writer)?;
+        if let Some(mean) = &self.mean {
+            write_string(writer, Self::MEAN, mean)?;
+        }
+        if let Some(name) = &self.name {
+            write_string(writer, Self::NAME, name)?;
+        }
+        for data in self.data.iter() {
+            let header = BoxHeader::new(BoxType::Data, data.header_size() + data.data_size(), 0);
+            super::write_box_header(&header, writer)?;
+            data.write(writer, 0)?;
+        }
+        writer.write_all(&self.ext)?;
+        Ok(self.size())
+    }
+}
+
+impl IlstBox {
+    pub const KEY_TITLE: FourCC = FourCC {
+        value: [0xa9, b'n', b'a', b'm'],
+    };
+    pub const KEY_ARTIST: FourCC = FourCC {
+        value: [0xa9, b'A', b'R', b'T'],
+    };
+    pub const KEY_DAY: FourCC = FourCC {
+        value: [0xa9, b'd', b'a', b'y'],
+    };
+    pub const KEY_COVER: FourCC = FourCC { value: *b"covr" };
+    pub const KEY_DESCRIPTION: FourCC = FourCC { value: *b"desc" };
+    pub const KEY_TRACK_NUMBER: FourCC = FourCC { value: *b"trkn" };
+    pub const KEY_DISC_NUMBER: FourCC = FourCC { value: *b"disk" };
+    pub const KEY_TEMPO: FourCC = FourCC { value: *b"tmpo" };
+    pub const KEY_COMPILATION: FourCC = FourCC { value: *b"cpil" };
+    pub const KEY_FREEFORM: FourCC = FourCC { value: *b"----" };
+
+    pub fn get(&self, key: FourCC) -> Option<&IlstItem> {
+        self.items.iter().find(|i| i.key == key)
+    }
+
+    pub fn get_freeform(&self, mean: &str, name: &str) -> Option<&IlstItem> {
+        self.items.iter().find(|i| {
+            i.key == Self::KEY_FREEFORM
+                && i.mean.as_deref() == Some(mean)
+                && i.name.as_deref() == Some(name)
+        })
+    }
+
+    // replaces the item with the same key in place, or appends it
+    pub fn set(&mut self, item: IlstItem) {
+        let position = self.items.iter().position(|i| {
+            i.key == item.key
+                && (i.key != Self::KEY_FREEFORM || (&i.mean, &i.name) == (&item.mean, &item.name))
+        });
+        match position {
+            Some(position) => self.items[position] = item,
+            None => self.items.push(item),
+        }
+    }
+
+    pub fn remove(&mut self, key: FourCC) {
+        self.items.retain(|i| i.key != key);
+    }
+}
+
+impl Ibox for IlstBox {
+    fn typ(&self) -> BoxType {
+        BoxType::Ilst
+    }
+
+    fn data_size(&self) -> u64 {
+        self.items.iter().map(|i| i.size()).sum()
+    }
+
+    fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(self).unwrap())
+    }
+
+    fn summary(&self) -> Result<String> {
+        let keys: Vec<String> = self.items.iter().map(|i| i.key.to_string()).collect();
+        let s = format!("items={}", keys.join(","));
+        Ok(s)
+    }
+}
+
+impl<R: Read + Seek> ReadBox<&mut R> for IlstBox {
+    fn read(reader: &mut R, header: &BoxHeader) -> Result<Self> {
+        let end = header.offset + header.size;
+        let mut items = Vec::new();
+        let mut current = reader.stream_position()?;
+        while current + HEADER_SIZE <= end {
+            let item_header = super::read_box_header(reader, current)?;
+            if item_header.size < HEADER_SIZE || current + item_header.size > end {
+                return Err(Error::InvalidData("ilst contains an invalid item"));
+            }
+            items.push(IlstItem::read(reader, &item_header)?);
+            current += item_header.size;
+        }
+
+        Ok(IlstBox { items })
+    }
+}
+
+impl<W: Write> WriteBox<&mut W> for IlstBox {
+    fn write(&self, writer: &mut W, _: u64) -> Result<u64> {
+        for item in self.items.iter() {
+            item.write(writer)?;
+        }
+
+        Ok(self.data_size())
+    }
+}
+
+// mean and name are full boxes holding a string without a terminator
+fn read_string<R: Read + Seek>(reader: &mut R, header: &BoxHeader) -> Result<String> {
+    super::read_box_header_ext(reader)?;
+    let size = header
+        .size
+        .checked_sub(HEADER_SIZE + 4)
+        .ok_or(Error::InvalidData("string box size too small"))?;
+    let mut buf = vec![0u8; size as usize];
+    reader.read_exact(&mut buf)?;
+    Ok(String::from_utf8_lossy(&buf).into_owned())
+}
+
+fn write_string<W: Write>(writer: &mut W, typ: u32, value: &str) -> Result<u64> {
+    let size = HEADER_SIZE + 4 + value.len() as u64;
+    writer.write_u32::<BigEndian>(size as u32)?;
+    writer.write_u32::<BigEndian>(typ)?;
+    super::write_box_header_ext(writer, 0, 0)?;
+    writer.write_all(value.as_bytes())?;
+    Ok(size)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn item(key: &[u8; 4], children: &[Vec<u8>]) -> Vec<u8> {
+        let size: usize = children.iter().map(|c| c.len()).sum();
+        let mut buf = (8 + size as u32).to_be_bytes().to_vec();
+        buf.extend_from_slice(key);
+        children.iter().for_each(|c| buf.extend_from_slice(c));
+        buf
+    }
+
+    fn full_box(typ: &[u8; 4], flags: u32, payload: &[u8]) -> Vec<u8> {
+        let mut buf = (12 + payload.len() as u32).to_be_bytes().to_vec();
+        buf.extend_from_slice(typ);
+        buf.extend_from_slice(&flags.to_be_bytes());
+        buf.extend_from_slice(payload);
+        buf
+    }
+
+    fn data(data_type: u32, value: &[u8]) -> Vec<u8> {
+        let mut payload = vec![0u8; 4];
+        payload.extend_from_slice(value);
+        full_box(b"data", data_type, &payload)
+    }
+
+    // items are read after the ilst header and written back as they were
+    fn round_trip(items: &[Vec<u8>]) -> IlstBox {
+        let mut buf = vec![0u8; HEADER_SIZE as usize];
+        items.iter().for_each(|i| buf.extend_from_slice(i));
+        let header = BoxHeader::new(BoxType::Ilst, buf.len() as u64, 0);
+        let mut reader = Cursor::new(&buf);
+        reader.set_position(HEADER_SIZE);
+        let ilst = IlstBox::read(&mut reader, &header).unwrap();
+
+        let mut out = Vec::new();
+        assert_eq!(ilst.write(&mut out, 0).unwrap(), ilst.data_size());
+        assert_eq!(out, buf[HEADER_SIZE as usize..]);
+        ilst
+    }
+
+    #[test]
+    fn ilst_items() {
+        let ilst = round_trip(&[
+            item(b"covr", &[data(13, &[0xFF, 0xD8]), data(14, b"PNG")]),
+            item(b"trkn", &[data(0, &[0, 0, 0, 3, 0, 12, 0, 0])]),
+            item(b"disk", &[data(0, &[0, 0, 0, 1, 0, 2])]),
+            item(b"tmpo", &[data(21, &[0, 120])]),
+            item(b"cpil", &[data(21, &[1])]),
+            item(
+                b"----",
+                &[
+                    full_box(b"mean", 0, b"com.apple.iTunes"),
+                    full_box(b"name", 0, b"ENCODER"),
+                    data(1, b"mp4box"),
+                ],
+            ),
+        ]);
+
+        let covers = &ilst.get(IlstBox::KEY_COVER).unwrap().data;
+        assert_eq!(covers.len(), 2);
+        assert_eq!(covers[1].data_type, DataBox::TYPE_PNG);
+        let value = |key: FourCC| ilst.get(key).unwrap().data[0].as_integer();
+        assert_eq!(value(IlstBox::KEY_TEMPO), Some(120));
+        assert_eq!(value(IlstBox::KEY_COMPILATION), Some(1));
+        let trkn = &ilst.get(IlstBox::KEY_TRACK_NUMBER).unwrap().data[0];
+        assert_eq!(trkn.value[2..6], [0, 3, 0, 12]);
+        let freeform = ilst.get_freeform("com.apple.iTunes", "ENCODER").unwrap();
+        assert_eq!(freeform.data[0].as_str().as_deref(), Some("mp4box"));
+    }
+
+    #[test]
+    fn freeform_names_which_are_not_utf8() {
+        let buf = item(
+            b"----",
+            &[
+                full_box(b"mean", 0, b"com.apple.iTunes"),
+                full_box(b"name", 0, b"caf\xE9"),
+                data(1, b"value"),
+            ],
+        );
+        let mut ilst = vec![0u8; HEADER_SIZE as usize];
+        ilst.extend_from_slice(&buf);
+        let header = BoxHeader::new(BoxType::Ilst, ilst.len() as u64, 0);
+        let mut reader = Cursor::new(&ilst);
+        reader.set_position(HEADER_SIZE);
+        let ilst = IlstBox::read(&mut reader, &header).unwrap();
+        assert_eq!(ilst.items[0].name.as_deref(), Some("caf\u{FFFD}"));
+    }
+}
@@ -1,4 +1,4 @@
-use byteorder::{BigEndian, ReadBytesExt};
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
 use serde::Serialize;
 use std::char::{self, REPLACEMENT_CHARACTER};
 use std::io::{Read, Seek, Write};
@@ -84,8 +84,27 @@ impl<R: Read + Seek> ReadBox<&mut R> for MdhdBox {
 }
 
 impl<W: Write> WriteBox<&mut W> for MdhdBox {
-    fn write(&self, _: &mut W, _: u64) -> Result<u64> {
-        todo!();
+    fn write(&self, writer: &mut W, _: u64) -> Result<u64> {
+        super::write_box_header_ext(writer, self.version, self.flags)?;
+
+        if self.version == 1 {
+            writer.write_u64::<BigEndian>(self.creation_time)?;
+            writer.write_u64::<BigEndian>(self.modification_time)?;
+            writer.write_u32::<BigEndian>(self.timescale)?;
+            writer.write_u64::<BigEndian>(self.duration)?;
+        } else if self.version == 0 {
+            writer.write_u32::<BigEndian>(self.creation_time as u32)?;
+            writer.write_u32::<BigEndian>(self.modification_time as u32)?;
+            writer.write_u32::<BigEndian>(self.timescale)?;
+            writer.write_u32::<BigEndian>(self.duration as u32)?;
+        } else {
+            return Err(Error::InvalidData("version must be 0 or 1"));
+        }
+
+        writer.write_u16::<BigEndian>(language_code(&self.language))?;
+        writer.write_u16::<BigEndian>(0)?; // pre-defined
+
+        Ok(self.header_size() - HEADER_SIZE + self.data_size())
     }
 }
 
@@ -103,3 +122,11 @@ fn language_string(language: u16) -> String {
 
     lang_str
 }
+
+fn language_code(language: &str) -> u16 {
+    let mut code: u16 = 0;
+    for c in language.encode_utf16().take(3) {
+        code = (code << 5) | (c.wrapping_sub(0x60) & 0x1F);
+    }
+    code
+}
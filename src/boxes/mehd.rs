@@ -0,0 +1,78 @@
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use serde::Serialize;
+use std::io::{Read, Seek, Write};
+
+use super::{BoxHeader, BoxType, Ibox, ReadBox, WriteBox, HEADER_SIZE};
+use crate::error::Error;
+
+type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct MehdBox {
+    pub version: u8,
+    pub flags: u32,
+    pub fragment_duration: u64,
+}
+
+impl Ibox for MehdBox {
+    fn typ(&self) -> BoxType {
+        BoxType::Mehd
+    }
+
+    fn header_size(&self) -> u64 {
+        HEADER_SIZE + 4
+    }
+
+    fn data_size(&self) -> u64 {
+        if self.version == 1 {
+            8
+        } else {
+            4
+        }
+    }
+
+    fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(self).unwrap())
+    }
+
+    fn summary(&self) -> Result<String> {
+        let s = format!("fragment_duration={}", self.fragment_duration);
+        Ok(s)
+    }
+}
+
+impl<R: Read + Seek> ReadBox<&mut R> for MehdBox {
+    fn read(reader: &mut R, _: &BoxHeader) -> Result<Self> {
+        let (version, flags) = super::read_box_header_ext(reader)?;
+
+        let fragment_duration = if version == 1 {
+            reader.read_u64::<BigEndian>()?
+        } else if version == 0 {
+            reader.read_u32::<BigEndian>()? as u64
+        } else {
+            return Err(Error::InvalidData("version must be 0 or 1"));
+        };
+
+        Ok(MehdBox {
+            version,
+            flags,
+            fragment_duration,
+        })
+    }
+}
+
+impl<W: Write> WriteBox<&mut W> for MehdBox {
+    fn write(&self, writer: &mut W, _: u64) -> Result<u64> {
+        super::write_box_header_ext(writer, self.version, self.flags)?;
+
+        if self.version == 1 {
+            writer.write_u64::<BigEndian>(self.fragment_duration)?;
+        } else if self.version == 0 {
+            writer.write_u32::<BigEndian>(self.fragment_duration as u32)?;
+        } else {
+            return Err(Error::InvalidData("version must be 0 or 1"));
+        }
+
+        Ok(self.header_size() - HEADER_SIZE + self.data_size())
+    }
+}
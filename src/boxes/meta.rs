@@ -0,0 +1,76 @@
+use serde::Serialize;
+use std::io::{Read, Seek, SeekFrom, Write};
+
+use super::{BoxHeader, BoxType, Ibox, ReadBox, WriteBox, HEADER_SIZE};
+use crate::error::Error;
+
+type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct MetaBox {
+    pub version: u8,
+    pub flags: u32,
+    // QuickTime meta has no version and flags
+    pub full_box: bool,
+}
+
+impl Ibox for MetaBox {
+    fn typ(&self) -> BoxType {
+        BoxType::Meta
+    }
+
+    fn header_size(&self) -> u64 {
+        if self.full_box {
+            HEADER_SIZE + 4
+        } else {
+            HEADER_SIZE
+        }
+    }
+
+    fn data_size(&self) -> u64 {
+        0
+    }
+
+    fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(self).unwrap())
+    }
+
+    fn summary(&self) -> Result<String> {
+        Ok(String::from(""))
+    }
+}
+
+impl<R: Read + Seek> ReadBox<&mut R> for MetaBox {
+    fn read(reader: &mut R, header: &BoxHeader) -> Result<Self> {
+        // a QuickTime meta starts directly with its hdlr child
+        if header.size >= HEADER_SIZE * 2 {
+            let mut child = [0u8; 8];
+            reader.read_exact(&mut child)?;
+            reader.seek(SeekFrom::Current(-8))?;
+            if &child[4..] == b"hdlr" {
+                return Ok(MetaBox {
+                    version: 0,
+                    flags: 0,
+                    full_box: false,
+                });
+            }
+        }
+
+        let (version, flags) = super::read_box_header_ext(reader)?;
+        Ok(MetaBox {
+            version,
+            flags,
+            full_box: true,
+        })
+    }
+}
+
+impl<W: Write> WriteBox<&mut W> for MetaBox {
+    fn write(&self, writer: &mut W, _: u64) -> Result<u64> {
+        if self.full_box {
+            super::write_box_header_ext(writer, self.version, self.flags)?;
+        }
+
+        Ok(self.header_size() - HEADER_SIZE + self.data_size())
+    }
+}
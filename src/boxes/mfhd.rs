@@ -0,0 +1,62 @@
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use serde::Serialize;
+use std::io::{Read, Seek, Write};
+
+use super::{BoxHeader, BoxType, Ibox, ReadBox, WriteBox, HEADER_SIZE};
+use crate::error::Error;
+
+type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct MfhdBox {
+    pub version: u8,
+    pub flags: u32,
+    pub sequence_number: u32,
+}
+
+impl Ibox for MfhdBox {
+    fn typ(&self) -> BoxType {
+        BoxType::Mfhd
+    }
+
+    fn header_size(&self) -> u64 {
+        HEADER_SIZE + 4
+    }
+
+    fn data_size(&self) -> u64 {
+        4
+    }
+
+    fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(self).unwrap())
+    }
+
+    fn summary(&self) -> Result<String> {
+        let s = format!("sequence_number={}", self.sequence_number);
+        Ok(s)
+    }
+}
+
+impl<R: Read + Seek> ReadBox<&mut R> for MfhdBox {
+    fn read(reader: &mut R, _: &BoxHeader) -> Result<Self> {
+        let (version, flags) = super::read_box_header_ext(reader)?;
+
+        let sequence_number = reader.read_u32::<BigEndian>()?;
+
+        Ok(MfhdBox {
+            version,
+            flags,
+            sequence_number,
+        })
+    }
+}
+
+impl<W: Write> WriteBox<&mut W> for MfhdBox {
+    fn write(&self, writer: &mut W, _: u64) -> Result<u64> {
+        super::write_box_header_ext(writer, self.version, self.flags)?;
+
+        writer.write_u32::<BigEndian>(self.sequence_number)?;
+
+        Ok(self.header_size() - HEADER_SIZE + self.data_size())
+    }
+}
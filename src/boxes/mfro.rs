@@ -0,0 +1,63 @@
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use serde::Serialize;
+use std::io::{Read, Seek, Write};
+
+use super::{BoxHeader, BoxType, Ibox, ReadBox, WriteBox, HEADER_SIZE};
+use crate::error::Error;
+
+type Result<T> = std::result::Result<T, Error>;
+
+// the last box of mfra, so that mfra can be found from the end of the file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct MfroBox {
+    pub version: u8,
+    pub flags: u32,
+    pub size: u32,
+}
+
+impl Ibox for MfroBox {
+    fn typ(&self) -> BoxType {
+        BoxType::Mfro
+    }
+
+    fn header_size(&self) -> u64 {
+        HEADER_SIZE + 4
+    }
+
+    fn data_size(&self) -> u64 {
+        4
+    }
+
+    fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(self).unwrap())
+    }
+
+    fn summary(&self) -> Result<String> {
+        let s = format!("size={}", self.size);
+        Ok(s)
+    }
+}
+
+impl<R: Read + Seek> ReadBox<&mut R> for MfroBox {
+    fn read(reader: &mut R, _: &BoxHeader) -> Result<Self> {
+        let (version, flags) = super::read_box_header_ext(reader)?;
+
+        let size = reader.read_u32::<BigEndian>()?;
+
+        Ok(MfroBox {
+            version,
+            flags,
+            size,
+        })
+    }
+}
+
+impl<W: Write> WriteBox<&mut W> for MfroBox {
+    fn write(&self, writer: &mut W, _: u64) -> Result<u64> {
+        super::write_box_header_ext(writer, self.version, self.flags)?;
+
+        writer.write_u32::<BigEndian>(self.size)?;
+
+        Ok(self.header_size() - HEADER_SIZE + self.data_size())
+    }
+}
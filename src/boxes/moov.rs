@@ -35,6 +35,6 @@ impl<R: Read + Seek> ReadBox<&mut R> for MoovBox {
 
 impl<W: Write> WriteBox<&mut W> for MoovBox {
     fn write(&self, _: &mut W, _: u64) -> Result<u64> {
-        todo!();
+        Ok(0)
     }
 }
@@ -0,0 +1,735 @@
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use serde::Serialize;
+use std::io::{Cursor, Read, Seek, Write};
+
+use super::{BoxHeader, BoxType, Ibox, ReadBox, WriteBox, HEADER_SIZE};
+use crate::bitreader::BitReader;
+use crate::error::Error;
+use crate::types::FixedPointU16;
+
+type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Mp4aBox {
+    pub data_reference_index: u16,
+    // QuickTime sound description version, 0 for ISO files
+    pub version: u16,
+    pub channelcount: u16,
+    pub samplesize: u16,
+    pub samplerate: FixedPointU16,
+    // QuickTime version 1 and 2 fields are kept as is
+    pub ext: Vec<u8>,
+}
+
+impl Ibox for Mp4aBox {
+    fn typ(&self) -> BoxType {
+        BoxType::Mp4a
+    }
+
+    fn data_size(&self) -> u64 {
+        8 + 20 + self.ext.len() as u64
+    }
+
+    fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(self).unwrap())
+    }
+
+    fn summary(&self) -> Result<String> {
+        let s = format!(
+            "data_reference_index={} channelcount={} samplesize={} samplerate={}",
+            self.data_reference_index,
+            self.channelcount,
+            self.samplesize,
+            self.samplerate.value()
+        );
+        Ok(s)
+    }
+}
+
+impl<R: Read + Seek> ReadBox<&mut R> for Mp4aBox {
+    fn read(reader: &mut R, _: &BoxHeader) -> Result<Self> {
+        reader.read_u32::<BigEndian>()?; // reserved
+        reader.read_u16::<BigEndian>()?; // reserved
+        let data_reference_index = reader.read_u16::<BigEndian>()?;
+
+        let version = reader.read_u16::<BigEndian>()?;
+        reader.read_u16::<BigEndian>()?; // reserved
+        reader.read_u32::<BigEndian>()?; // reserved
+        let channelcount = reader.read_u16::<BigEndian>()?;
+        let samplesize = reader.read_u16::<BigEndian>()?;
+        reader.read_u32::<BigEndian>()?; // pre-defined, reserved
+        let samplerate = FixedPointU16::new_raw(reader.read_u32::<BigEndian>()?);
+
+        let ext_size = match version {
+            0 => 0,
+            1 => 16,
+            2 => 36,
+            _ => return Err(Error::InvalidData("version must be 0, 1 or 2")),
+        };
+        let mut ext = vec![0u8; ext_size];
+        reader.read_exact(&mut ext)?;
+
+        Ok(Mp4aBox {
+            data_reference_index,
+            version,
+            channelcount,
+            samplesize,
+            samplerate,
+            ext,
+        })
+    }
+}
+
+impl<W: Write> WriteBox<&mut W> for Mp4aBox {
+    fn write(&self, writer: &mut W, _: u64) -> Result<u64> {
+        writer.write_u32::<BigEndian>(0)?; // reserved
+        writer.write_u16::<BigEndian>(0)?; // reserved
+        writer.write_u16::<BigEndian>(self.data_reference_index)?;
+
+        writer.write_u16::<BigEndian>(self.version)?;
+        writer.write_u16::<BigEndian>(0)?; // reserved
+        writer.write_u32::<BigEndian>(0)?; // reserved
+        writer.write_u16::<BigEndian>(self.channelcount)?;
+        writer.write_u16::<BigEndian>(self.samplesize)?;
+        writer.write_u32::<BigEndian>(0)?; // pre-defined, reserved
+        writer.write_u32::<BigEndian>(self.samplerate.raw_value())?;
+        writer.write_all(&self.ext)?;
+
+        Ok(self.data_size())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct EsdsBox {
+    pub version: u8,
+    pub flags: u32,
+    pub es_desc: EsDescriptor,
+    // descriptor sizes use the shortest encoding instead of the 4 byte form
+    pub compact_size: bool,
+}
+
+impl Ibox for EsdsBox {
+    fn typ(&self) -> BoxType {
+        BoxType::Esds
+    }
+
+    fn header_size(&self) -> u64 {
+        HEADER_SIZE + 4
+    }
+
+    fn data_size(&self) -> u64 {
+        self.es_desc.size(self.compact_size)
+    }
+
+    fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(self).unwrap())
+    }
+
+    fn summary(&self) -> Result<String> {
+        let dec_config = &self.es_desc.dec_config;
+        let s = format!(
+            "es_id={} object_type_indication={:#04x} max_bitrate={} avg_bitrate={}",
+            self.es_desc.es_id,
+            dec_config.object_type_indication,
+            dec_config.max_bitrate,
+            dec_config.avg_bitrate
+        );
+        Ok(s)
+    }
+}
+
+impl<R: Read + Seek> ReadBox<&mut R> for EsdsBox {
+    fn read(reader: &mut R, header: &BoxHeader) -> Result<Self> {
+        let (version, flags) = super::read_box_header_ext(reader)?;
+
+        let data_size = (header.offset + header.size)
+            .checked_sub(reader.stream_position()?)
+            .ok_or(Error::InvalidData("box size too small"))?;
+        let mut buf = vec![0u8; data_size as usize];
+        reader.read_exact(&mut buf)?;
+
+        let mut cursor = Cursor::new(&buf[..]);
+        let (tag, size, compact_size) = read_descriptor_header(&mut cursor)?;
+        if tag != EsDescriptor::TAG {
+            return Err(Error::InvalidData("esds must start with ES_Descriptor"));
+        }
+        let es_desc = EsDescriptor::read(&read_descriptor_body(&mut cursor, size)?)?;
+
+        Ok(EsdsBox {
+            version,
+            flags,
+            es_desc,
+            compact_size,
+        })
+    }
+}
+
+impl<W: Write> WriteBox<&mut W> for EsdsBox {
+    fn write(&self, writer: &mut W, _: u64) -> Result<u64> {
+        super::write_box_header_ext(writer, self.version, self.flags)?;
+
+        self.es_desc.write(writer, self.compact_size)?;
+
+        Ok(self.header_size() - HEADER_SIZE + self.data_size())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct EsDescriptor {
+    pub es_id: u16,
+    pub stream_priority: u8,
+    pub depends_on_es_id: Option<u16>,
+    pub url: Option<Vec<u8>>,
+    pub ocr_es_id: Option<u16>,
+    pub dec_config: DecoderConfigDescriptor,
+    pub sl_config: Option<SlConfigDescriptor>,
+    // descriptors following SLConfigDescriptor are kept as is
+    pub ext: Vec<u8>,
+}
+
+impl EsDescriptor {
+    pub const TAG: u8 = 0x03;
+
+    fn body_size(&self, compact_size: bool) -> u64 {
+        let mut size = 3;
+        if self.depends_on_es_id.is_some() {
+            size += 2;
+        }
+        if let Some(url) = &self.url {
+            size += 1 + url.len() as u64;
+        }
+        if self.ocr_es_id.is_some() {
+            size += 2;
+        }
+        size += self.dec_config.size(compact_size);
+        if let Some(sl_config) = &self.sl_config {
+            size += sl_config.size(compact_size);
+        }
+        size + self.ext.len() as u64
+    }
+
+    fn size(&self, compact_size: bool) -> u64 {
+        descriptor_size(self.body_size(compact_size), compact_size)
+    }
+
+    fn read(buf: &[u8]) -> Result<Self> {
+        let mut reader = Cursor::new(buf);
+        let es_id = reader.read_u16::<BigEndian>()?;
+        let params = reader.read_u8()?;
+        let stream_priority = params & 0x1F;
+
+        let depends_on_es_id = if params & 0x80 != 0 {
+            Some(reader.read_u16::<BigEndian>()?)
+        } else {
+            None
+        };
+        let url = if params & 0x40 != 0 {
+            let length = reader.read_u8()?;
+            let mut url = vec![0u8; length as usize];
+            reader.read_exact(&mut url)?;
+            Some(url)
+        } else {
+            None
+        };
+        let ocr_es_id = if params & 0x20 != 0 {
+            Some(reader.read_u16::<BigEndian>()?)
+        } else {
+            None
+        };
+
+        let (tag, size, _) = read_descriptor_header(&mut reader)?;
+        if tag != DecoderConfigDescriptor::TAG {
+            return Err(Error::InvalidData(
+                "ES_Descriptor must contain DecoderConfigDescriptor",
+            ));
+        }
+        let dec_config = DecoderConfigDescriptor::read(&read_descriptor_body(&mut reader, size)?)?;
+
+        let mut sl_config = None;
+        if peek_descriptor_tag(&reader) == Some(SlConfigDescriptor::TAG) {
+            let (_, size, _) = read_descriptor_header(&mut reader)?;
+            sl_config = Some(SlConfigDescriptor::read(&read_descriptor_body(
+                &mut reader,
+                size,
+            )?)?);
+        }
+
+        let mut ext = Vec::new();
+        reader.read_to_end(&mut ext)?;
+
+        Ok(EsDescriptor {
+            es_id,
+            stream_priority,
+            depends_on_es_id,
+            url,
+            ocr_es_id,
+            dec_config,
+            sl_config,
+            ext,
+        })
+    }
+
+    fn write<W: Write>(&self, writer: &mut W, compact_size: bool) -> Result<u64> {
+        write_descriptor_header(
+            writer,
+            EsDescriptor::TAG,
+            self.body_size(compact_size),
+            compact_size,
+        )?;
+
+        let mut params = self.stream_priority & 0x1F;
+        if self.depends_on_es_id.is_some() {
+            params |= 0x80;
+        }
+        if self.url.is_some() {
+            params |= 0x40;
+        }
+        if self.ocr_es_id.is_some() {
+            params |= 0x20;
+        }
+        writer.write_u16::<BigEndian>(self.es_id)?;
+        writer.write_u8(params)?;
+        if let Some(depends_on_es_id) = self.depends_on_es_id {
+            writer.write_u16::<BigEndian>(depends_on_es_id)?;
+        }
+        if let Some(url) = &self.url {
+            writer.write_u8(url.len() as u8)?;
+            writer.write_all(url)?;
+        }
+        if let Some(ocr_es_id) = self.ocr_es_id {
+            writer.write_u16::<BigEndian>(ocr_es_id)?;
+        }
+        self.dec_config.write(writer, compact_size)?;
+        if let Some(sl_config) = &self.sl_config {
+            sl_config.write(writer, compact_size)?;
+        }
+        writer.write_all(&self.ext)?;
+
+        Ok(self.size(compact_size))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DecoderConfigDescriptor {
+    pub object_type_indication: u8,
+    pub stream_type: u8,
+    pub up_stream: bool,
+    pub buffer_size_db: u32,
+    pub max_bitrate: u32,
+    pub avg_bitrate: u32,
+    pub dec_specific: Option<DecoderSpecificInfo>,
+    // profile level indication descriptors are kept as is
+    pub ext: Vec<u8>,
+}
+
+impl DecoderConfigDescriptor {
+    pub const TAG: u8 = 0x04;
+
+    pub const OBJECT_TYPE_AAC: u8 = 0x40;
+    pub const OBJECT_TYPE_MPEG2_AAC_MAIN: u8 = 0x66;
+    pub const OBJECT_TYPE_MPEG2_AAC_LC: u8 = 0x67;
+    pub const OBJECT_TYPE_MPEG2_AAC_SSR: u8 = 0x68;
+    pub const OBJECT_TYPE_MP3: u8 = 0x6B;
+
+    pub const STREAM_TYPE_VISUAL: u8 = 0x04;
+    pub const STREAM_TYPE_AUDIO: u8 = 0x05;
+
+    fn body_size(&self, compact_size: bool) -> u64 {
+        let mut size = 13;
+        if let Some(dec_specific) = &self.dec_specific {
+            size += dec_specific.size(compact_size);
+        }
+        size + self.ext.len() as u64
+    }
+
+    fn size(&self, compact_size: bool) -> u64 {
+        descriptor_size(self.body_size(compact_size), compact_size)
+    }
+
+    fn read(buf: &[u8]) -> Result<Self> {
+        let mut reader = Cursor::new(buf);
+        let object_type_indication = reader.read_u8()?;
+        let params = reader.read_u8()?;
+        let stream_type = params >> 2;
+        let up_stream = (params >> 1) & 0x1 == 0x1;
+        let buffer_size_db = reader.read_u24::<BigEndian>()?;
+        let max_bitrate = reader.read_u32::<BigEndian>()?;
+        let avg_bitrate = reader.read_u32::<BigEndian>()?;
+
+        let mut dec_specific = None;
+        if peek_descriptor_tag(&reader) == Some(DecoderSpecificInfo::TAG) {
+            let (_, size, _) = read_descriptor_header(&mut reader)?;
+            dec_specific = Some(DecoderSpecificInfo {
+                bytes: read_descriptor_body(&mut reader, size)?,
+            });
+        }
+
+        let mut ext = Vec::new();
+        reader.read_to_end(&mut ext)?;
+
+        Ok(DecoderConfigDescriptor {
+            object_type_indication,
+            stream_type,
+            up_stream,
+            buffer_size_db,
+            max_bitrate,
+            avg_bitrate,
+            dec_specific,
+            ext,
+        })
+    }
+
+    fn write<W: Write>(&self, writer: &mut W, compact_size: bool) -> Result<u64> {
+        write_descriptor_header(
+            writer,
+            DecoderConfigDescriptor::TAG,
+            self.body_size(compact_size),
+            compact_size,
+        )?;
+
+        writer.write_u8(self.object_type_indication)?;
+        // the reserved bit is always 1
+        writer.write_u8((self.stream_type << 2) | ((self.up_stream as u8) << 1) | 0x1)?;
+        writer.write_u24::<BigEndian>(self.buffer_size_db)?;
+        writer.write_u32::<BigEndian>(self.max_bitrate)?;
+        writer.write_u32::<BigEndian>(self.avg_bitrate)?;
+        if let Some(dec_specific) = &self.dec_specific {
+            dec_specific.write(writer, compact_size)?;
+        }
+        writer.write_all(&self.ext)?;
+
+        Ok(self.size(compact_size))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DecoderSpecificInfo {
+    pub bytes: Vec<u8>,
+}
+
+impl DecoderSpecificInfo {
+    pub const TAG: u8 = 0x05;
+
+    fn size(&self, compact_size: bool) -> u64 {
+        descriptor_size(self.bytes.len() as u64, compact_size)
+    }
+
+    fn write<W: Write>(&self, writer: &mut W, compact_size: bool) -> Result<u64> {
+        write_descriptor_header(
+            writer,
+            DecoderSpecificInfo::TAG,
+            self.bytes.len() as u64,
+            compact_size,
+        )?;
+        writer.write_all(&self.bytes)?;
+        Ok(self.size(compact_size))
+    }
+
+    // only meaningful when the object type indication is one of the AAC types
+    pub fn audio_specific_config(&self) -> Result<AudioSpecificConfig> {
+        AudioSpecificConfig::read(&self.bytes)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SlConfigDescriptor {
+    pub predefined: u8,
+    pub ext: Vec<u8>,
+}
+
+impl SlConfigDescriptor {
+    pub const TAG: u8 = 0x06;
+
+    fn size(&self, compact_size: bool) -> u64 {
+        descriptor_size(1 + self.ext.len() as u64, compact_size)
+    }
+
+    fn read(buf: &[u8]) -> Result<Self> {
+        let (predefined, ext) = buf
+            .split_first()
+            .ok_or(Error::InvalidData("SLConfigDescriptor is empty"))?;
+        Ok(SlConfigDescriptor {
+            predefined: *predefined,
+            ext: ext.to_vec(),
+        })
+    }
+
+    fn write<W: Write>(&self, writer: &mut W, compact_size: bool) -> Result<u64> {
+        write_descriptor_header(
+            writer,
+            SlConfigDescriptor::TAG,
+            1 + self.ext.len() as u64,
+            compact_size,
+        )?;
+        writer.write_u8(self.predefined)?;
+        writer.write_all(&self.ext)?;
+        Ok(self.size(compact_size))
+    }
+}
+
+fn descriptor_size_length(size: u64, compact_size: bool) -> u64 {
+    if !compact_size {
+        return 4;
+    }
+    match size {
+        0..=0x7F => 1,
+        0x80..=0x3FFF => 2,
+        0x4000..=0x1F_FFFF => 3,
+        _ => 4,
+    }
+}
+
+fn descriptor_size(body_size: u64, compact_size: bool) -> u64 {
+    1 + descriptor_size_length(body_size, compact_size) + body_size
+}
+
+fn peek_descriptor_tag(reader: &Cursor<&[u8]>) -> Option<u8> {
+    reader.get_ref().get(reader.position() as usize).copied()
+}
+
+fn read_descriptor_header<R: Read>(reader: &mut R) -> Result<(u8, u64, bool)> {
+    let tag = reader.read_u8()?;
+    let mut size = 0u64;
+    let mut length = 0;
+    loop {
+        let b = reader.read_u8()?;
+        size = (size << 7) | (b & 0x7F) as u64;
+        length += 1;
+        if b & 0x80 == 0 {
+            break;
+        }
+        if length == 4 {
+            return Err(Error::InvalidData("descriptor size is too long"));
+        }
+    }
+    Ok((tag, size, length == descriptor_size_length(size, true)))
+}
+
+fn read_descriptor_body<R: Read>(reader: &mut R, size: u64) -> Result<Vec<u8>> {
+    let mut body = vec![0u8; size as usize];
+    reader.read_exact(&mut body)?;
+    Ok(body)
+}
+
+fn write_descriptor_header<W: Write>(
+    writer: &mut W,
+    tag: u8,
+    size: u64,
+    compact_size: bool,
+) -> Result<()> {
+    writer.write_u8(tag)?;
+    let length = descriptor_size_length(size, compact_size);
+    for i in (0..length).rev() {
+        let b = ((size >> (7 * i)) & 0x7F) as u8;
+        writer.write_u8(if i > 0 { b | 0x80 } else { b })?;
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AudioSpecificConfig {
+    pub audio_object_type: u8,
+    pub sampling_frequency_index: u8,
+    pub sampling_frequency: u32,
+    pub channel_configuration: u8,
+    pub sbr_present: bool,
+    pub ps_present: bool,
+    // AOT_SBR when SBR or PS is signalled, audio_object_type is then the core codec
+    pub extension_audio_object_type: Option<u8>,
+    pub extension_sampling_frequency_index: Option<u8>,
+    pub extension_sampling_frequency: Option<u32>,
+}
+
+impl AudioSpecificConfig {
+    pub const AOT_AAC_MAIN: u8 = 1;
+    pub const AOT_AAC_LC: u8 = 2;
+    pub const AOT_AAC_SSR: u8 = 3;
+    pub const AOT_AAC_LTP: u8 = 4;
+    pub const AOT_SBR: u8 = 5;
+    pub const AOT_PS: u8 = 29;
+
+    pub const SAMPLING_FREQUENCIES: [u32; 13] = [
+        96000, 88200, 64000, 48000, 44100, 32000, 24000, 22050, 16000, 12000, 11025, 8000, 7350,
+    ];
+
+    pub fn read(bytes: &[u8]) -> Result<Self> {
+        let mut reader = BitReader::new(bytes);
+
+        let mut audio_object_type = read_audio_object_type(&mut reader)?;
+        let (sampling_frequency_index, sampling_frequency) = read_sampling_frequency(&mut reader)?;
+        let channel_configuration = reader.read_bits(4)? as u8;
+
+        let mut sbr_present = false;
+        let mut ps_present = false;
+        let mut extension_audio_object_type = None;
+        let mut extension_sampling_frequency_index = None;
+        let mut extension_sampling_frequency = None;
+
+        // explicit hierarchical signalling
+        if audio_object_type == Self::AOT_SBR || audio_object_type == Self::AOT_PS {
+            sbr_present = true;
+            ps_present = audio_object_type == Self::AOT_PS;
+            extension_audio_object_type = Some(Self::AOT_SBR);
+            let (index, frequency) = read_sampling_frequency(&mut reader)?;
+            extension_sampling_frequency_index = Some(index);
+            extension_sampling_frequency = Some(frequency);
+            audio_object_type = read_audio_object_type(&mut reader)?;
+        }
+
+        // backward compatible signalling follows GASpecificConfig
+        if matches!(audio_object_type, 1..=4 | 6 | 7)
+            && channel_configuration != 0
+            && Self::skip_ga_specific_config(&mut reader, audio_object_type).is_ok()
+            && reader.remaining() >= 16
+            && reader.read_bits(11)? == 0x2B7
+        {
+            let extension_type = read_audio_object_type(&mut reader)?;
+            if extension_type == Self::AOT_SBR && reader.read_bit()? {
+                sbr_present = true;
+                extension_audio_object_type = Some(Self::AOT_SBR);
+                let (index, frequency) = read_sampling_frequency(&mut reader)?;
+                extension_sampling_frequency_index = Some(index);
+                extension_sampling_frequency = Some(frequency);
+                if reader.remaining() >= 12 && reader.read_bits(11)? == 0x548 {
+                    ps_present = reader.read_bit()?;
+                }
+            }
+        }
+
+        Ok(AudioSpecificConfig {
+            audio_object_type,
+            sampling_frequency_index,
+            sampling_frequency,
+            channel_configuration,
+            sbr_present,
+            ps_present,
+            extension_audio_object_type,
+            extension_sampling_frequency_index,
+            extension_sampling_frequency,
+        })
+    }
+
+    fn skip_ga_specific_config(reader: &mut BitReader, audio_object_type: u8) -> Result<()> {
+        reader.read_bit()?; // frame_length_flag
+        if reader.read_bit()? {
+            reader.skip_bits(14)?; // core_coder_delay
+        }
+        let extension_flag = reader.read_bit()?;
+        if audio_object_type == 6 {
+            reader.skip_bits(3)?; // layer_nr
+        }
+        if extension_flag {
+            reader.skip_bits(1)?; // extension_flag3
+        }
+        Ok(())
+    }
+
+    // the number of output channels, None when a program config element is used
+    pub fn channel_count(&self) -> Option<u16> {
+        match self.channel_configuration {
+            1..=6 => Some(self.channel_configuration as u16),
+            7 => Some(8),
+            _ => None,
+        }
+    }
+
+    // the sampling frequency of the decoded output including SBR
+    pub fn output_sampling_frequency(&self) -> u32 {
+        match (self.sbr_present, self.extension_sampling_frequency) {
+            (true, Some(frequency)) => frequency,
+            _ => self.sampling_frequency,
+        }
+    }
+}
+
+fn read_audio_object_type(reader: &mut BitReader) -> Result<u8> {
+    let audio_object_type = reader.read_bits(5)? as u8;
+    if audio_object_type == 31 {
+        Ok(32 + reader.read_bits(6)? as u8)
+    } else {
+        Ok(audio_object_type)
+    }
+}
+
+fn read_sampling_frequency(reader: &mut BitReader) -> Result<(u8, u32)> {
+    let index = reader.read_bits(4)? as u8;
+    let frequency = match index {
+        0x0F => reader.read_bits(24)? as u32,
+        _ => *AudioSpecificConfig::SAMPLING_FREQUENCIES
+            .get(index as usize)
+            .ok_or(Error::InvalidData("reserved sampling frequency index"))?,
+    };
+    Ok((index, frequency))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn audio_specific_config_aac_lc() {
+        // 44100 Hz stereo
+        let asc = AudioSpecificConfig::read(&[0x12, 0x10]).unwrap();
+        assert_eq!(asc.audio_object_type, AudioSpecificConfig::AOT_AAC_LC);
+        assert_eq!(asc.sampling_frequency_index, 4);
+        assert_eq!(asc.sampling_frequency, 44100);
+        assert_eq!(asc.channel_count(), Some(2));
+        assert!(!asc.sbr_present);
+        assert!(!asc.ps_present);
+        assert_eq!(asc.extension_audio_object_type, None);
+        assert_eq!(asc.output_sampling_frequency(), 44100);
+    }
+
+    #[test]
+    fn audio_specific_config_he_aac_explicit() {
+        // SBR at 48000 Hz over an AAC-LC core at 24000 Hz, stereo
+        let asc = AudioSpecificConfig::read(&[0x2B, 0x11, 0x88, 0x00]).unwrap();
+        assert_eq!(asc.audio_object_type, AudioSpecificConfig::AOT_AAC_LC);
+        assert_eq!(asc.sampling_frequency, 24000);
+        assert_eq!(asc.channel_count(), Some(2));
+        assert!(asc.sbr_present);
+        assert!(!asc.ps_present);
+        assert_eq!(
+            asc.extension_audio_object_type,
+            Some(AudioSpecificConfig::AOT_SBR)
+        );
+        assert_eq!(asc.extension_sampling_frequency_index, Some(3));
+        assert_eq!(asc.output_sampling_frequency(), 48000);
+    }
+
+    #[test]
+    fn audio_specific_config_he_aac_backward_compatible() {
+        // the same stream signalled after GASpecificConfig with the 0x2B7 sync
+        let asc = AudioSpecificConfig::read(&[0x13, 0x10, 0x56, 0xE5, 0x98]).unwrap();
+        assert_eq!(asc.audio_object_type, AudioSpecificConfig::AOT_AAC_LC);
+        assert_eq!(asc.sampling_frequency, 24000);
+        assert!(asc.sbr_present);
+        assert!(!asc.ps_present);
+        assert_eq!(
+            asc.extension_audio_object_type,
+            Some(AudioSpecificConfig::AOT_SBR)
+        );
+        assert_eq!(asc.output_sampling_frequency(), 48000);
+    }
+
+    #[test]
+    fn audio_specific_config_he_aac_v2() {
+        // PS over SBR at 48000 Hz, the core is mono at 24000 Hz
+        let asc = AudioSpecificConfig::read(&[0xEB, 0x09, 0x88, 0x00]).unwrap();
+        assert_eq!(asc.audio_object_type, AudioSpecificConfig::AOT_AAC_LC);
+        assert_eq!(asc.sampling_frequency, 24000);
+        assert_eq!(asc.channel_count(), Some(1));
+        assert!(asc.sbr_present);
+        assert!(asc.ps_present);
+        assert_eq!(
+            asc.extension_audio_object_type,
+            Some(AudioSpecificConfig::AOT_SBR)
+        );
+        assert_eq!(asc.output_sampling_frequency(), 48000);
+
+        let asc = AudioSpecificConfig::read(&[0x13, 0x08, 0x56, 0xE5, 0x9D, 0x48, 0x80]).unwrap();
+        assert!(asc.sbr_present);
+        assert!(asc.ps_present);
+        assert_eq!(asc.channel_count(), Some(1));
+    }
+}
@@ -1,4 +1,4 @@
-use byteorder::{BigEndian, ReadBytesExt};
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
 use serde::Serialize;
 use std::io::{Read, Seek, Write};
 
@@ -45,7 +45,10 @@ impl<R: Read + Seek> ReadBox<&mut R> for PaspBox {
 }
 
 impl<W: Write> WriteBox<&mut W> for PaspBox {
-    fn write(&self, _: &mut W, _: u64) -> Result<u64> {
-        todo!();
+    fn write(&self, writer: &mut W, _: u64) -> Result<u64> {
+        writer.write_u32::<BigEndian>(self.h_spacing)?;
+        writer.write_u32::<BigEndian>(self.v_spacing)?;
+
+        Ok(self.data_size())
     }
 }
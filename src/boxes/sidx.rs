@@ -0,0 +1,186 @@
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use serde::Serialize;
+use std::io::{Read, Seek, Write};
+
+use super::{BoxHeader, BoxType, Ibox, ReadBox, WriteBox, HEADER_SIZE};
+use crate::error::Error;
+
+type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SidxBox {
+    pub version: u8,
+    pub flags: u32,
+    pub reference_id: u32,
+    pub timescale: u32,
+    pub earliest_presentation_time: u64,
+    pub first_offset: u64,
+    pub references: Vec<SidxReference>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct SidxReference {
+    // true if the reference points to another sidx instead of media
+    pub reference_type: bool,
+    pub referenced_size: u32,
+    pub subsegment_duration: u32,
+    pub starts_with_sap: bool,
+    pub sap_type: u8,
+    pub sap_delta_time: u32,
+}
+
+// a reference resolved to an absolute byte range and a presentation time range
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct SidxRange {
+    pub offset: u64,
+    pub size: u64,
+    pub presentation_time: u64,
+    pub duration: u64,
+    pub reference_type: bool,
+    pub starts_with_sap: bool,
+}
+
+impl SidxRange {
+    // inclusive last byte, as used in an HTTP Range header; None for an empty range
+    pub fn last_byte(&self) -> Option<u64> {
+        self.size.checked_sub(1).map(|n| self.offset + n)
+    }
+}
+
+impl SidxBox {
+    // anchor is the offset of the first byte following this sidx
+    pub fn resolve(&self, anchor: u64) -> Vec<SidxRange> {
+        let mut offset = anchor + self.first_offset;
+        let mut presentation_time = self.earliest_presentation_time;
+        self.references
+            .iter()
+            .map(|r| {
+                let range = SidxRange {
+                    offset,
+                    size: r.referenced_size as u64,
+                    presentation_time,
+                    duration: r.subsegment_duration as u64,
+                    reference_type: r.reference_type,
+                    starts_with_sap: r.starts_with_sap,
+                };
+                offset += r.referenced_size as u64;
+                presentation_time += r.subsegment_duration as u64;
+                range
+            })
+            .collect()
+    }
+}
+
+impl Ibox for SidxBox {
+    fn typ(&self) -> BoxType {
+        BoxType::Sidx
+    }
+
+    fn header_size(&self) -> u64 {
+        HEADER_SIZE + 4
+    }
+
+    fn data_size(&self) -> u64 {
+        let times_size = if self.version == 1 { 16 } else { 8 };
+        8 + times_size + 4 + self.references.len() as u64 * 12
+    }
+
+    fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(self).unwrap())
+    }
+
+    fn summary(&self) -> Result<String> {
+        let s = format!(
+            "reference_id={} timescale={} earliest_presentation_time={} first_offset={} reference_count={}",
+            self.reference_id,
+            self.timescale,
+            self.earliest_presentation_time,
+            self.first_offset,
+            self.references.len()
+        );
+        Ok(s)
+    }
+}
+
+impl<R: Read + Seek> ReadBox<&mut R> for SidxBox {
+    fn read(reader: &mut R, _: &BoxHeader) -> Result<Self> {
+        let (version, flags) = super::read_box_header_ext(reader)?;
+
+        let reference_id = reader.read_u32::<BigEndian>()?;
+        let timescale = reader.read_u32::<BigEndian>()?;
+        let (earliest_presentation_time, first_offset) = if version == 1 {
+            (
+                reader.read_u64::<BigEndian>()?,
+                reader.read_u64::<BigEndian>()?,
+            )
+        } else if version == 0 {
+            (
+                reader.read_u32::<BigEndian>()? as u64,
+                reader.read_u32::<BigEndian>()? as u64,
+            )
+        } else {
+            return Err(Error::InvalidData("version must be 0 or 1"));
+        };
+        reader.read_u16::<BigEndian>()?; // reserved
+        let reference_count = reader.read_u16::<BigEndian>()?;
+
+        let mut references = Vec::with_capacity(reference_count as usize);
+        for _ in 0..reference_count {
+            let params = reader.read_u32::<BigEndian>()?;
+            let subsegment_duration = reader.read_u32::<BigEndian>()?;
+            let sap = reader.read_u32::<BigEndian>()?;
+            references.push(SidxReference {
+                reference_type: params >> 31 == 0x1,
+                referenced_size: params & 0x7FFF_FFFF,
+                subsegment_duration,
+                starts_with_sap: sap >> 31 == 0x1,
+                sap_type: ((sap >> 28) & 0x7) as u8,
+                sap_delta_time: sap & 0x0FFF_FFFF,
+            });
+        }
+
+        Ok(SidxBox {
+            version,
+            flags,
+            reference_id,
+            timescale,
+            earliest_presentation_time,
+            first_offset,
+            references,
+        })
+    }
+}
+
+impl<W: Write> WriteBox<&mut W> for SidxBox {
+    fn write(&self, writer: &mut W, _: u64) -> Result<u64> {
+        super::write_box_header_ext(writer, self.version, self.flags)?;
+
+        writer.write_u32::<BigEndian>(self.reference_id)?;
+        writer.write_u32::<BigEndian>(self.timescale)?;
+        if self.version == 1 {
+            writer.write_u64::<BigEndian>(self.earliest_presentation_time)?;
+            writer.write_u64::<BigEndian>(self.first_offset)?;
+        } else if self.version == 0 {
+            writer.write_u32::<BigEndian>(self.earliest_presentation_time as u32)?;
+            writer.write_u32::<BigEndian>(self.first_offset as u32)?;
+        } else {
+            return Err(Error::InvalidData("version must be 0 or 1"));
+        }
+        writer.write_u16::<BigEndian>(0)?; // reserved
+        writer.write_u16::<BigEndian>(self.references.len() as u16)?;
+
+        for r in self.references.iter() {
+            writer.write_u32::<BigEndian>(
+                ((r.reference_type as u32) << 31) | (r.referenced_size & 0x7FFF_FFFF),
+            )?;
+            writer.write_u32::<BigEndian>(r.subsegment_duration)?;
+            writer.write_u32::<BigEndian>(
+                ((r.starts_with_sap as u32) << 31)
+                    | ((r.sap_type as u32 & 0x7) << 28)
+                    | (r.sap_delta_time & 0x0FFF_FFFF),
+            )?;
+        }
+
+        Ok(self.header_size() - HEADER_SIZE + self.data_size())
+    }
+}
@@ -1,4 +1,4 @@
-use byteorder::{BigEndian, ReadBytesExt};
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
 use serde::Serialize;
 use std::io::{Read, Seek, Write};
 
@@ -53,7 +53,12 @@ impl<R: Read + Seek> ReadBox<&mut R> for SmhdBox {
 }
 
 impl<W: Write> WriteBox<&mut W> for SmhdBox {
-    fn write(&self, _: &mut W, _: u64) -> Result<u64> {
-        todo!();
+    fn write(&self, writer: &mut W, _: u64) -> Result<u64> {
+        super::write_box_header_ext(writer, self.version, self.flags)?;
+
+        writer.write_i16::<BigEndian>(self.balance.raw_value())?;
+        writer.write_u16::<BigEndian>(0)?; // reserved
+
+        Ok(self.header_size() - HEADER_SIZE + self.data_size())
     }
 }
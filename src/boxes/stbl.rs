@@ -35,6 +35,6 @@ impl<R: Read + Seek> ReadBox<&mut R> for StblBox {
 
 impl<W: Write> WriteBox<&mut W> for StblBox {
     fn write(&self, _: &mut W, _: u64) -> Result<u64> {
-        todo!();
+        Ok(0)
     }
 }
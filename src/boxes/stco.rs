@@ -1,4 +1,4 @@
-use byteorder::{BigEndian, ReadBytesExt};
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
 use serde::Serialize;
 use std::io::{Read, Seek, Write};
 
@@ -57,7 +57,14 @@ impl<R: Read + Seek> ReadBox<&mut R> for StcoBox {
 }
 
 impl<W: Write> WriteBox<&mut W> for StcoBox {
-    fn write(&self, _: &mut W, _: u64) -> Result<u64> {
-        todo!();
+    fn write(&self, writer: &mut W, _: u64) -> Result<u64> {
+        super::write_box_header_ext(writer, self.version, self.flags)?;
+
+        writer.write_u32::<BigEndian>(self.chunk_offsets.len() as u32)?;
+        for chunk_offset in self.chunk_offsets.iter() {
+            writer.write_i32::<BigEndian>(*chunk_offset)?;
+        }
+
+        Ok(self.header_size() - HEADER_SIZE + self.data_size())
     }
 }
@@ -1,4 +1,4 @@
-use byteorder::{BigEndian, ReadBytesExt};
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
 use serde::Serialize;
 use std::io::{Read, Seek, Write};
 
@@ -90,7 +90,16 @@ impl<R: Read + Seek> ReadBox<&mut R> for StscBox {
 }
 
 impl<W: Write> WriteBox<&mut W> for StscBox {
-    fn write(&self, _: &mut W, _: u64) -> Result<u64> {
-        todo!();
+    fn write(&self, writer: &mut W, _: u64) -> Result<u64> {
+        super::write_box_header_ext(writer, self.version, self.flags)?;
+
+        writer.write_u32::<BigEndian>(self.entries.len() as u32)?;
+        for entry in self.entries.iter() {
+            writer.write_u32::<BigEndian>(entry.first_chunk)?;
+            writer.write_u32::<BigEndian>(entry.sample_per_chunk)?;
+            writer.write_u32::<BigEndian>(entry.sample_description_index)?;
+        }
+
+        Ok(self.header_size() - HEADER_SIZE + self.data_size())
     }
 }
@@ -1,4 +1,4 @@
-use byteorder::{BigEndian, ReadBytesExt};
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
 use serde::Serialize;
 use std::io::{Read, Seek, Write};
 
@@ -65,7 +65,20 @@ impl<R: Read + Seek> ReadBox<&mut R> for StszBox {
 }
 
 impl<W: Write> WriteBox<&mut W> for StszBox {
-    fn write(&self, _: &mut W, _: u64) -> Result<u64> {
-        todo!();
+    fn write(&self, writer: &mut W, _: u64) -> Result<u64> {
+        super::write_box_header_ext(writer, self.version, self.flags)?;
+
+        writer.write_u32::<BigEndian>(self.sample_size)?;
+        writer.write_u32::<BigEndian>(self.sample_count)?;
+        if self.sample_size == 0 {
+            if self.sample_count as usize != self.sample_sizes.len() {
+                return Err(Error::InvalidData("stsz sample_count mismatch"));
+            }
+            for sample_number in self.sample_sizes.iter() {
+                writer.write_u32::<BigEndian>(*sample_number)?;
+            }
+        }
+
+        Ok(self.header_size() - HEADER_SIZE + self.data_size())
     }
 }
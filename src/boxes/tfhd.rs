@@ -0,0 +1,138 @@
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use serde::Serialize;
+use std::io::{Read, Seek, Write};
+
+use super::{BoxHeader, BoxType, Ibox, ReadBox, WriteBox, HEADER_SIZE};
+use crate::error::Error;
+
+type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TfhdBox {
+    pub version: u8,
+    pub flags: u32,
+    pub track_id: u32,
+    pub base_data_offset: Option<u64>,
+    pub sample_description_index: Option<u32>,
+    pub default_sample_duration: Option<u32>,
+    pub default_sample_size: Option<u32>,
+    pub default_sample_flags: Option<u32>,
+}
+
+impl TfhdBox {
+    pub const FLAG_BASE_DATA_OFFSET: u32 = 0x01;
+    pub const FLAG_SAMPLE_DESCRIPTION_INDEX: u32 = 0x02;
+    pub const FLAG_DEFAULT_SAMPLE_DURATION: u32 = 0x08;
+    pub const FLAG_DEFAULT_SAMPLE_SIZE: u32 = 0x10;
+    pub const FLAG_DEFAULT_SAMPLE_FLAGS: u32 = 0x20;
+    pub const FLAG_DURATION_IS_EMPTY: u32 = 0x010000;
+    pub const FLAG_DEFAULT_BASE_IS_MOOF: u32 = 0x020000;
+}
+
+impl Ibox for TfhdBox {
+    fn typ(&self) -> BoxType {
+        BoxType::Tfhd
+    }
+
+    fn header_size(&self) -> u64 {
+        HEADER_SIZE + 4
+    }
+
+    fn data_size(&self) -> u64 {
+        let mut size = 4;
+        if self.flags & TfhdBox::FLAG_BASE_DATA_OFFSET != 0 {
+            size += 8;
+        }
+        if self.flags & TfhdBox::FLAG_SAMPLE_DESCRIPTION_INDEX != 0 {
+            size += 4;
+        }
+        if self.flags & TfhdBox::FLAG_DEFAULT_SAMPLE_DURATION != 0 {
+            size += 4;
+        }
+        if self.flags & TfhdBox::FLAG_DEFAULT_SAMPLE_SIZE != 0 {
+            size += 4;
+        }
+        if self.flags & TfhdBox::FLAG_DEFAULT_SAMPLE_FLAGS != 0 {
+            size += 4;
+        }
+        size
+    }
+
+    fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(self).unwrap())
+    }
+
+    fn summary(&self) -> Result<String> {
+        let s = format!("track_id={} flags={:#x}", self.track_id, self.flags);
+        Ok(s)
+    }
+}
+
+impl<R: Read + Seek> ReadBox<&mut R> for TfhdBox {
+    fn read(reader: &mut R, _: &BoxHeader) -> Result<Self> {
+        let (version, flags) = super::read_box_header_ext(reader)?;
+
+        let track_id = reader.read_u32::<BigEndian>()?;
+        let base_data_offset = if flags & TfhdBox::FLAG_BASE_DATA_OFFSET != 0 {
+            Some(reader.read_u64::<BigEndian>()?)
+        } else {
+            None
+        };
+        let sample_description_index = if flags & TfhdBox::FLAG_SAMPLE_DESCRIPTION_INDEX != 0 {
+            Some(reader.read_u32::<BigEndian>()?)
+        } else {
+            None
+        };
+        let default_sample_duration = if flags & TfhdBox::FLAG_DEFAULT_SAMPLE_DURATION != 0 {
+            Some(reader.read_u32::<BigEndian>()?)
+        } else {
+            None
+        };
+        let default_sample_size = if flags & TfhdBox::FLAG_DEFAULT_SAMPLE_SIZE != 0 {
+            Some(reader.read_u32::<BigEndian>()?)
+        } else {
+            None
+        };
+        let default_sample_flags = if flags & TfhdBox::FLAG_DEFAULT_SAMPLE_FLAGS != 0 {
+            Some(reader.read_u32::<BigEndian>()?)
+        } else {
+            None
+        };
+
+        Ok(TfhdBox {
+            version,
+            flags,
+            track_id,
+            base_data_offset,
+            sample_description_index,
+            default_sample_duration,
+            default_sample_size,
+            default_sample_flags,
+        })
+    }
+}
+
+impl<W: Write> WriteBox<&mut W> for TfhdBox {
+    fn write(&self, writer: &mut W, _: u64) -> Result<u64> {
+        super::write_box_header_ext(writer, self.version, self.flags)?;
+
+        writer.write_u32::<BigEndian>(self.track_id)?;
+        if self.flags & TfhdBox::FLAG_BASE_DATA_OFFSET != 0 {
+            writer.write_u64::<BigEndian>(self.base_data_offset.unwrap_or_default())?;
+        }
+        if self.flags & TfhdBox::FLAG_SAMPLE_DESCRIPTION_INDEX != 0 {
+            writer.write_u32::<BigEndian>(self.sample_description_index.unwrap_or_default())?;
+        }
+        if self.flags & TfhdBox::FLAG_DEFAULT_SAMPLE_DURATION != 0 {
+            writer.write_u32::<BigEndian>(self.default_sample_duration.unwrap_or_default())?;
+        }
+        if self.flags & TfhdBox::FLAG_DEFAULT_SAMPLE_SIZE != 0 {
+            writer.write_u32::<BigEndian>(self.default_sample_size.unwrap_or_default())?;
+        }
+        if self.flags & TfhdBox::FLAG_DEFAULT_SAMPLE_FLAGS != 0 {
+            writer.write_u32::<BigEndian>(self.default_sample_flags.unwrap_or_default())?;
+        }
+
+        Ok(self.header_size() - HEADER_SIZE + self.data_size())
+    }
+}
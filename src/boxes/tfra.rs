@@ -0,0 +1,231 @@
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use serde::Serialize;
+use std::io::{Read, Seek, Write};
+
+use super::{BoxHeader, BoxType, Ibox, ReadBox, WriteBox, HEADER_SIZE};
+use crate::error::Error;
+
+type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TfraBox {
+    pub version: u8,
+    pub flags: u32,
+    pub track_id: u32,
+    // field sizes in bytes minus one, as stored in the box
+    pub length_size_of_traf_num: u8,
+    pub length_size_of_trun_num: u8,
+    pub length_size_of_sample_num: u8,
+    pub entries: Vec<TfraEntry>,
+}
+
+// traf, trun and sample numbers are 1-based
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct TfraEntry {
+    pub time: u64,
+    pub moof_offset: u64,
+    pub traf_number: u32,
+    pub trun_number: u32,
+    pub sample_number: u32,
+}
+
+impl TfraBox {
+    // the last entry at or before the time, given in the timescale of the track
+    pub fn entry_at(&self, time: u64) -> Option<&TfraEntry> {
+        let i = self.entries.partition_point(|e| e.time <= time);
+        i.checked_sub(1).map(|i| &self.entries[i])
+    }
+
+    fn entry_size(&self) -> u64 {
+        let times_size = if self.version == 1 { 16 } else { 8 };
+        times_size
+            + self.length_size_of_traf_num as u64
+            + self.length_size_of_trun_num as u64
+            + self.length_size_of_sample_num as u64
+            + 3
+    }
+}
+
+impl Ibox for TfraBox {
+    fn typ(&self) -> BoxType {
+        BoxType::Tfra
+    }
+
+    fn header_size(&self) -> u64 {
+        HEADER_SIZE + 4
+    }
+
+    fn data_size(&self) -> u64 {
+        12 + self.entries.len() as u64 * self.entry_size()
+    }
+
+    fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(self).unwrap())
+    }
+
+    fn summary(&self) -> Result<String> {
+        let s = format!(
+            "track_id={} entry_count={}",
+            self.track_id,
+            self.entries.len()
+        );
+        Ok(s)
+    }
+}
+
+impl<R: Read + Seek> ReadBox<&mut R> for TfraBox {
+    fn read(reader: &mut R, _: &BoxHeader) -> Result<Self> {
+        let (version, flags) = super::read_box_header_ext(reader)?;
+        if version > 1 {
+            return Err(Error::InvalidData("version must be 0 or 1"));
+        }
+
+        let track_id = reader.read_u32::<BigEndian>()?;
+        let lengths = reader.read_u32::<BigEndian>()?;
+        let length_size_of_traf_num = ((lengths >> 4) & 0x3) as u8;
+        let length_size_of_trun_num = ((lengths >> 2) & 0x3) as u8;
+        let length_size_of_sample_num = (lengths & 0x3) as u8;
+        let entry_count = reader.read_u32::<BigEndian>()?;
+
+        let mut entries = Vec::new();
+        for _ in 0..entry_count {
+            let (time, moof_offset) = if version == 1 {
+                (
+                    reader.read_u64::<BigEndian>()?,
+                    reader.read_u64::<BigEndian>()?,
+                )
+            } else {
+                (
+                    reader.read_u32::<BigEndian>()? as u64,
+                    reader.read_u32::<BigEndian>()? as u64,
+                )
+            };
+            entries.push(TfraEntry {
+                time,
+                moof_offset,
+                traf_number: read_number(reader, length_size_of_traf_num)?,
+                trun_number: read_number(reader, length_size_of_trun_num)?,
+                sample_number: read_number(reader, length_size_of_sample_num)?,
+            });
+        }
+
+        Ok(TfraBox {
+            version,
+            flags,
+            track_id,
+            length_size_of_traf_num,
+            length_size_of_trun_num,
+            length_size_of_sample_num,
+            entries,
+        })
+    }
+}
+
+impl<W: Write> WriteBox<&mut W> for TfraBox {
+    fn write(&self, writer: &mut W, _: u64) -> Result<u64> {
+        if self.version > 1 {
+            return Err(Error::InvalidData("version must be 0 or 1"));
+        }
+        super::write_box_header_ext(writer, self.version, self.flags)?;
+
+        writer.write_u32::<BigEndian>(self.track_id)?;
+        writer.write_u32::<BigEndian>(
+            ((self.length_size_of_traf_num as u32 & 0x3) << 4)
+                | ((self.length_size_of_trun_num as u32 & 0x3) << 2)
+                | (self.length_size_of_sample_num as u32 & 0x3),
+        )?;
+        writer.write_u32::<BigEndian>(self.entries.len() as u32)?;
+
+        for e in self.entries.iter() {
+            if self.version == 1 {
+                writer.write_u64::<BigEndian>(e.time)?;
+                writer.write_u64::<BigEndian>(e.moof_offset)?;
+            } else {
+                writer.write_u32::<BigEndian>(e.time as u32)?;
+                writer.write_u32::<BigEndian>(e.moof_offset as u32)?;
+            }
+            write_number(writer, self.length_size_of_traf_num, e.traf_number)?;
+            write_number(writer, self.length_size_of_trun_num, e.trun_number)?;
+            write_number(writer, self.length_size_of_sample_num, e.sample_number)?;
+        }
+
+        Ok(self.header_size() - HEADER_SIZE + self.data_size())
+    }
+}
+
+fn read_number<R: Read>(reader: &mut R, length_size: u8) -> Result<u32> {
+    Ok(reader.read_uint::<BigEndian>(length_size as usize + 1)? as u32)
+}
+
+fn write_number<W: Write>(writer: &mut W, length_size: u8, value: u32) -> Result<()> {
+    if length_size < 3 && value >> ((length_size as u32 + 1) * 8) != 0 {
+        return Err(Error::InvalidData("tfra number does not fit in its length"));
+    }
+    writer.write_uint::<BigEndian>(value as u64, length_size as usize + 1)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn tfra(version: u8, entries: Vec<TfraEntry>) -> TfraBox {
+        TfraBox {
+            version,
+            flags: 0,
+            track_id: 1,
+            length_size_of_traf_num: 0,
+            length_size_of_trun_num: 1,
+            length_size_of_sample_num: 3,
+            entries,
+        }
+    }
+
+    fn round_trip(tfra: &TfraBox) {
+        let mut buf = vec![0u8; HEADER_SIZE as usize];
+        let size = tfra.write(&mut buf, 0).unwrap();
+        assert_eq!(size, tfra.header_size() - HEADER_SIZE + tfra.data_size());
+        assert_eq!(buf.len() as u64, HEADER_SIZE + size);
+
+        let header = BoxHeader::new(BoxType::Tfra, buf.len() as u64, 0);
+        let mut reader = Cursor::new(&buf);
+        reader.set_position(HEADER_SIZE);
+        assert_eq!(&TfraBox::read(&mut reader, &header).unwrap(), tfra);
+    }
+
+    #[test]
+    fn tfra_versions() {
+        let entry = TfraEntry {
+            time: 0,
+            moof_offset: 1024,
+            traf_number: 1,
+            trun_number: 0x1234,
+            sample_number: 0x0102_0304,
+        };
+        round_trip(&tfra(0, vec![entry]));
+        let large = TfraEntry {
+            time: 1 << 33,
+            moof_offset: 1 << 34,
+            ..entry
+        };
+        round_trip(&tfra(1, vec![entry, large]));
+    }
+
+    #[test]
+    fn tfra_write_checks_before_writing() {
+        let entry = TfraEntry {
+            time: 0,
+            moof_offset: 0,
+            traf_number: 0x100,
+            trun_number: 1,
+            sample_number: 1,
+        };
+        // traf numbers take a single byte
+        assert!(tfra(0, vec![entry]).write(&mut Vec::new(), 0).is_err());
+
+        let mut buf = Vec::new();
+        assert!(tfra(2, Vec::new()).write(&mut buf, 0).is_err());
+        assert!(buf.is_empty());
+    }
+}
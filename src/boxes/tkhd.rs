@@ -1,4 +1,4 @@
-use byteorder::{BigEndian, ReadBytesExt};
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
 use serde::Serialize;
 use std::io::{Read, Seek, Write};
 
@@ -21,6 +21,22 @@ pub struct Matrix {
     pub w: i32,
 }
 
+impl Matrix {
+    pub fn identity() -> Self {
+        Matrix {
+            a: 0x0001_0000,
+            b: 0,
+            u: 0,
+            c: 0,
+            d: 0x0001_0000,
+            v: 0,
+            x: 0,
+            y: 0,
+            w: 0x4000_0000,
+        }
+    }
+}
+
 impl std::fmt::Display for Matrix {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
@@ -144,7 +160,44 @@ impl<R: Read + Seek> ReadBox<&mut R> for TkhdBox {
 }
 
 impl<W: Write> WriteBox<&mut W> for TkhdBox {
-    fn write(&self, _: &mut W, _: u64) -> Result<u64> {
-        todo!();
+    fn write(&self, writer: &mut W, _: u64) -> Result<u64> {
+        super::write_box_header_ext(writer, self.version, self.flags)?;
+
+        if self.version == 1 {
+            writer.write_u64::<BigEndian>(self.creation_time)?;
+            writer.write_u64::<BigEndian>(self.modification_time)?;
+            writer.write_u32::<BigEndian>(self.track_id)?;
+            writer.write_u32::<BigEndian>(0)?; // reserved
+            writer.write_u64::<BigEndian>(self.duration)?;
+        } else if self.version == 0 {
+            writer.write_u32::<BigEndian>(self.creation_time as u32)?;
+            writer.write_u32::<BigEndian>(self.modification_time as u32)?;
+            writer.write_u32::<BigEndian>(self.track_id)?;
+            writer.write_u32::<BigEndian>(0)?; // reserved
+            writer.write_u32::<BigEndian>(self.duration as u32)?;
+        } else {
+            return Err(Error::InvalidData("version must be 0 or 1"));
+        }
+
+        writer.write_u64::<BigEndian>(0)?; // reserved
+        writer.write_u16::<BigEndian>(self.layer)?;
+        writer.write_u16::<BigEndian>(self.alternate_group)?;
+        writer.write_u16::<BigEndian>(self.volume.raw_value())?;
+
+        writer.write_u16::<BigEndian>(0)?; // reserved
+        writer.write_i32::<BigEndian>(self.matrix.a)?;
+        writer.write_i32::<BigEndian>(self.matrix.b)?;
+        writer.write_i32::<BigEndian>(self.matrix.u)?;
+        writer.write_i32::<BigEndian>(self.matrix.c)?;
+        writer.write_i32::<BigEndian>(self.matrix.d)?;
+        writer.write_i32::<BigEndian>(self.matrix.v)?;
+        writer.write_i32::<BigEndian>(self.matrix.x)?;
+        writer.write_i32::<BigEndian>(self.matrix.y)?;
+        writer.write_i32::<BigEndian>(self.matrix.w)?;
+
+        writer.write_u32::<BigEndian>(self.width.raw_value())?;
+        writer.write_u32::<BigEndian>(self.height.raw_value())?;
+
+        Ok(self.header_size() - HEADER_SIZE + self.data_size())
     }
 }
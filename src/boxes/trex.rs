@@ -0,0 +1,84 @@
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use serde::Serialize;
+use std::io::{Read, Seek, Write};
+
+use super::{BoxHeader, BoxType, Ibox, ReadBox, WriteBox, HEADER_SIZE};
+use crate::error::Error;
+
+type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TrexBox {
+    pub version: u8,
+    pub flags: u32,
+    pub track_id: u32,
+    pub default_sample_description_index: u32,
+    pub default_sample_duration: u32,
+    pub default_sample_size: u32,
+    pub default_sample_flags: u32,
+}
+
+impl Ibox for TrexBox {
+    fn typ(&self) -> BoxType {
+        BoxType::Trex
+    }
+
+    fn header_size(&self) -> u64 {
+        HEADER_SIZE + 4
+    }
+
+    fn data_size(&self) -> u64 {
+        20
+    }
+
+    fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(self).unwrap())
+    }
+
+    fn summary(&self) -> Result<String> {
+        let s = format!(
+            "track_id={} default_sample_duration={} default_sample_size={} default_sample_flags={:#x}",
+            self.track_id,
+            self.default_sample_duration,
+            self.default_sample_size,
+            self.default_sample_flags
+        );
+        Ok(s)
+    }
+}
+
+impl<R: Read + Seek> ReadBox<&mut R> for TrexBox {
+    fn read(reader: &mut R, _: &BoxHeader) -> Result<Self> {
+        let (version, flags) = super::read_box_header_ext(reader)?;
+
+        let track_id = reader.read_u32::<BigEndian>()?;
+        let default_sample_description_index = reader.read_u32::<BigEndian>()?;
+        let default_sample_duration = reader.read_u32::<BigEndian>()?;
+        let default_sample_size = reader.read_u32::<BigEndian>()?;
+        let default_sample_flags = reader.read_u32::<BigEndian>()?;
+
+        Ok(TrexBox {
+            version,
+            flags,
+            track_id,
+            default_sample_description_index,
+            default_sample_duration,
+            default_sample_size,
+            default_sample_flags,
+        })
+    }
+}
+
+impl<W: Write> WriteBox<&mut W> for TrexBox {
+    fn write(&self, writer: &mut W, _: u64) -> Result<u64> {
+        super::write_box_header_ext(writer, self.version, self.flags)?;
+
+        writer.write_u32::<BigEndian>(self.track_id)?;
+        writer.write_u32::<BigEndian>(self.default_sample_description_index)?;
+        writer.write_u32::<BigEndian>(self.default_sample_duration)?;
+        writer.write_u32::<BigEndian>(self.default_sample_size)?;
+        writer.write_u32::<BigEndian>(self.default_sample_flags)?;
+
+        Ok(self.header_size() - HEADER_SIZE + self.data_size())
+    }
+}
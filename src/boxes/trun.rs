@@ -0,0 +1,166 @@
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use serde::Serialize;
+use std::io::{Read, Seek, Write};
+
+use super::{BoxHeader, BoxType, Ibox, ReadBox, WriteBox, HEADER_SIZE};
+use crate::error::Error;
+
+type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TrunBox {
+    pub version: u8,
+    pub flags: u32,
+    pub data_offset: Option<i32>,
+    pub first_sample_flags: Option<u32>,
+    pub entries: Vec<TrunEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize)]
+pub struct TrunEntry {
+    pub sample_duration: Option<u32>,
+    pub sample_size: Option<u32>,
+    pub sample_flags: Option<u32>,
+    pub sample_composition_time_offset: Option<i64>,
+}
+
+impl TrunBox {
+    pub const FLAG_DATA_OFFSET: u32 = 0x01;
+    pub const FLAG_FIRST_SAMPLE_FLAGS: u32 = 0x04;
+    pub const FLAG_SAMPLE_DURATION: u32 = 0x100;
+    pub const FLAG_SAMPLE_SIZE: u32 = 0x200;
+    pub const FLAG_SAMPLE_FLAGS: u32 = 0x400;
+    pub const FLAG_SAMPLE_COMPOSITION_TIME_OFFSET: u32 = 0x800;
+
+    fn entry_size(&self) -> u64 {
+        [
+            TrunBox::FLAG_SAMPLE_DURATION,
+            TrunBox::FLAG_SAMPLE_SIZE,
+            TrunBox::FLAG_SAMPLE_FLAGS,
+            TrunBox::FLAG_SAMPLE_COMPOSITION_TIME_OFFSET,
+        ]
+        .iter()
+        .filter(|f| self.flags & **f != 0)
+        .count() as u64
+            * 4
+    }
+}
+
+impl Ibox for TrunBox {
+    fn typ(&self) -> BoxType {
+        BoxType::Trun
+    }
+
+    fn header_size(&self) -> u64 {
+        HEADER_SIZE + 4
+    }
+
+    fn data_size(&self) -> u64 {
+        let mut size = 4;
+        if self.flags & TrunBox::FLAG_DATA_OFFSET != 0 {
+            size += 4;
+        }
+        if self.flags & TrunBox::FLAG_FIRST_SAMPLE_FLAGS != 0 {
+            size += 4;
+        }
+        size + self.entry_size() * self.entries.len() as u64
+    }
+
+    fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(self).unwrap())
+    }
+
+    fn summary(&self) -> Result<String> {
+        let s = format!(
+            "sample_count={} flags={:#x}",
+            self.entries.len(),
+            self.flags
+        );
+        Ok(s)
+    }
+}
+
+impl<R: Read + Seek> ReadBox<&mut R> for TrunBox {
+    fn read(reader: &mut R, _: &BoxHeader) -> Result<Self> {
+        let (version, flags) = super::read_box_header_ext(reader)?;
+
+        let sample_count = reader.read_u32::<BigEndian>()?;
+        let data_offset = if flags & TrunBox::FLAG_DATA_OFFSET != 0 {
+            Some(reader.read_i32::<BigEndian>()?)
+        } else {
+            None
+        };
+        let first_sample_flags = if flags & TrunBox::FLAG_FIRST_SAMPLE_FLAGS != 0 {
+            Some(reader.read_u32::<BigEndian>()?)
+        } else {
+            None
+        };
+
+        let mut entries = Vec::with_capacity(sample_count as usize);
+        for _ in 0..sample_count {
+            let mut entry = TrunEntry::default();
+            if flags & TrunBox::FLAG_SAMPLE_DURATION != 0 {
+                entry.sample_duration = Some(reader.read_u32::<BigEndian>()?);
+            }
+            if flags & TrunBox::FLAG_SAMPLE_SIZE != 0 {
+                entry.sample_size = Some(reader.read_u32::<BigEndian>()?);
+            }
+            if flags & TrunBox::FLAG_SAMPLE_FLAGS != 0 {
+                entry.sample_flags = Some(reader.read_u32::<BigEndian>()?);
+            }
+            if flags & TrunBox::FLAG_SAMPLE_COMPOSITION_TIME_OFFSET != 0 {
+                let offset = if version == 0 {
+                    reader.read_u32::<BigEndian>()? as i64
+                } else {
+                    reader.read_i32::<BigEndian>()? as i64
+                };
+                entry.sample_composition_time_offset = Some(offset);
+            }
+            entries.push(entry);
+        }
+
+        Ok(TrunBox {
+            version,
+            flags,
+            data_offset,
+            first_sample_flags,
+            entries,
+        })
+    }
+}
+
+impl<W: Write> WriteBox<&mut W> for TrunBox {
+    fn write(&self, writer: &mut W, _: u64) -> Result<u64> {
+        super::write_box_header_ext(writer, self.version, self.flags)?;
+
+        writer.write_u32::<BigEndian>(self.entries.len() as u32)?;
+        if self.flags & TrunBox::FLAG_DATA_OFFSET != 0 {
+            writer.write_i32::<BigEndian>(self.data_offset.unwrap_or_default())?;
+        }
+        if self.flags & TrunBox::FLAG_FIRST_SAMPLE_FLAGS != 0 {
+            writer.write_u32::<BigEndian>(self.first_sample_flags.unwrap_or_default())?;
+        }
+
+        for entry in self.entries.iter() {
+            if self.flags & TrunBox::FLAG_SAMPLE_DURATION != 0 {
+                writer.write_u32::<BigEndian>(entry.sample_duration.unwrap_or_default())?;
+            }
+            if self.flags & TrunBox::FLAG_SAMPLE_SIZE != 0 {
+                writer.write_u32::<BigEndian>(entry.sample_size.unwrap_or_default())?;
+            }
+            if self.flags & TrunBox::FLAG_SAMPLE_FLAGS != 0 {
+                writer.write_u32::<BigEndian>(entry.sample_flags.unwrap_or_default())?;
+            }
+            if self.flags & TrunBox::FLAG_SAMPLE_COMPOSITION_TIME_OFFSET != 0 {
+                let offset = entry.sample_composition_time_offset.unwrap_or_default();
+                if self.version == 0 {
+                    writer.write_u32::<BigEndian>(offset as u32)?;
+                } else {
+                    writer.write_i32::<BigEndian>(offset as i32)?;
+                }
+            }
+        }
+
+        Ok(self.header_size() - HEADER_SIZE + self.data_size())
+    }
+}
@@ -0,0 +1,507 @@
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use serde::Serialize;
+use std::io::{Cursor, Read, Seek, Write};
+
+use super::{BoxHeader, BoxType, Ibox, ReadBox, WriteBox, HEADER_SIZE};
+use crate::error::Error;
+use crate::types::FourCC;
+
+type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Tx3gBox {
+    pub data_reference_index: u16,
+    pub display_flags: u32,
+    pub horizontal_justification: i8,
+    pub vertical_justification: i8,
+    pub background_color_rgba: [u8; 4],
+    pub default_text_box: BoxRecord,
+    pub default_style: StyleRecord,
+}
+
+impl Tx3gBox {
+    pub const DISPLAY_FLAG_SCROLL_IN: u32 = 0x20;
+    pub const DISPLAY_FLAG_SCROLL_OUT: u32 = 0x40;
+    pub const DISPLAY_FLAG_CONTINUOUS_KARAOKE: u32 = 0x800;
+    pub const DISPLAY_FLAG_WRITE_TEXT_VERTICALLY: u32 = 0x20000;
+    pub const DISPLAY_FLAG_FILL_TEXT_REGION: u32 = 0x40000;
+}
+
+impl Ibox for Tx3gBox {
+    fn typ(&self) -> BoxType {
+        BoxType::Tx3g
+    }
+
+    fn data_size(&self) -> u64 {
+        8 + 4 + 2 + 4 + BoxRecord::SIZE + StyleRecord::SIZE
+    }
+
+    fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(self).unwrap())
+    }
+
+    fn summary(&self) -> Result<String> {
+        let s = format!(
+            "data_reference_index={} display_flags={:#x} horizontal_justification={} vertical_justification={} font_id={} font_size={}",
+            self.data_reference_index,
+            self.display_flags,
+            self.horizontal_justification,
+            self.vertical_justification,
+            self.default_style.font_id,
+            self.default_style.font_size
+        );
+        Ok(s)
+    }
+}
+
+impl<R: Read + Seek> ReadBox<&mut R> for Tx3gBox {
+    fn read(reader: &mut R, _: &BoxHeader) -> Result<Self> {
+        reader.read_u32::<BigEndian>()?; // reserved
+        reader.read_u16::<BigEndian>()?; // reserved
+        let data_reference_index = reader.read_u16::<BigEndian>()?;
+
+        let display_flags = reader.read_u32::<BigEndian>()?;
+        let horizontal_justification = reader.read_i8()?;
+        let vertical_justification = reader.read_i8()?;
+        let mut background_color_rgba = [0u8; 4];
+        reader.read_exact(&mut background_color_rgba)?;
+        let default_text_box = BoxRecord::read(reader)?;
+        let default_style = StyleRecord::read(reader)?;
+
+        Ok(Tx3gBox {
+            data_reference_index,
+            display_flags,
+            horizontal_justification,
+            vertical_justification,
+            background_color_rgba,
+            default_text_box,
+            default_style,
+        })
+    }
+}
+
+impl<W: Write> WriteBox<&mut W> for Tx3gBox {
+    fn write(&self, writer: &mut W, _: u64) -> Result<u64> {
+        writer.write_u32::<BigEndian>(0)?; // reserved
+        writer.write_u16::<BigEndian>(0)?; // reserved
+        writer.write_u16::<BigEndian>(self.data_reference_index)?;
+
+        writer.write_u32::<BigEndian>(self.display_flags)?;
+        writer.write_i8(self.horizontal_justification)?;
+        writer.write_i8(self.vertical_justification)?;
+        writer.write_all(&self.background_color_rgba)?;
+        self.default_text_box.write(writer)?;
+        self.default_style.write(writer)?;
+
+        Ok(self.data_size())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct BoxRecord {
+    pub top: i16,
+    pub left: i16,
+    pub bottom: i16,
+    pub right: i16,
+}
+
+impl BoxRecord {
+    const SIZE: u64 = 8;
+
+    fn read<R: Read>(reader: &mut R) -> Result<Self> {
+        Ok(BoxRecord {
+            top: reader.read_i16::<BigEndian>()?,
+            left: reader.read_i16::<BigEndian>()?,
+            bottom: reader.read_i16::<BigEndian>()?,
+            right: reader.read_i16::<BigEndian>()?,
+        })
+    }
+
+    fn write<W: Write>(&self, writer: &mut W) -> Result<u64> {
+        writer.write_i16::<BigEndian>(self.top)?;
+        writer.write_i16::<BigEndian>(self.left)?;
+        writer.write_i16::<BigEndian>(self.bottom)?;
+        writer.write_i16::<BigEndian>(self.right)?;
+        Ok(Self::SIZE)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct StyleRecord {
+    pub start_char: u16,
+    pub end_char: u16,
+    pub font_id: u16,
+    pub face_style_flags: u8,
+    pub font_size: u8,
+    pub text_color_rgba: [u8; 4],
+}
+
+impl StyleRecord {
+    const SIZE: u64 = 12;
+
+    pub const FACE_STYLE_BOLD: u8 = 0x1;
+    pub const FACE_STYLE_ITALIC: u8 = 0x2;
+    pub const FACE_STYLE_UNDERLINE: u8 = 0x4;
+
+    fn read<R: Read>(reader: &mut R) -> Result<Self> {
+        let start_char = reader.read_u16::<BigEndian>()?;
+        let end_char = reader.read_u16::<BigEndian>()?;
+        let font_id = reader.read_u16::<BigEndian>()?;
+        let face_style_flags = reader.read_u8()?;
+        let font_size = reader.read_u8()?;
+        let mut text_color_rgba = [0u8; 4];
+        reader.read_exact(&mut text_color_rgba)?;
+
+        Ok(StyleRecord {
+            start_char,
+            end_char,
+            font_id,
+            face_style_flags,
+            font_size,
+            text_color_rgba,
+        })
+    }
+
+    fn write<W: Write>(&self, writer: &mut W) -> Result<u64> {
+        writer.write_u16::<BigEndian>(self.start_char)?;
+        writer.write_u16::<BigEndian>(self.end_char)?;
+        writer.write_u16::<BigEndian>(self.font_id)?;
+        writer.write_u8(self.face_style_flags)?;
+        writer.write_u8(self.font_size)?;
+        writer.write_all(&self.text_color_rgba)?;
+        Ok(Self::SIZE)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FtabBox {
+    pub entries: Vec<FontRecord>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FontRecord {
+    pub font_id: u16,
+    pub font_name: String,
+    // the name as read when it is not UTF-8; it is written back instead of font_name
+    pub raw_font_name: Option<Vec<u8>>,
+}
+
+impl FontRecord {
+    fn font_name_bytes(&self) -> &[u8] {
+        match &self.raw_font_name {
+            Some(raw_font_name) => raw_font_name,
+            None => self.font_name.as_bytes(),
+        }
+    }
+}
+
+impl FtabBox {
+    pub fn font_name(&self, font_id: u16) -> Option<&str> {
+        self.entries
+            .iter()
+            .find(|e| e.font_id == font_id)
+            .map(|e| e.font_name.as_str())
+    }
+}
+
+impl Ibox for FtabBox {
+    fn typ(&self) -> BoxType {
+        BoxType::Ftab
+    }
+
+    fn data_size(&self) -> u64 {
+        self.entries
+            .iter()
+            .fold(2, |acc, e| acc + 3 + e.font_name_bytes().len() as u64)
+    }
+
+    fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(self).unwrap())
+    }
+
+    fn summary(&self) -> Result<String> {
+        let names: Vec<&str> = self.entries.iter().map(|e| e.font_name.as_str()).collect();
+        let s = format!("fonts={}", names.join(","));
+        Ok(s)
+    }
+}
+
+impl<R: Read + Seek> ReadBox<&mut R> for FtabBox {
+    fn read(reader: &mut R, _: &BoxHeader) -> Result<Self> {
+        let entry_count = reader.read_u16::<BigEndian>()?;
+        let mut entries = Vec::with_capacity(entry_count as usize);
+        for _ in 0..entry_count {
+            let font_id = reader.read_u16::<BigEndian>()?;
+            let font_name_length = reader.read_u8()?;
+            let mut font_name = vec![0u8; font_name_length as usize];
+            reader.read_exact(&mut font_name)?;
+            let (font_name, raw_font_name) = match String::from_utf8(font_name) {
+                Ok(font_name) => (font_name, None),
+                Err(e) => (
+                    String::from_utf8_lossy(e.as_bytes()).into_owned(),
+                    Some(e.into_bytes()),
+                ),
+            };
+            entries.push(FontRecord {
+                font_id,
+                font_name,
+                raw_font_name,
+            });
+        }
+
+        Ok(FtabBox { entries })
+    }
+}
+
+impl<W: Write> WriteBox<&mut W> for FtabBox {
+    fn write(&self, writer: &mut W, _: u64) -> Result<u64> {
+        writer.write_u16::<BigEndian>(self.entries.len() as u16)?;
+        for e in self.entries.iter() {
+            writer.write_u16::<BigEndian>(e.font_id)?;
+            writer.write_u8(e.font_name_bytes().len() as u8)?;
+            writer.write_all(e.font_name_bytes())?;
+        }
+
+        Ok(self.data_size())
+    }
+}
+
+// a decoded tx3g sample: the text followed by its modifier boxes
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TextSample {
+    pub text: String,
+    pub styles: Vec<StyleRecord>,
+    pub highlight: Option<TextHighlight>,
+    pub highlight_color_rgba: Option<[u8; 4]>,
+    pub karaoke: Option<TextKaraoke>,
+    // modifier boxes which are not decoded, such as dlay, tbox or href
+    pub other_modifiers: Vec<(FourCC, Vec<u8>)>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct TextHighlight {
+    pub start_char_offset: u16,
+    pub end_char_offset: u16,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TextKaraoke {
+    pub highlight_start_time: u32,
+    pub entries: Vec<KaraokeEntry>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct KaraokeEntry {
+    pub highlight_end_time: u32,
+    pub start_char_offset: u16,
+    pub end_char_offset: u16,
+}
+
+impl TextSample {
+    const STYL: u32 = 0x73_74_79_6c;
+    const HLIT: u32 = 0x68_6c_69_74;
+    const HCLR: u32 = 0x68_63_6c_72;
+    const KROK: u32 = 0x6b_72_6f_6b;
+
+    pub fn read(data: &[u8]) -> Result<Self> {
+        let mut reader = Cursor::new(data);
+        let text_length = reader.read_u16::<BigEndian>()?;
+        let mut text = vec![0u8; text_length as usize];
+        reader.read_exact(&mut text)?;
+        let text = decode_text(&text)?;
+
+        let mut sample = TextSample {
+            text,
+            styles: Vec::new(),
+            highlight: None,
+            highlight_color_rgba: None,
+            karaoke: None,
+            other_modifiers: Vec::new(),
+        };
+
+        while (reader.position() as usize) + HEADER_SIZE as usize <= data.len() {
+            let size = reader.read_u32::<BigEndian>()? as u64;
+            let typ = reader.read_u32::<BigEndian>()?;
+            let payload_size = size
+                .checked_sub(HEADER_SIZE)
+                .ok_or(Error::InvalidData("modifier box size too small"))?;
+            if payload_size > (data.len() - reader.position() as usize) as u64 {
+                return Err(Error::InvalidData("modifier box is larger than the sample"));
+            }
+            let mut payload = vec![0u8; payload_size as usize];
+            reader.read_exact(&mut payload)?;
+            let mut payload_reader = Cursor::new(&payload[..]);
+
+            match typ {
+                Self::STYL => {
+                    let entry_count = payload_reader.read_u16::<BigEndian>()?;
+                    for _ in 0..entry_count {
+                        sample.styles.push(StyleRecord::read(&mut payload_reader)?);
+                    }
+                }
+                Self::HLIT => {
+                    sample.highlight = Some(TextHighlight {
+                        start_char_offset: payload_reader.read_u16::<BigEndian>()?,
+                        end_char_offset: payload_reader.read_u16::<BigEndian>()?,
+                    });
+                }
+                Self::HCLR => {
+                    let mut rgba = [0u8; 4];
+                    payload_reader.read_exact(&mut rgba)?;
+                    sample.highlight_color_rgba = Some(rgba);
+                }
+                Self::KROK => {
+                    let highlight_start_time = payload_reader.read_u32::<BigEndian>()?;
+                    let entry_count = payload_reader.read_u16::<BigEndian>()?;
+                    let mut entries = Vec::with_capacity(entry_count as usize);
+                    for _ in 0..entry_count {
+                        entries.push(KaraokeEntry {
+                            highlight_end_time: payload_reader.read_u32::<BigEndian>()?,
+                            start_char_offset: payload_reader.read_u16::<BigEndian>()?,
+                            end_char_offset: payload_reader.read_u16::<BigEndian>()?,
+                        });
+                    }
+                    sample.karaoke = Some(TextKaraoke {
+                        highlight_start_time,
+                        entries,
+                    });
+                }
+                _ => sample.other_modifiers.push((FourCC::from(typ), payload)),
+            }
+        }
+
+        Ok(sample)
+    }
+}
+
+// text is UTF-8 unless it starts with a UTF-16 byte order mark
+fn decode_text(bytes: &[u8]) -> Result<String> {
+    if bytes.len() >= 2 && bytes[0] == 0xFE && bytes[1] == 0xFF {
+        let units: Vec<u16> = bytes[2..]
+            .chunks_exact(2)
+            .map(|c| u16::from_be_bytes([c[0], c[1]]))
+            .collect();
+        String::from_utf16(&units).map_err(|_| Error::InvalidData("text is not valid UTF-16"))
+    } else {
+        String::from_utf8(bytes.to_vec()).map_err(|_| Error::InvalidData("text is not valid UTF-8"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tx3g_sample_entry() {
+        let mut data = vec![0, 0, 0, 0, 0, 0, 0, 1];
+        data.extend_from_slice(&0x20000u32.to_be_bytes());
+        data.extend_from_slice(&[1, 0xFF]);
+        data.extend_from_slice(&[0, 0, 0, 0xFF]);
+        data.extend_from_slice(&[0, 0, 0, 0, 0, 60, 1, 64]);
+        data.extend_from_slice(&[0, 0, 0, 0, 0, 1, 1, 18, 0xFF, 0xFF, 0xFF, 0xFF]);
+        let header = BoxHeader::new(BoxType::Tx3g, HEADER_SIZE + data.len() as u64, 0);
+        let tx3g = Tx3gBox::read(&mut Cursor::new(&data), &header).unwrap();
+        assert_eq!(tx3g.data_reference_index, 1);
+        assert_eq!(
+            tx3g.display_flags,
+            Tx3gBox::DISPLAY_FLAG_WRITE_TEXT_VERTICALLY
+        );
+        assert_eq!(tx3g.horizontal_justification, 1);
+        assert_eq!(tx3g.vertical_justification, -1);
+        assert_eq!(tx3g.default_text_box.bottom, 60);
+        assert_eq!(tx3g.default_text_box.right, 320);
+        assert_eq!(tx3g.default_style.font_id, 1);
+        assert_eq!(
+            tx3g.default_style.face_style_flags,
+            StyleRecord::FACE_STYLE_BOLD
+        );
+        assert_eq!(tx3g.default_style.font_size, 18);
+
+        let mut buf = Vec::new();
+        assert_eq!(tx3g.write(&mut buf, 0).unwrap(), data.len() as u64);
+        assert_eq!(buf, data);
+    }
+
+    #[test]
+    fn ftab_keeps_font_names_which_are_not_utf8() {
+        let mut data = vec![0, 2];
+        data.extend_from_slice(b"\x00\x01\x05Serif");
+        data.extend_from_slice(b"\x00\x02\x04Sans");
+        data[14] = 0xE9;
+        let header = BoxHeader::new(BoxType::Ftab, HEADER_SIZE + data.len() as u64, 0);
+        let ftab = FtabBox::read(&mut Cursor::new(&data), &header).unwrap();
+        assert_eq!(ftab.font_name(1), Some("Serif"));
+        assert_eq!(ftab.font_name(2), Some("S\u{FFFD}ns"));
+        assert_eq!(
+            ftab.entries[1].raw_font_name.as_deref(),
+            Some(&b"S\xE9ns"[..])
+        );
+
+        let mut buf = Vec::new();
+        assert_eq!(ftab.write(&mut buf, 0).unwrap(), data.len() as u64);
+        assert_eq!(buf, data);
+    }
+
+    #[test]
+    fn text_sample_modifiers() {
+        let mut data = vec![0, 5];
+        data.extend_from_slice(b"Hello");
+        data.extend_from_slice(&[0, 0, 0, 22]);
+        data.extend_from_slice(b"styl");
+        data.extend_from_slice(&[0, 1, 0, 0, 0, 5, 0, 1, 2, 24, 0xFF, 0, 0, 0xFF]);
+        data.extend_from_slice(&[0, 0, 0, 12]);
+        data.extend_from_slice(b"hlit");
+        data.extend_from_slice(&[0, 1, 0, 3]);
+        data.extend_from_slice(&[0, 0, 0, 12]);
+        data.extend_from_slice(b"hclr");
+        data.extend_from_slice(&[0, 0xFF, 0, 0xFF]);
+        data.extend_from_slice(&[0, 0, 0, 22]);
+        data.extend_from_slice(b"krok");
+        data.extend_from_slice(&[0, 0, 0, 10, 0, 1, 0, 0, 1, 0, 0, 0, 0, 2]);
+        data.extend_from_slice(&[0, 0, 0, 10]);
+        data.extend_from_slice(b"dlay");
+        data.extend_from_slice(&[0, 7]);
+
+        let sample = TextSample::read(&data).unwrap();
+        assert_eq!(sample.text, "Hello");
+        assert_eq!(sample.styles.len(), 1);
+        assert_eq!(sample.styles[0].end_char, 5);
+        assert_eq!(
+            sample.styles[0].face_style_flags,
+            StyleRecord::FACE_STYLE_ITALIC
+        );
+        assert_eq!(sample.styles[0].font_size, 24);
+        assert_eq!(sample.styles[0].text_color_rgba, [0xFF, 0, 0, 0xFF]);
+        assert_eq!(
+            sample.highlight,
+            Some(TextHighlight {
+                start_char_offset: 1,
+                end_char_offset: 3
+            })
+        );
+        assert_eq!(sample.highlight_color_rgba, Some([0, 0xFF, 0, 0xFF]));
+        assert_eq!(
+            sample.karaoke,
+            Some(TextKaraoke {
+                highlight_start_time: 10,
+                entries: vec![KaraokeEntry {
+                    highlight_end_time: 256,
+                    start_char_offset: 0,
+                    end_char_offset: 2
+                }]
+            })
+        );
+        assert_eq!(
+            sample.other_modifiers,
+            vec![(FourCC::from(*b"dlay"), vec![0, 7])]
+        );
+    }
+
+    #[test]
+    fn text_sample_modifier_larger_than_sample() {
+        let mut data = vec![0, 0];
+        data.extend_from_slice(&u32::MAX.to_be_bytes());
+        data.extend_from_slice(b"styl");
+        assert!(TextSample::read(&data).is_err());
+    }
+}
@@ -0,0 +1,134 @@
+use serde::Serialize;
+use std::fmt;
+use std::io::{Read, Seek, Write};
+
+use super::{BoxHeader, BoxType, FourCC, Ibox, WriteBox};
+use crate::error::Error;
+use crate::types::Bytes;
+
+type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Clone, PartialEq, Eq, Serialize)]
+pub struct UnknownBox {
+    pub box_type: FourCC,
+    pub offset: u64,
+    pub size: u64,
+    pub data_offset: u64,
+    #[serde(skip)]
+    pub data: Option<Bytes>,
+}
+
+impl fmt::Debug for UnknownBox {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("UnknownBox")
+            .field("box_type", &self.box_type)
+            .field("offset", &self.offset)
+            .field("size", &self.size)
+            .field("data_offset", &self.data_offset)
+            .field("data", &self.data.as_ref().map(|d| d.len()))
+            .finish()
+    }
+}
+
+impl Ibox for UnknownBox {
+    fn typ(&self) -> BoxType {
+        BoxType::from(u32::from(self.box_type))
+    }
+
+    // the header as it was read, so the payload is written back where it was
+    fn header_size(&self) -> u64 {
+        self.data_offset - self.offset
+    }
+
+    fn data_size(&self) -> u64 {
+        self.offset + self.size - self.data_offset
+    }
+
+    fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(self).unwrap())
+    }
+
+    fn summary(&self) -> Result<String> {
+        let s = format!(
+            "box_type={} offset={} size={} loaded={}",
+            self.box_type,
+            self.offset,
+            self.size,
+            self.data.is_some()
+        );
+        Ok(s)
+    }
+}
+
+impl UnknownBox {
+    // bytes at the end of a container which are too few for a box header; they
+    // have no header of their own and are written back as they are
+    pub fn is_tail(&self) -> bool {
+        self.data_offset == self.offset
+    }
+
+    // the payload is kept in memory only when it is not larger than max_data_size
+    pub fn read<R: Read + Seek>(
+        reader: &mut R,
+        header: &BoxHeader,
+        max_data_size: u64,
+    ) -> Result<Self> {
+        let data_offset = reader.stream_position()?;
+        let data_size = (header.offset + header.size)
+            .checked_sub(data_offset)
+            .ok_or(Error::InvalidData("box size too small"))?;
+
+        let data = if data_size <= max_data_size {
+            let mut buf = vec![0u8; data_size as usize];
+            reader.read_exact(&mut buf)?;
+            Some(Bytes::from(buf))
+        } else {
+            None
+        };
+
+        Ok(UnknownBox {
+            box_type: From::from(header.typ),
+            offset: header.offset,
+            size: header.size,
+            data_offset,
+            data,
+        })
+    }
+}
+
+impl<W: Write> WriteBox<&mut W> for UnknownBox {
+    fn write(&self, writer: &mut W, _: u64) -> Result<u64> {
+        match &self.data {
+            Some(data) => {
+                writer.write_all(data)?;
+                Ok(data.len() as u64)
+            }
+            None => Err(Error::InvalidData("unknown box data is not loaded")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::boxes::{read_box_header, write_box_header, HEADER_SIZE_LARGE};
+    use std::io::Cursor;
+
+    #[test]
+    fn unknown_box_with_large_header() {
+        let mut data = vec![0, 0, 0, 1];
+        data.extend_from_slice(b"uuid");
+        data.extend_from_slice(&20u64.to_be_bytes());
+        data.extend_from_slice(&[1, 2, 3, 4]);
+        let mut reader = Cursor::new(&data);
+        let header = read_box_header(&mut reader, 0).unwrap();
+        let b = UnknownBox::read(&mut reader, &header, 1024).unwrap();
+        assert_eq!(b.header_size(), HEADER_SIZE_LARGE);
+        assert_eq!(b.data_size(), 4);
+
+        let mut buf = Vec::new();
+        write_box_header(&header, &mut buf).unwrap();
+        b.write(&mut buf, 0).unwrap();
+        assert_eq!(buf, data);
+    }
+}
@@ -1,3 +1,4 @@
+use byteorder::WriteBytesExt;
 use serde::Serialize;
 use std::io::{Read, Seek, Write};
 
@@ -8,9 +9,9 @@ type Result<T> = std::result::Result<T, Error>;
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize)]
 pub struct UrlBox {
-    version: u8,
-    flags: u32,
-    location: String,
+    pub version: u8,
+    pub flags: u32,
+    pub location: String,
 }
 
 impl Ibox for UrlBox {
@@ -74,7 +75,14 @@ impl<R: Read + Seek> ReadBox<&mut R> for UrlBox {
 }
 
 impl<W: Write> WriteBox<&mut W> for UrlBox {
-    fn write(&self, _: &mut W, _: u64) -> Result<u64> {
-        todo!();
+    fn write(&self, writer: &mut W, _: u64) -> Result<u64> {
+        super::write_box_header_ext(writer, self.version, self.flags)?;
+
+        if self.flags != 0x1 {
+            writer.write_all(self.location.as_bytes())?;
+            writer.write_u8(0)?; // null-terminated
+        }
+
+        Ok(self.header_size() - HEADER_SIZE + self.data_size())
     }
 }
@@ -1,4 +1,4 @@
-use byteorder::{BigEndian, ReadBytesExt};
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
 use serde::Serialize;
 use std::io::{Read, Seek, Write};
 
@@ -69,7 +69,14 @@ impl<R: Read + Seek> ReadBox<&mut R> for VmhdBox {
 }
 
 impl<W: Write> WriteBox<&mut W> for VmhdBox {
-    fn write(&self, _: &mut W, _: u64) -> Result<u64> {
-        todo!();
+    fn write(&self, writer: &mut W, _: u64) -> Result<u64> {
+        super::write_box_header_ext(writer, self.version, self.flags)?;
+
+        writer.write_u16::<BigEndian>(self.graphics_mode)?;
+        writer.write_u16::<BigEndian>(self.op_color.red)?;
+        writer.write_u16::<BigEndian>(self.op_color.green)?;
+        writer.write_u16::<BigEndian>(self.op_color.blue)?;
+
+        Ok(self.header_size() - HEADER_SIZE + self.data_size())
     }
 }
@@ -0,0 +1,12 @@
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use serde::Serialize;
+use std::io::{Read, Seek, Write};
+
+use super::avc1::visual_sample_entry;
+use super::{BoxHeader, BoxType, Ibox, ReadBox, WriteBox};
+use crate::error::Error;
+use crate::types::FixedPointU16;
+
+type Result<T> = std::result::Result<T, Error>;
+
+visual_sample_entry!(Vp09Box, Vp09);
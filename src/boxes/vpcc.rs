@@ -0,0 +1,156 @@
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use serde::Serialize;
+use std::io::{Read, Seek, Write};
+
+use super::{BoxHeader, BoxType, Ibox, ReadBox, WriteBox, HEADER_SIZE};
+use crate::error::Error;
+
+type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct VpccBox {
+    pub version: u8,
+    pub flags: u32,
+    pub profile: u8,
+    pub level: u8,
+    pub bit_depth: u8,
+    pub chroma_subsampling: u8,
+    pub video_full_range_flag: bool,
+    pub colour_primaries: u8,
+    pub transfer_characteristics: u8,
+    pub matrix_coefficients: u8,
+    pub codec_initialization_data: Vec<u8>,
+}
+
+impl VpccBox {
+    pub const CHROMA_SUBSAMPLING_420_VERTICAL: u8 = 0;
+    pub const CHROMA_SUBSAMPLING_420_COLLOCATED: u8 = 1;
+    pub const CHROMA_SUBSAMPLING_422: u8 = 2;
+    pub const CHROMA_SUBSAMPLING_444: u8 = 3;
+}
+
+impl Ibox for VpccBox {
+    fn typ(&self) -> BoxType {
+        BoxType::Vpcc
+    }
+
+    fn header_size(&self) -> u64 {
+        HEADER_SIZE + 4
+    }
+
+    fn data_size(&self) -> u64 {
+        8 + self.codec_initialization_data.len() as u64
+    }
+
+    fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(self).unwrap())
+    }
+
+    fn summary(&self) -> Result<String> {
+        let s = format!(
+            "profile={} level={} bit_depth={} chroma_subsampling={} video_full_range_flag={}",
+            self.profile,
+            self.level,
+            self.bit_depth,
+            self.chroma_subsampling,
+            self.video_full_range_flag
+        );
+        Ok(s)
+    }
+}
+
+impl<R: Read + Seek> ReadBox<&mut R> for VpccBox {
+    fn read(reader: &mut R, _: &BoxHeader) -> Result<Self> {
+        let (version, flags) = super::read_box_header_ext(reader)?;
+        if version != 1 {
+            return Err(Error::UnsupportedBoxVersion(BoxType::Vpcc, version));
+        }
+
+        let profile = reader.read_u8()?;
+        let level = reader.read_u8()?;
+        let params = reader.read_u8()?;
+        let bit_depth = params >> 4;
+        let chroma_subsampling = (params >> 1) & 0x7;
+        let video_full_range_flag = params & 0x1 == 0x1;
+        let colour_primaries = reader.read_u8()?;
+        let transfer_characteristics = reader.read_u8()?;
+        let matrix_coefficients = reader.read_u8()?;
+        let codec_initialization_data_size = reader.read_u16::<BigEndian>()?;
+        let mut codec_initialization_data = vec![0u8; codec_initialization_data_size as usize];
+        reader.read_exact(&mut codec_initialization_data)?;
+
+        Ok(VpccBox {
+            version,
+            flags,
+            profile,
+            level,
+            bit_depth,
+            chroma_subsampling,
+            video_full_range_flag,
+            colour_primaries,
+            transfer_characteristics,
+            matrix_coefficients,
+            codec_initialization_data,
+        })
+    }
+}
+
+impl<W: Write> WriteBox<&mut W> for VpccBox {
+    fn write(&self, writer: &mut W, _: u64) -> Result<u64> {
+        super::write_box_header_ext(writer, self.version, self.flags)?;
+
+        writer.write_u8(self.profile)?;
+        writer.write_u8(self.level)?;
+        writer.write_u8(
+            (self.bit_depth << 4)
+                | ((self.chroma_subsampling & 0x7) << 1)
+                | self.video_full_range_flag as u8,
+        )?;
+        writer.write_u8(self.colour_primaries)?;
+        writer.write_u8(self.transfer_characteristics)?;
+        writer.write_u8(self.matrix_coefficients)?;
+        writer.write_u16::<BigEndian>(self.codec_initialization_data.len() as u16)?;
+        writer.write_all(&self.codec_initialization_data)?;
+
+        Ok(self.header_size() - HEADER_SIZE + self.data_size())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::boxes::BoxData;
+    use crate::reader::{self, ReadOptions};
+    use crate::writer;
+    use std::io::Cursor;
+
+    #[test]
+    fn vpcc_version_0_is_kept_opaque() {
+        let mut data = Vec::new();
+        for (version, payload) in [
+            (0u8, &[0, 10, 0x82, 0x10, 0, 0][..]),
+            (1, &[0, 10, 0x82, 1, 1, 1, 0, 0][..]),
+        ] {
+            data.extend_from_slice(&(12 + payload.len() as u32).to_be_bytes());
+            data.extend_from_slice(b"vpcC");
+            data.extend_from_slice(&[version, 0, 0, 0]);
+            data.extend_from_slice(payload);
+        }
+        let size = data.len() as u64;
+        let trees =
+            reader::read_mp4_box(&mut Cursor::new(&data), size, &ReadOptions::default()).unwrap();
+        assert!(matches!(trees[0].node.data, BoxData::Unknown(_)));
+        let BoxData::Vpcc(vpcc) = &trees[1].node.data else {
+            panic!("vpcC version 1 is not parsed");
+        };
+        assert_eq!(vpcc.bit_depth, 8);
+        assert_eq!(
+            vpcc.chroma_subsampling,
+            VpccBox::CHROMA_SUBSAMPLING_420_COLLOCATED
+        );
+
+        let mut buf = Vec::new();
+        writer::write_mp4_box_tree(&trees, &mut buf).unwrap();
+        assert_eq!(buf, data);
+    }
+}
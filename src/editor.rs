@@ -0,0 +1,448 @@
+use std::io::{BufReader, Read, Seek, SeekFrom, Write};
+use std::ops::Range;
+
+use crate::boxes::{self, BoxData, BoxHeader, BoxType, Matrix, Mp4BoxTree};
+use crate::error::Error;
+use crate::metadata::Metadata;
+use crate::reader::{self, ReadOptions};
+use crate::types::FixedPointU8;
+use crate::writer;
+use crate::Result;
+
+const FREE: u32 = 0x66_72_65_65;
+
+// changes an existing file in place; boxes are serialized again and written
+// back at their original offsets, so nothing else in the file moves
+pub struct Mp4Editor<F> {
+    file: F,
+    size: u64,
+    pub trees: Vec<Mp4BoxTree>,
+}
+
+impl<F: Read + Write + Seek> Mp4Editor<F> {
+    pub fn new(mut file: F, size: u64) -> Result<Self> {
+        let trees = read_trees(&mut file, size)?;
+        Ok(Mp4Editor { file, size, trees })
+    }
+
+    pub fn into_inner(self) -> F {
+        self.file
+    }
+
+    pub fn set_movie_times(&mut self, creation_time: u64, modification_time: u64) -> Result<()> {
+        let mvhd = moov_mut(&mut self.trees)?
+            .find_mut(BoxType::Mvhd)
+            .ok_or(Error::BoxNotFound(BoxType::Mvhd))?;
+        let mut field = 0..0;
+        if let BoxData::Mvhd(mvhd) = &mut mvhd.node.data {
+            check_time(mvhd.version, creation_time, modification_time)?;
+            mvhd.creation_time = creation_time;
+            mvhd.modification_time = modification_time;
+            field = times_field(mvhd.version);
+        }
+        patch_box(&mut self.file, mvhd, field)
+    }
+
+    // sets the times of both tkhd and mdhd
+    pub fn set_track_times(
+        &mut self,
+        track_id: u32,
+        creation_time: u64,
+        modification_time: u64,
+    ) -> Result<()> {
+        let trak = trak_mut(&mut self.trees, track_id)?;
+        // both boxes are checked before either is written
+        if let BoxData::Mdhd(mdhd) = &mdhd_mut(trak, track_id)?.node.data {
+            check_time(mdhd.version, creation_time, modification_time)?;
+        }
+        let tkhd = trak.find_mut(BoxType::Tkhd).unwrap();
+        let mut field = 0..0;
+        if let BoxData::Tkhd(tkhd) = &mut tkhd.node.data {
+            check_time(tkhd.version, creation_time, modification_time)?;
+            tkhd.creation_time = creation_time;
+            tkhd.modification_time = modification_time;
+            field = times_field(tkhd.version);
+        }
+        patch_box(&mut self.file, tkhd, field)?;
+
+        let mdhd = mdhd_mut(trak, track_id)?;
+        let mut field = 0..0;
+        if let BoxData::Mdhd(mdhd) = &mut mdhd.node.data {
+            mdhd.creation_time = creation_time;
+            mdhd.modification_time = modification_time;
+            field = times_field(mdhd.version);
+        }
+        patch_box(&mut self.file, mdhd, field)
+    }
+
+    pub fn set_matrix(&mut self, track_id: u32, matrix: Matrix) -> Result<()> {
+        let tkhd = trak_mut(&mut self.trees, track_id)?
+            .find_mut(BoxType::Tkhd)
+            .unwrap();
+        let mut field = 0..0;
+        if let BoxData::Tkhd(tkhd) = &mut tkhd.node.data {
+            tkhd.matrix = matrix;
+            field = if tkhd.version == 1 { 52..88 } else { 40..76 };
+        }
+        patch_box(&mut self.file, tkhd, field)
+    }
+
+    pub fn set_volume(&mut self, track_id: u32, volume: FixedPointU8) -> Result<()> {
+        let tkhd = trak_mut(&mut self.trees, track_id)?
+            .find_mut(BoxType::Tkhd)
+            .unwrap();
+        let mut field = 0..0;
+        if let BoxData::Tkhd(tkhd) = &mut tkhd.node.data {
+            tkhd.volume = volume;
+            field = if tkhd.version == 1 { 48..50 } else { 36..38 };
+        }
+        patch_box(&mut self.file, tkhd, field)
+    }
+
+    // language is an ISO 639-2/T code such as "eng"
+    pub fn set_language(&mut self, track_id: u32, language: &str) -> Result<()> {
+        if language.len() != 3 || !language.bytes().all(|c| c.is_ascii_lowercase()) {
+            return Err(Error::InvalidData(
+                "language must be three lowercase letters",
+            ));
+        }
+        let trak = trak_mut(&mut self.trees, track_id)?;
+        let mdhd = mdhd_mut(trak, track_id)?;
+        let mut field = 0..0;
+        if let BoxData::Mdhd(mdhd) = &mut mdhd.node.data {
+            mdhd.language = language.to_string();
+            field = if mdhd.version == 1 { 32..34 } else { 20..22 };
+        }
+        patch_box(&mut self.file, mdhd, field)
+    }
+
+    // ilst may change size; the deepest of ilst, meta, udta and moov which
+    // fits into its own space plus an immediately following free box is
+    // rewritten, and what is left of the space becomes a free box again
+    pub fn set_metadata(&mut self, metadata: &Metadata) -> Result<()> {
+        let moov_index = self
+            .trees
+            .iter()
+            .position(|t| t.node.header.typ == BoxType::Moov)
+            .ok_or(Error::BoxNotFound(BoxType::Moov))?;
+        let mut moov = self.trees[moov_index].clone();
+        metadata.write(std::slice::from_mut(&mut moov))?;
+
+        let path = [BoxType::Udta, BoxType::Meta, BoxType::Ilst];
+        for depth in (0..=path.len()).rev() {
+            let (siblings, index) = if depth == 0 {
+                (&self.trees[..], moov_index)
+            } else {
+                let parent = match self.trees[moov_index].find_path(&path[..depth - 1]) {
+                    Some(parent) => parent,
+                    None => continue,
+                };
+                match parent
+                    .children
+                    .iter()
+                    .position(|c| c.node.header.typ == path[depth - 1])
+                {
+                    Some(index) => (&parent.children[..], index),
+                    None => continue,
+                }
+            };
+
+            let old = &siblings[index].node.header;
+            let free_size = siblings
+                .get(index + 1)
+                .filter(|c| u32::from(c.node.header.typ) == FREE)
+                .map_or(0, |c| c.node.header.size);
+            let available = old.size + free_size;
+            let new = moov.find_path(&path[..depth]).unwrap();
+            let remaining = match available.checked_sub(new.size()) {
+                Some(r) if r == 0 || r >= boxes::HEADER_SIZE => r,
+                _ => continue,
+            };
+
+            let offset = old.offset;
+            let mut buf = Vec::new();
+            writer::write_mp4_box_tree_from(
+                std::slice::from_ref(new),
+                &mut BufReader::new(&mut self.file),
+                &mut buf,
+            )?;
+            if remaining > 0 {
+                let free = BoxHeader::new(BoxType::from(FREE), remaining, 0);
+                boxes::write_box_header(&free, &mut buf)?;
+                buf.resize(buf.len() + (remaining - boxes::HEADER_SIZE) as usize, 0);
+            }
+            self.file.seek(SeekFrom::Start(offset))?;
+            self.file.write_all(&buf)?;
+            self.file.flush()?;
+
+            self.trees = read_trees(&mut self.file, self.size)?;
+            return Ok(());
+        }
+        Err(Error::InvalidData(
+            "not enough free space next to ilst to write metadata in place",
+        ))
+    }
+}
+
+fn read_trees<F: Read + Seek>(file: &mut F, size: u64) -> Result<Vec<Mp4BoxTree>> {
+    file.seek(SeekFrom::Start(0))?;
+    reader::read_mp4_box(&mut BufReader::new(file), size, &ReadOptions::default())
+}
+
+// the box must serialize to exactly the bytes it has in the file, except for
+// the field being changed; field is a byte range following the box header
+fn patch_box<F: Read + Write + Seek>(
+    file: &mut F,
+    tree: &Mp4BoxTree,
+    field: Range<u64>,
+) -> Result<()> {
+    let header = &tree.node.header;
+    let mut buf = Vec::new();
+    writer::write_mp4_box_tree(std::slice::from_ref(tree), &mut buf)?;
+    if buf.len() as u64 != header.size {
+        return Err(Error::InvalidData("box size would change"));
+    }
+
+    let mut old = vec![0u8; buf.len()];
+    file.seek(SeekFrom::Start(header.offset))?;
+    file.read_exact(&mut old)?;
+    let start = (header.header_size() + field.start) as usize;
+    let end = (header.header_size() + field.end) as usize;
+    if buf[..start] != old[..start] || buf[end..] != old[end..] {
+        return Err(Error::InvalidData(
+            "box would change outside of the patched field",
+        ));
+    }
+
+    file.seek(SeekFrom::Start(header.offset))?;
+    file.write_all(&buf)?;
+    file.flush()?;
+    Ok(())
+}
+
+// creation and modification times follow version and flags in mvhd, tkhd and mdhd
+fn times_field(version: u8) -> Range<u64> {
+    if version == 1 {
+        4..20
+    } else {
+        4..12
+    }
+}
+
+// version 0 boxes only have room for 32-bit times
+fn check_time(version: u8, creation_time: u64, modification_time: u64) -> Result<()> {
+    if version == 0 && creation_time.max(modification_time) > u32::MAX as u64 {
+        return Err(Error::InvalidData("time does not fit in a version 0 box"));
+    }
+    Ok(())
+}
+
+fn moov_mut(trees: &mut [Mp4BoxTree]) -> Result<&mut Mp4BoxTree> {
+    trees
+        .iter_mut()
+        .find(|t| t.node.header.typ == BoxType::Moov)
+        .ok_or(Error::BoxNotFound(BoxType::Moov))
+}
+
+fn trak_mut(trees: &mut [Mp4BoxTree], track_id: u32) -> Result<&mut Mp4BoxTree> {
+    moov_mut(trees)?
+        .children
+        .iter_mut()
+        .filter(|c| c.node.header.typ == BoxType::Trak)
+        .find(
+            |trak| match trak.find(BoxType::Tkhd).map(|t| &t.node.data) {
+                Some(BoxData::Tkhd(tkhd)) => tkhd.track_id == track_id,
+                _ => false,
+            },
+        )
+        .ok_or(Error::TrakNotFound(track_id))
+}
+
+fn mdhd_mut(trak: &mut Mp4BoxTree, track_id: u32) -> Result<&mut Mp4BoxTree> {
+    trak.find_mut(BoxType::Mdia)
+        .and_then(|mdia| mdia.find_mut(BoxType::Mdhd))
+        .ok_or(Error::BoxInTrakNotFound(track_id, BoxType::Mdhd))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::muxer::tests::{assert_samples, mux, test_samples};
+    use crate::reader::Mp4Reader;
+    use std::io::Cursor;
+
+    #[test]
+    fn set_metadata_in_place() {
+        let samples = test_samples(20, true);
+        let mut buf = mux(&samples);
+        // moov is last, followed by free space for the metadata
+        buf.extend_from_slice(&512u32.to_be_bytes());
+        buf.extend_from_slice(b"free");
+        buf.resize(buf.len() + 504, 0);
+        let size = buf.len() as u64;
+
+        let mut editor = Mp4Editor::new(Cursor::new(buf), size).unwrap();
+        let metadata = Metadata {
+            title: Some(String::from("Title")),
+            artist: Some(String::from("Artist")),
+            track_number: Some((3, 12)),
+            ..Default::default()
+        };
+        editor.set_metadata(&metadata).unwrap();
+        assert_eq!(Metadata::read(&editor.trees).unwrap(), metadata);
+
+        // the space a shorter title leaves is given back to the free box
+        let metadata = Metadata {
+            title: Some(String::from("T")),
+            ..metadata
+        };
+        editor.set_metadata(&metadata).unwrap();
+
+        let buf = editor.into_inner().into_inner();
+        assert_eq!(buf.len() as u64, size);
+        let mut reader = Mp4Reader::new(Cursor::new(buf), size).unwrap();
+        assert_eq!(Metadata::read(&reader.trees).unwrap(), metadata);
+        let free = reader.trees.last().unwrap();
+        assert_eq!(u32::from(free.node.header.typ), FREE);
+        assert_samples(&mut reader, &samples);
+    }
+
+    #[test]
+    fn set_metadata_without_free_space() {
+        let buf = mux(&test_samples(20, false));
+        let size = buf.len() as u64;
+        let mut editor = Mp4Editor::new(Cursor::new(buf.clone()), size).unwrap();
+        let metadata = Metadata {
+            title: Some(String::from("Title")),
+            ..Default::default()
+        };
+        assert!(editor.set_metadata(&metadata).is_err());
+        assert_eq!(editor.into_inner().into_inner(), buf);
+    }
+
+    type Editor = Mp4Editor<Cursor<Vec<u8>>>;
+
+    // patches a muxed file and returns it before and after
+    fn patch(edit: impl FnOnce(&mut Editor) -> Result<()>) -> (Vec<u8>, Vec<u8>) {
+        let buf = mux(&test_samples(20, true));
+        let size = buf.len() as u64;
+        let mut editor = Mp4Editor::new(Cursor::new(buf.clone()), size).unwrap();
+        edit(&mut editor).unwrap();
+        (buf, editor.into_inner().into_inner())
+    }
+
+    fn trees(buf: &[u8]) -> Vec<Mp4BoxTree> {
+        read_trees(&mut Cursor::new(buf), buf.len() as u64).unwrap()
+    }
+
+    fn tkhd(trees: &mut [Mp4BoxTree], track_id: u32) -> Mp4BoxTree {
+        trak_mut(trees, track_id)
+            .unwrap()
+            .find(BoxType::Tkhd)
+            .unwrap()
+            .clone()
+    }
+
+    fn mdhd(trees: &mut [Mp4BoxTree], track_id: u32) -> Mp4BoxTree {
+        let trak = trak_mut(trees, track_id).unwrap();
+        mdhd_mut(trak, track_id).unwrap().clone()
+    }
+
+    // the file keeps its length and only bytes inside the boxes change
+    fn assert_changed_within(old: &[u8], new: &[u8], boxes: &[&Mp4BoxTree]) {
+        assert_eq!(old.len(), new.len());
+        let changed: Vec<u64> = (0..old.len())
+            .filter(|i| old[*i] != new[*i])
+            .map(|i| i as u64)
+            .collect();
+        assert!(!changed.is_empty());
+        for i in changed {
+            assert!(boxes.iter().any(|b| {
+                let header = &b.node.header;
+                (header.offset..header.offset + header.size).contains(&i)
+            }));
+        }
+    }
+
+    #[test]
+    fn set_track_times_in_place() {
+        let (old, new) = patch(|e| e.set_track_times(2, 3_000_000_000, 3_000_000_001));
+        let mut trees = trees(&new);
+        let tkhd = tkhd(&mut trees, 2);
+        let mdhd = mdhd(&mut trees, 2);
+        assert_changed_within(&old, &new, &[&tkhd, &mdhd]);
+        let (BoxData::Tkhd(t), BoxData::Mdhd(m)) = (&tkhd.node.data, &mdhd.node.data) else {
+            panic!("tkhd or mdhd is missing");
+        };
+        assert_eq!(
+            (t.creation_time, t.modification_time),
+            (3_000_000_000, 3_000_000_001)
+        );
+        assert_eq!(
+            (m.creation_time, m.modification_time),
+            (3_000_000_000, 3_000_000_001)
+        );
+
+        // version 0 boxes have no room for the time, and nothing is written
+        let buf = new.clone();
+        let mut editor = Mp4Editor::new(Cursor::new(buf), new.len() as u64).unwrap();
+        assert!(editor.set_track_times(2, 1 << 32, 0).is_err());
+        assert_eq!(editor.into_inner().into_inner(), new);
+    }
+
+    #[test]
+    fn set_matrix_in_place() {
+        let matrix = Matrix {
+            a: 0,
+            b: 0x0001_0000,
+            c: -0x0001_0000,
+            d: 0,
+            ..Matrix::identity()
+        };
+        let (old, new) = patch(|e| e.set_matrix(1, matrix.clone()));
+        let mut trees = trees(&new);
+        let tkhd = tkhd(&mut trees, 1);
+        assert_changed_within(&old, &new, &[&tkhd]);
+        let BoxData::Tkhd(t) = &tkhd.node.data else {
+            panic!("tkhd is missing");
+        };
+        assert_eq!(t.matrix, matrix);
+    }
+
+    #[test]
+    fn set_volume_in_place() {
+        let volume = FixedPointU8::new_raw(0x80);
+        let (old, new) = patch(|e| e.set_volume(2, volume));
+        let mut trees = trees(&new);
+        let tkhd = tkhd(&mut trees, 2);
+        assert_changed_within(&old, &new, &[&tkhd]);
+        let BoxData::Tkhd(t) = &tkhd.node.data else {
+            panic!("tkhd is missing");
+        };
+        assert_eq!(t.volume, volume);
+    }
+
+    #[test]
+    fn set_language_in_place() {
+        let (old, new) = patch(|e| e.set_language(1, "fra"));
+        let mut trees = trees(&new);
+        let mdhd = mdhd(&mut trees, 1);
+        assert_changed_within(&old, &new, &[&mdhd]);
+        let BoxData::Mdhd(m) = &mdhd.node.data else {
+            panic!("mdhd is missing");
+        };
+        assert_eq!(m.language, "fra");
+    }
+
+    #[test]
+    fn patch_leaves_bytes_outside_the_field() {
+        // a reserved byte of tkhd which is written back as 0
+        let mut buf = mux(&test_samples(20, false));
+        let offset = tkhd(&mut trees(&buf), 1).node.header.offset as usize;
+        buf[offset + 24] = 1;
+        let size = buf.len() as u64;
+        let mut editor = Mp4Editor::new(Cursor::new(buf.clone()), size).unwrap();
+        assert!(editor.set_volume(1, FixedPointU8::new(1)).is_err());
+        assert_eq!(editor.into_inner().into_inner(), buf);
+    }
+}
@@ -24,6 +24,8 @@ pub enum Error {
     EntryInStblNotFound(u32, BoxType, u32),
     #[error("traf[{0}].trun.{1}.entry[{2}] not found")]
     EntryInTrunNotFound(u32, BoxType, u32),
+    #[error("trak[{0}].dref.entry[{1}] is not self-contained")]
+    DataReferenceNotSelfContained(u32, u16),
     #[error("{0} version {1} is not supported")]
     UnsupportedBoxVersion(BoxType, u8),
 }
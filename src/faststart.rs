@@ -0,0 +1,229 @@
+use crate::boxes::{BoxData, BoxType, Co64Box, Ibox, Mp4BoxTree, StcoBox};
+use crate::error::Error;
+use crate::Result;
+
+pub(crate) const MDAT: u32 = 0x6d_64_61_74;
+
+// moves moov in front of the first mdat; chunk offsets are shifted to follow the
+// boxes they point into and stco is upgraded to co64 when an offset overflows
+pub fn relocate_moov(trees: &mut Vec<Mp4BoxTree>) -> Result<()> {
+    let moov_index = trees
+        .iter()
+        .position(|t| t.node.header.typ == BoxType::Moov)
+        .ok_or(Error::BoxNotFound(BoxType::Moov))?;
+    let mdat_index = trees
+        .iter()
+        .position(|t| u32::from(t.node.header.typ) == MDAT)
+        .ok_or(Error::BoxNotFound(BoxType::UnKnown(MDAT)))?;
+    // offsets in tfhd, sidx and tfra are not shifted
+    if trees.iter().any(|t| {
+        matches!(
+            t.node.header.typ,
+            BoxType::Moof | BoxType::Sidx | BoxType::Mfra
+        )
+    }) {
+        return Err(Error::InvalidData(
+            "fragmented files can not be rewritten by faststart",
+        ));
+    }
+    if moov_index < mdat_index {
+        return Ok(());
+    }
+
+    let old_ranges = box_ranges(trees);
+    let mut order: Vec<usize> = (0..trees.len()).collect();
+    order.remove(moov_index);
+    order.insert(mdat_index, moov_index);
+
+    let moov = trees.remove(moov_index);
+    trees.insert(mdat_index, moov);
+    shift_chunk_offsets(trees, mdat_index, &order, &old_ranges)
+}
+
+// offset, size and payload offset of each top-level box as read
+pub(crate) fn box_ranges(trees: &[Mp4BoxTree]) -> Vec<(u64, u64, u64)> {
+    trees
+        .iter()
+        .map(|t| {
+            let header = &t.node.header;
+            let payload = match &t.node.data {
+                BoxData::Unknown(b) => b.data_offset,
+                _ => header.offset + header.header_size(),
+            };
+            (header.offset, header.size, payload)
+        })
+        .collect()
+}
+
+// payload offset of a box once written at offset; a compact header becomes a
+// large one when the box no longer fits in 32 bits
+fn payload_offset(tree: &Mp4BoxTree, offset: u64) -> u64 {
+    match &tree.node.data {
+        BoxData::Unknown(b) => offset + tree.size() - b.data_size(),
+        _ => offset + tree.write_header(offset, false).header_size(),
+    }
+}
+
+// rewrites the chunk offsets in trees[moov_index] for boxes that moved;
+// order gives the original index of each tree and old_ranges the ranges
+// returned by box_ranges before the trees were changed. offsets follow the
+// payload of their box since its header may change size when written
+pub(crate) fn shift_chunk_offsets(
+    trees: &mut [Mp4BoxTree],
+    moov_index: usize,
+    order: &[usize],
+    old_ranges: &[(u64, u64, u64)],
+) -> Result<()> {
+    let originals: Vec<Vec<u64>> = chunk_offset_boxes(&mut trees[moov_index])
+        .iter()
+        .map(|b| chunk_offsets(b))
+        .collect();
+
+    // moov grows when stco becomes co64, which moves the boxes after it again
+    loop {
+        let moov_size = trees[moov_index].size();
+        let mut new_payloads = vec![0; old_ranges.len()];
+        let mut offset = 0;
+        for (tree, i) in trees.iter().zip(order.iter()) {
+            new_payloads[*i] = payload_offset(tree, offset);
+            offset += tree.size();
+        }
+
+        let boxes = chunk_offset_boxes(&mut trees[moov_index]);
+        for (b, originals) in boxes.into_iter().zip(originals.iter()) {
+            let offsets = originals
+                .iter()
+                .map(|o| {
+                    let i = old_ranges
+                        .iter()
+                        .position(|(start, size, payload)| *payload <= *o && *o < start + size)
+                        .ok_or(Error::InvalidData(
+                            "chunk offset is outside of box payloads",
+                        ))?;
+                    Ok(new_payloads[i] + (o - old_ranges[i].2))
+                })
+                .collect::<Result<Vec<_>>>()?;
+            set_chunk_offsets(b, offsets);
+        }
+
+        if trees[moov_index].size() == moov_size {
+            return Ok(());
+        }
+    }
+}
+
+pub(crate) fn chunk_offset_boxes(moov: &mut Mp4BoxTree) -> Vec<&mut Mp4BoxTree> {
+    let mut boxes = Vec::new();
+    let stbls = moov
+        .children
+        .iter_mut()
+        .filter(|c| c.node.header.typ == BoxType::Trak)
+        .filter_map(|trak| trak.find_mut(BoxType::Mdia))
+        .filter_map(|mdia| mdia.find_mut(BoxType::Minf))
+        .filter_map(|minf| minf.find_mut(BoxType::Stbl));
+    for stbl in stbls {
+        boxes.extend(
+            stbl.children
+                .iter_mut()
+                .filter(|c| matches!(c.node.header.typ, BoxType::Stco | BoxType::Co64)),
+        );
+    }
+    boxes
+}
+
+pub(crate) fn chunk_offsets(tree: &Mp4BoxTree) -> Vec<u64> {
+    match &tree.node.data {
+        BoxData::Stco(stco) => stco
+            .chunk_offsets
+            .iter()
+            .map(|o| *o as u32 as u64)
+            .collect(),
+        BoxData::Co64(co64) => co64.chunk_offsets.clone(),
+        _ => Vec::new(),
+    }
+}
+
+// co64 is kept as is, and stco is replaced by co64 when an offset overflows
+pub(crate) fn set_chunk_offsets(tree: &mut Mp4BoxTree, offsets: Vec<u64>) {
+    let data = match &tree.node.data {
+        BoxData::Stco(stco) if offsets.iter().all(|o| *o <= u32::MAX as u64) => {
+            BoxData::Stco(StcoBox {
+                version: stco.version,
+                flags: stco.flags,
+                chunk_offsets: offsets.iter().map(|o| *o as u32 as i32).collect(),
+            })
+        }
+        BoxData::Stco(_) | BoxData::Co64(_) => BoxData::Co64(Co64Box {
+            version: 0,
+            flags: 0,
+            chunk_offsets: offsets,
+        }),
+        _ => return,
+    };
+    *tree = Mp4BoxTree::from_data(data, Vec::new());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::muxer::tests::{assert_samples, mux, test_samples};
+    use crate::reader::{self, Mp4Reader, ReadOptions};
+    use crate::writer;
+    use std::io::Cursor;
+
+    fn read(buf: &[u8]) -> Vec<Mp4BoxTree> {
+        reader::read_mp4_box(
+            &mut Cursor::new(buf),
+            buf.len() as u64,
+            &ReadOptions::default(),
+        )
+        .unwrap()
+    }
+
+    fn types(trees: &[Mp4BoxTree]) -> Vec<u32> {
+        trees.iter().map(|t| u32::from(t.node.header.typ)).collect()
+    }
+
+    #[test]
+    fn relocate_moov_ahead_of_large_mdat() {
+        let samples = test_samples(50, true);
+        let mut buf = mux(&samples);
+
+        // wide and the compact mdat header become a 16 byte large header, so
+        // chunk offsets in the source stay valid
+        let ftyp_size = u32::from_be_bytes(buf[0..4].try_into().unwrap()) as usize;
+        let header = &mut buf[ftyp_size..ftyp_size + 16];
+        let mdat_size = u32::from_be_bytes(header[8..12].try_into().unwrap()) as u64 + 8;
+        header[0..4].copy_from_slice(&1u32.to_be_bytes());
+        header[4..8].copy_from_slice(b"mdat");
+        header[8..16].copy_from_slice(&mdat_size.to_be_bytes());
+
+        let mut trees = read(&buf);
+        assert_eq!(trees[1].node.header.size, mdat_size);
+        relocate_moov(&mut trees).unwrap();
+        let mut out = Vec::new();
+        writer::write_mp4_box_tree_from(&trees, &mut Cursor::new(&buf), &mut out).unwrap();
+
+        // mdat keeps its large header
+        assert_eq!(out.len(), buf.len());
+        let trees = read(&out);
+        let mdat = &trees[2].node.header;
+        assert_eq!(types(&trees)[1..], [u32::from(BoxType::Moov), MDAT]);
+        assert_eq!(mdat.size, mdat_size);
+        assert!(mdat.largesize);
+
+        let size = out.len() as u64;
+        assert_samples(
+            &mut Mp4Reader::new(Cursor::new(out), size).unwrap(),
+            &samples,
+        );
+    }
+
+    #[test]
+    fn relocate_moov_rejects_fragments() {
+        let mut trees = read(&mux(&test_samples(10, false)));
+        let moof = Mp4BoxTree::from_data(BoxData::Moof(crate::boxes::MoofBox), Vec::new());
+        trees.push(moof);
+        assert!(relocate_moov(&mut trees).is_err());
+    }
+}
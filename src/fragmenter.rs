@@ -0,0 +1,438 @@
+use std::io::Write;
+use std::time::Duration;
+
+use crate::boxes::{
+    BoxData, BoxType, FtypBox, MfhdBox, MoofBox, MoovBox, Mp4BoxTree, MvexBox, TfdtBox, TfhdBox,
+    TrafBox, TrexBox, TrunBox, TrunEntry,
+};
+use crate::error::Error;
+use crate::muxer::{self, InputSample, SampleTableBuilder, TrackConfig, MOVIE_TIMESCALE};
+use crate::types::FourCC;
+use crate::writer;
+use crate::Result;
+
+// sample_depends_on is 2 for sync samples, 1 with sample_is_non_sync_sample for others
+const SYNC_SAMPLE_FLAGS: u32 = 0x0200_0000;
+const NON_SYNC_SAMPLE_FLAGS: u32 = 0x0101_0000;
+
+#[derive(Debug, Clone)]
+pub struct FragmentOptions {
+    // fragments are cut at the first keyframe of the primary track once they
+    // reach this duration; None cuts at every keyframe
+    pub fragment_duration: Option<Duration>,
+    pub ftyp: FtypBox,
+}
+
+impl Default for FragmentOptions {
+    fn default() -> Self {
+        FragmentOptions {
+            fragment_duration: None,
+            ftyp: FtypBox {
+                major_brand: FourCC { value: *b"iso6" },
+                minor_version: 0,
+                compatible_brands: vec![FourCC { value: *b"iso6" }, FourCC { value: *b"cmfc" }],
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct FragmentSample {
+    dts: u64,
+    cts: u64,
+    duration: u32,
+    is_sync: bool,
+    data: Vec<u8>,
+}
+
+#[derive(Debug, Clone)]
+struct FragmentTrack {
+    track_id: u32,
+    timescale: u32,
+    // the duration of a sample is known once the next one arrives
+    pending: Option<FragmentSample>,
+    last_duration: u32,
+    samples: Vec<FragmentSample>,
+}
+
+impl FragmentTrack {
+    fn complete_pending(&mut self, next_dts: Option<u64>) -> Result<()> {
+        if let Some(mut sample) = self.pending.take() {
+            sample.duration = match next_dts {
+                Some(dts) => u32::try_from(dts - sample.dts)
+                    .map_err(|_| Error::InvalidData("sample duration does not fit in trun"))?,
+                None => self.last_duration,
+            };
+            self.last_duration = sample.duration;
+            self.samples.push(sample);
+        }
+        Ok(())
+    }
+}
+
+// streaming writer of an init segment followed by moof and mdat pairs
+pub struct FragmentedWriter<W> {
+    writer: W,
+    options: FragmentOptions,
+    tracks: Vec<FragmentTrack>,
+    // index of the track whose keyframes start fragments
+    primary: usize,
+    sequence_number: u32,
+}
+
+impl<W: Write> FragmentedWriter<W> {
+    // writes the init segment; track_ids are assigned from 1 in the order of configs
+    pub fn new(mut writer: W, configs: Vec<TrackConfig>, options: FragmentOptions) -> Result<Self> {
+        if configs.is_empty() {
+            return Err(Error::InvalidData("at least one track is required"));
+        }
+
+        let mut children = vec![muxer::mvhd(MOVIE_TIMESCALE, 0, configs.len() as u32 + 1)];
+        let mut trexs = Vec::with_capacity(configs.len());
+        for (i, config) in configs.iter().enumerate() {
+            let track_id = i as u32 + 1;
+            let stbl = SampleTableBuilder::default().build(&config.sample_entry)?;
+            children.push(muxer::trak(config, track_id, 0, Vec::new(), 0, stbl)?);
+            let trex = TrexBox {
+                version: 0,
+                flags: 0,
+                track_id,
+                default_sample_description_index: 1,
+                default_sample_duration: 0,
+                default_sample_size: 0,
+                default_sample_flags: 0,
+            };
+            trexs.push(Mp4BoxTree::from_data(BoxData::Trex(trex), Vec::new()));
+        }
+        children.push(Mp4BoxTree::from_data(BoxData::Mvex(MvexBox), trexs));
+
+        let init = [
+            Mp4BoxTree::from_data(BoxData::Ftyp(options.ftyp.clone()), Vec::new()),
+            Mp4BoxTree::from_data(BoxData::Moov(MoovBox), children),
+        ];
+        writer::write_mp4_box_tree(&init, &mut writer)?;
+
+        let primary = configs.iter().position(|c| c.is_video()).unwrap_or(0);
+        let tracks = configs
+            .iter()
+            .enumerate()
+            .map(|(i, c)| FragmentTrack {
+                track_id: i as u32 + 1,
+                timescale: c.timescale,
+                pending: None,
+                last_duration: 0,
+                samples: Vec::new(),
+            })
+            .collect();
+
+        Ok(FragmentedWriter {
+            writer,
+            options,
+            tracks,
+            primary,
+            sequence_number: 1,
+        })
+    }
+
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.writer
+    }
+
+    pub fn write_sample(&mut self, track_id: u32, sample: &InputSample) -> Result<()> {
+        let i = self
+            .tracks
+            .iter()
+            .position(|t| t.track_id == track_id)
+            .ok_or(Error::TrakNotFound(track_id))?;
+        let track = &mut self.tracks[i];
+        if track.pending.as_ref().is_some_and(|p| sample.dts < p.dts) {
+            return Err(Error::InvalidData("sample dts must not decrease"));
+        }
+        track.complete_pending(Some(sample.dts))?;
+
+        if i == self.primary && sample.is_sync && self.should_cut(sample.dts) {
+            self.flush()?;
+        }
+
+        self.tracks[i].pending = Some(FragmentSample {
+            dts: sample.dts,
+            cts: sample.cts,
+            duration: 0,
+            is_sync: sample.is_sync,
+            data: sample.data.to_vec(),
+        });
+        Ok(())
+    }
+
+    fn should_cut(&self, dts: u64) -> bool {
+        if self.tracks.iter().all(|t| t.samples.is_empty()) {
+            return false;
+        }
+        let primary = &self.tracks[self.primary];
+        match (self.options.fragment_duration, primary.samples.first()) {
+            (Some(duration), Some(first)) => {
+                let ticks = duration.as_nanos() * primary.timescale as u128 / 1_000_000_000;
+                (dts - first.dts) as u128 >= ticks
+            }
+            _ => true,
+        }
+    }
+
+    // writes the samples gathered so far as a fragment; samples whose duration
+    // is not known yet are left for the next one
+    pub fn flush(&mut self) -> Result<()> {
+        if self.tracks.iter().all(|t| t.samples.is_empty()) {
+            return Ok(());
+        }
+
+        let mfhd = MfhdBox {
+            version: 0,
+            flags: 0,
+            sequence_number: self.sequence_number,
+        };
+        let mut children = vec![Mp4BoxTree::from_data(BoxData::Mfhd(mfhd), Vec::new())];
+        let mut data_size = 0;
+        for t in self.tracks.iter().filter(|t| !t.samples.is_empty()) {
+            children.push(traf(t.track_id, &t.samples, data_size)?);
+            data_size += t.samples.iter().map(|s| s.data.len() as u64).sum::<u64>();
+        }
+        let mut moof = Mp4BoxTree::from_data(BoxData::Moof(MoofBox), children);
+
+        // data offsets are relative to moof, which is followed by the mdat header
+        let moof_size = moof.size() as i64 + 8;
+        for traf in moof.children.iter_mut().skip(1) {
+            if let Some(trun) = traf.find_mut(BoxType::Trun) {
+                if let BoxData::Trun(trun) = &mut trun.node.data {
+                    let offset = trun.data_offset.unwrap_or_default() as i64 + moof_size;
+                    trun.data_offset = Some(
+                        i32::try_from(offset)
+                            .map_err(|_| Error::InvalidData("fragment is too large"))?,
+                    );
+                }
+            }
+        }
+        if data_size + 8 > u32::MAX as u64 {
+            return Err(Error::InvalidData("fragment is too large"));
+        }
+
+        writer::write_mp4_box_tree(&[moof], &mut self.writer)?;
+        self.writer
+            .write_all(&(data_size as u32 + 8).to_be_bytes())?;
+        self.writer.write_all(b"mdat")?;
+        for t in self.tracks.iter_mut() {
+            for s in t.samples.drain(..) {
+                self.writer.write_all(&s.data)?;
+            }
+        }
+        self.sequence_number += 1;
+        Ok(())
+    }
+
+    // writes the remaining samples; the last sample of a track repeats the
+    // duration of the one before it
+    pub fn finish(mut self) -> Result<W> {
+        for t in self.tracks.iter_mut() {
+            t.complete_pending(None)?;
+        }
+        self.flush()?;
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+// traf for the samples of a track; data_offset is relative to the start of mdat
+// payload here and made relative to moof once the size of moof is known
+fn traf(track_id: u32, samples: &[FragmentSample], data_offset: u64) -> Result<Mp4BoxTree> {
+    let flags: Vec<u32> = samples
+        .iter()
+        .map(|s| {
+            if s.is_sync {
+                SYNC_SAMPLE_FLAGS
+            } else {
+                NON_SYNC_SAMPLE_FLAGS
+            }
+        })
+        .collect();
+    let uniform = |values: &[u32]| {
+        let first = *values.first()?;
+        values.iter().all(|v| *v == first).then_some(first)
+    };
+    let durations: Vec<u32> = samples.iter().map(|s| s.duration).collect();
+    let sizes: Vec<u32> = samples.iter().map(|s| s.data.len() as u32).collect();
+    let default_sample_duration = uniform(&durations);
+    let default_sample_size = uniform(&sizes);
+    // typically a keyframe followed by non-sync samples
+    let (default_sample_flags, first_sample_flags) = match uniform(&flags[1..]) {
+        None if flags.len() == 1 => (Some(flags[0]), None),
+        Some(rest) if rest == flags[0] => (Some(rest), None),
+        Some(rest) => (Some(rest), Some(flags[0])),
+        None => (None, None),
+    };
+
+    let mut tfhd = TfhdBox {
+        version: 0,
+        flags: TfhdBox::FLAG_DEFAULT_BASE_IS_MOOF,
+        track_id,
+        base_data_offset: None,
+        sample_description_index: None,
+        default_sample_duration,
+        default_sample_size,
+        default_sample_flags,
+    };
+    if default_sample_duration.is_some() {
+        tfhd.flags |= TfhdBox::FLAG_DEFAULT_SAMPLE_DURATION;
+    }
+    if default_sample_size.is_some() {
+        tfhd.flags |= TfhdBox::FLAG_DEFAULT_SAMPLE_SIZE;
+    }
+    if default_sample_flags.is_some() {
+        tfhd.flags |= TfhdBox::FLAG_DEFAULT_SAMPLE_FLAGS;
+    }
+
+    let base_media_decode_time = samples[0].dts;
+    let tfdt = TfdtBox {
+        version: if base_media_decode_time > u32::MAX as u64 {
+            1
+        } else {
+            0
+        },
+        flags: 0,
+        base_media_decode_time,
+    };
+
+    let offsets: Vec<i64> = samples
+        .iter()
+        .map(|s| s.cts as i64 - s.dts as i64)
+        .collect();
+    let mut trun = TrunBox {
+        version: if offsets.iter().any(|o| *o < 0) { 1 } else { 0 },
+        flags: TrunBox::FLAG_DATA_OFFSET,
+        data_offset: Some(
+            i32::try_from(data_offset).map_err(|_| Error::InvalidData("fragment is too large"))?,
+        ),
+        first_sample_flags,
+        entries: Vec::with_capacity(samples.len()),
+    };
+    if first_sample_flags.is_some() {
+        trun.flags |= TrunBox::FLAG_FIRST_SAMPLE_FLAGS;
+    }
+    if default_sample_duration.is_none() {
+        trun.flags |= TrunBox::FLAG_SAMPLE_DURATION;
+    }
+    if default_sample_size.is_none() {
+        trun.flags |= TrunBox::FLAG_SAMPLE_SIZE;
+    }
+    if default_sample_flags.is_none() {
+        trun.flags |= TrunBox::FLAG_SAMPLE_FLAGS;
+    }
+    let has_offsets = offsets.iter().any(|o| *o != 0);
+    if has_offsets {
+        trun.flags |= TrunBox::FLAG_SAMPLE_COMPOSITION_TIME_OFFSET;
+    }
+    for (i, s) in samples.iter().enumerate() {
+        let offset = offsets[i];
+        if i32::try_from(offset).is_err() {
+            return Err(Error::InvalidData(
+                "composition offset does not fit in trun",
+            ));
+        }
+        trun.entries.push(TrunEntry {
+            sample_duration: default_sample_duration.is_none().then_some(s.duration),
+            sample_size: default_sample_size.is_none().then_some(s.data.len() as u32),
+            sample_flags: default_sample_flags.is_none().then_some(flags[i]),
+            sample_composition_time_offset: has_offsets.then_some(offset),
+        });
+    }
+
+    Ok(Mp4BoxTree::from_data(
+        BoxData::Traf(TrafBox),
+        vec![
+            Mp4BoxTree::from_data(BoxData::Tfhd(tfhd), Vec::new()),
+            Mp4BoxTree::from_data(BoxData::Tfdt(tfdt), Vec::new()),
+            Mp4BoxTree::from_data(BoxData::Trun(trun), Vec::new()),
+        ],
+    ))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::muxer::tests::{audio_config, test_samples, video_config, TestSample};
+    use crate::reader::{self, ReadOptions};
+    use crate::track;
+    use std::io::Cursor;
+
+    pub(crate) fn fragment(samples: &[TestSample], options: FragmentOptions) -> Vec<u8> {
+        let configs = vec![video_config(), audio_config()];
+        let mut writer = FragmentedWriter::new(Vec::new(), configs, options).unwrap();
+        for s in samples.iter() {
+            let sample = InputSample {
+                dts: s.dts,
+                cts: s.cts,
+                is_sync: s.is_sync,
+                data: &s.data,
+            };
+            writer.write_sample(s.track_id, &sample).unwrap();
+        }
+        writer.finish().unwrap()
+    }
+
+    fn assert_fragments(buf: &[u8], samples: &[TestSample], fragments: usize) {
+        let trees = reader::read_mp4_box(
+            &mut Cursor::new(buf),
+            buf.len() as u64,
+            &ReadOptions::default(),
+        )
+        .unwrap();
+        let (moofs, init): (Vec<_>, Vec<_>) = trees
+            .into_iter()
+            .partition(|t| t.node.header.typ == BoxType::Moof);
+        assert_eq!(moofs.len(), fragments);
+
+        let mut tracks = track::tracks(&init).unwrap();
+        assert!(tracks.iter().all(|t| t.sample_count() == 0));
+        for moof in moofs.iter() {
+            track::add_fragment(&mut tracks, moof).unwrap();
+        }
+
+        for track in tracks.iter() {
+            let expected: Vec<_> = samples
+                .iter()
+                .filter(|s| s.track_id == track.track_id)
+                .collect();
+            assert_eq!(track.runs.len(), fragments);
+            assert_eq!(track.sample_count() as usize, expected.len());
+            for (sample, e) in track.samples().zip(expected.iter()) {
+                let sample = sample.unwrap();
+                assert_eq!(
+                    (sample.dts, sample.cts, sample.is_sync),
+                    (e.dts, e.cts, e.is_sync),
+                    "track {} sample {}",
+                    track.track_id,
+                    sample.index
+                );
+                let start = sample.offset as usize;
+                assert_eq!(&buf[start..start + sample.size as usize], &e.data[..]);
+            }
+        }
+    }
+
+    #[test]
+    fn fragments_at_every_keyframe() {
+        let samples = test_samples(50, true);
+        let buf = fragment(&samples, FragmentOptions::default());
+        assert_fragments(&buf, &samples, 5);
+    }
+
+    #[test]
+    fn fragments_of_a_duration() {
+        let samples = test_samples(50, true);
+        let options = FragmentOptions {
+            fragment_duration: Some(Duration::from_millis(700)),
+            ..Default::default()
+        };
+        let buf = fragment(&samples, options);
+        // cut at the keyframes at 0.8 s and 1.6 s
+        assert_fragments(&buf, &samples, 3);
+    }
+}
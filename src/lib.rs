@@ -1,5 +1,6 @@
 use std::fs::File;
-use std::io::BufReader;
+use std::io::{BufReader, BufWriter, Write};
+use std::time::Duration;
 
 mod error;
 pub use error::Error;
@@ -7,11 +8,38 @@ pub use error::Error;
 mod types;
 pub use types::*;
 
+mod bitreader;
+
 mod boxes;
 pub use boxes::*;
 
 mod reader;
-pub use reader::*;
+pub use reader::{Mp4Reader, ReadOptions};
+
+mod writer;
+
+mod track;
+pub use track::*;
+
+mod metadata;
+pub use metadata::*;
+
+mod segment;
+pub use segment::*;
+
+mod muxer;
+pub use muxer::{InputSample, Mp4Writer, TrackConfig};
+
+mod fragmenter;
+pub use fragmenter::{FragmentOptions, FragmentedWriter};
+
+mod faststart;
+pub use faststart::relocate_moov;
+
+mod editor;
+pub use editor::Mp4Editor;
+
+mod trim;
 
 pub type Result<T> = std::result::Result<T, Error>;
 
@@ -21,19 +49,66 @@ pub enum Scanning {
 }
 
 pub fn read_mp4_box(f: File) -> Result<Vec<Mp4BoxTree>> {
+    read_mp4_box_with_options(f, &ReadOptions::default())
+}
+
+pub fn read_mp4_box_with_options(f: File, options: &ReadOptions) -> Result<Vec<Mp4BoxTree>> {
     let size = f.metadata()?.len();
     let mut reader = BufReader::new(f);
-    let tree = reader::read_mp4_box(&mut reader, size)?;
+    let tree = reader::read_mp4_box(&mut reader, size, options)?;
     Ok(tree)
 }
 
 pub fn scan_mp4_box<C: FnMut(&BoxData) -> Scanning>(f: File, c: &mut C) -> Result<()> {
     let size = f.metadata()?.len();
     let mut reader = BufReader::new(f);
-    Ok(reader::scan_mp4_box(&mut reader, size, c)?)
+    reader::scan_mp4_box(&mut reader, size, c)
+}
+
+// reads only mfra and mfro at the end of the file; empty if there is no mfro
+pub fn read_fragment_index(f: File) -> Result<Vec<FragmentIndex>> {
+    let size = f.metadata()?.len();
+    let mut reader = BufReader::new(f);
+    match reader::read_mfra(&mut reader, size, &ReadOptions::default())? {
+        Some(mfra) => FragmentIndex::from_mfra(&mfra),
+        None => Ok(Vec::new()),
+    }
+}
+
+pub fn write_mp4_box_tree<W: Write>(trees: &[Mp4BoxTree], w: W) -> Result<u64> {
+    let mut writer = BufWriter::new(w);
+    let size = writer::write_mp4_box_tree(trees, &mut writer)?;
+    writer.flush()?;
+    Ok(size)
+}
+
+pub fn write_mp4_box_tree_from<W: Write>(trees: &[Mp4BoxTree], f: File, w: W) -> Result<u64> {
+    let mut reader = BufReader::new(f);
+    let mut writer = BufWriter::new(w);
+    let size = writer::write_mp4_box_tree_from(trees, &mut reader, &mut writer)?;
+    writer.flush()?;
+    Ok(size)
+}
+
+// rewrites the file with moov ahead of mdat so it can be played while downloading
+pub fn faststart<W: Write>(input: File, output: W) -> Result<u64> {
+    let mut trees = read_mp4_box(input.try_clone()?)?;
+    relocate_moov(&mut trees)?;
+    write_mp4_box_tree_from(&trees, input, output)
+}
+
+// cuts the presentation to [start, end) without re-encoding; samples from the
+// preceding sync sample are kept and an edit list hides them
+pub fn trim<W: Write>(input: File, output: W, start: Duration, end: Duration) -> Result<u64> {
+    let size = input.metadata()?.len();
+    let mut reader = BufReader::new(input);
+    let mut writer = BufWriter::new(output);
+    let size = trim::trim(&mut reader, size, &mut writer, start, end)?;
+    writer.flush()?;
+    Ok(size)
 }
 
-pub fn debug_dump_mp4_box(trees: &Vec<Mp4BoxTree>) {
+pub fn debug_dump_mp4_box(trees: &[Mp4BoxTree]) {
     let mut stack = Vec::new();
     for c in trees.iter().rev() {
         stack.push((0, c));
@@ -0,0 +1,291 @@
+use serde::Serialize;
+
+use crate::boxes::{
+    BoxData, BoxType, DataBox, HdlrBox, IlstBox, IlstItem, MetaBox, Mp4BoxTree, UdtaBox,
+};
+use crate::error::Error;
+use crate::faststart::{box_ranges, shift_chunk_offsets, MDAT};
+use crate::types::FourCC;
+use crate::Result;
+
+// iTunes-style tags stored in moov.udta.meta.ilst
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct Metadata {
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub year: Option<String>,
+    pub description: Option<String>,
+    pub track_number: Option<(u16, u16)>,
+    pub disc_number: Option<(u16, u16)>,
+    pub tempo: Option<u16>,
+    pub compilation: Option<bool>,
+    pub covers: Vec<DataBox>,
+    pub freeform: Vec<FreeformTag>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FreeformTag {
+    pub mean: String,
+    pub name: String,
+    pub value: String,
+}
+
+impl Metadata {
+    const HANDLER_TYPE: FourCC = FourCC { value: *b"mdir" };
+
+    pub fn read(trees: &[Mp4BoxTree]) -> Result<Self> {
+        let moov = trees
+            .iter()
+            .find(|t| t.node.header.typ == BoxType::Moov)
+            .ok_or(Error::BoxNotFound(BoxType::Moov))?;
+        match moov.find_path(&[BoxType::Udta, BoxType::Meta, BoxType::Ilst]) {
+            Some(ilst) => Ok(Self::from_ilst(ilst.try_into()?)),
+            None => Ok(Metadata::default()),
+        }
+    }
+
+    pub fn from_ilst(ilst: &IlstBox) -> Self {
+        let data = |key: FourCC| ilst.get(key).and_then(|i| i.data.first());
+        let text = |key: FourCC| data(key).and_then(|d| d.as_str());
+        let pair = |key: FourCC| {
+            data(key).filter(|d| d.value.len() >= 6).map(|d| {
+                (
+                    u16::from_be_bytes([d.value[2], d.value[3]]),
+                    u16::from_be_bytes([d.value[4], d.value[5]]),
+                )
+            })
+        };
+
+        Metadata {
+            title: text(IlstBox::KEY_TITLE),
+            artist: text(IlstBox::KEY_ARTIST),
+            year: text(IlstBox::KEY_DAY),
+            description: text(IlstBox::KEY_DESCRIPTION),
+            track_number: pair(IlstBox::KEY_TRACK_NUMBER),
+            disc_number: pair(IlstBox::KEY_DISC_NUMBER),
+            tempo: data(IlstBox::KEY_TEMPO)
+                .and_then(|d| d.as_integer())
+                .map(|v| v as u16),
+            compilation: data(IlstBox::KEY_COMPILATION)
+                .and_then(|d| d.as_integer())
+                .map(|v| v != 0),
+            covers: ilst
+                .get(IlstBox::KEY_COVER)
+                .map(|i| i.data.clone())
+                .unwrap_or_default(),
+            freeform: ilst.items.iter().filter_map(freeform_tag).collect(),
+        }
+    }
+
+    // the size of moov changes, so chunk offsets are shifted when moov is
+    // placed before mdat
+    pub fn write(&self, trees: &mut [Mp4BoxTree]) -> Result<()> {
+        let moov_index = trees
+            .iter()
+            .position(|t| t.node.header.typ == BoxType::Moov)
+            .ok_or(Error::BoxNotFound(BoxType::Moov))?;
+        let old_ranges = box_ranges(trees);
+        let moov = &mut trees[moov_index];
+        let udta = find_or_insert(moov, BoxData::Udta(UdtaBox));
+        let meta = find_or_insert(
+            udta,
+            BoxData::Meta(MetaBox {
+                version: 0,
+                flags: 0,
+                full_box: true,
+            }),
+        );
+        if meta.find(BoxType::Hdlr).is_none() {
+            let hdlr = HdlrBox {
+                version: 0,
+                flags: 0,
+                pre_defined: 0,
+                handler_type: Self::HANDLER_TYPE,
+                reserved: [0; 12],
+                name: String::new(),
+                raw_name: None,
+            };
+            meta.children
+                .insert(0, Mp4BoxTree::from_data(BoxData::Hdlr(hdlr), Vec::new()));
+        }
+        let ilst = find_or_insert(meta, BoxData::Ilst(IlstBox::default()));
+        if let BoxData::Ilst(ilst) = &mut ilst.node.data {
+            self.apply(ilst);
+        }
+
+        let precedes_mdat = trees[moov_index..]
+            .iter()
+            .any(|t| u32::from(t.node.header.typ) == MDAT);
+        if precedes_mdat {
+            let order: Vec<usize> = (0..trees.len()).collect();
+            shift_chunk_offsets(trees, moov_index, &order, &old_ranges)?;
+        }
+        Ok(())
+    }
+
+    // items which are not represented by Metadata are left untouched
+    pub fn apply(&self, ilst: &mut IlstBox) {
+        let text = |v: &Option<String>| v.as_deref().map(DataBox::utf8);
+        let pair = |v: Option<(u16, u16)>, size: usize| {
+            v.map(|(n, total)| {
+                let mut value = vec![0u8; size];
+                value[2..4].copy_from_slice(&n.to_be_bytes());
+                value[4..6].copy_from_slice(&total.to_be_bytes());
+                DataBox::new(DataBox::TYPE_BINARY, value)
+            })
+        };
+
+        set_or_remove(ilst, IlstBox::KEY_TITLE, text(&self.title));
+        set_or_remove(ilst, IlstBox::KEY_ARTIST, text(&self.artist));
+        set_or_remove(ilst, IlstBox::KEY_DAY, text(&self.year));
+        set_or_remove(ilst, IlstBox::KEY_DESCRIPTION, text(&self.description));
+        set_or_remove(ilst, IlstBox::KEY_TRACK_NUMBER, pair(self.track_number, 8));
+        set_or_remove(ilst, IlstBox::KEY_DISC_NUMBER, pair(self.disc_number, 6));
+        set_or_remove(
+            ilst,
+            IlstBox::KEY_TEMPO,
+            self.tempo.map(|v| DataBox::signed(v as i64, 2)),
+        );
+        set_or_remove(
+            ilst,
+            IlstBox::KEY_COMPILATION,
+            self.compilation.map(|v| DataBox::signed(v as i64, 1)),
+        );
+
+        if self.covers.is_empty() {
+            ilst.remove(IlstBox::KEY_COVER);
+        } else {
+            let mut item = IlstItem::new(IlstBox::KEY_COVER, self.covers[0].clone());
+            item.data = self.covers.clone();
+            ilst.set(item);
+        }
+
+        ilst.items.retain(|i| freeform_tag(i).is_none());
+        for tag in self.freeform.iter() {
+            ilst.items.push(IlstItem::freeform(
+                &tag.mean,
+                &tag.name,
+                DataBox::utf8(&tag.value),
+            ));
+        }
+    }
+}
+
+fn freeform_tag(item: &IlstItem) -> Option<FreeformTag> {
+    if item.key != IlstBox::KEY_FREEFORM {
+        return None;
+    }
+    Some(FreeformTag {
+        mean: item.mean.clone()?,
+        name: item.name.clone()?,
+        value: item.data.first()?.as_str()?,
+    })
+}
+
+fn set_or_remove(ilst: &mut IlstBox, key: FourCC, data: Option<DataBox>) {
+    match data {
+        Some(data) => ilst.set(IlstItem::new(key, data)),
+        None => ilst.remove(key),
+    }
+}
+
+fn find_or_insert(tree: &mut Mp4BoxTree, data: BoxData) -> &mut Mp4BoxTree {
+    let typ = data.typ();
+    let position = match tree.children.iter().position(|c| c.node.header.typ == typ) {
+        Some(position) => position,
+        None => {
+            tree.children.push(Mp4BoxTree::from_data(data, Vec::new()));
+            tree.children.len() - 1
+        }
+    };
+    &mut tree.children[position]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::muxer::tests::{assert_samples, mux, test_samples};
+    use crate::reader::{self, Mp4Reader, ReadOptions};
+    use crate::writer;
+    use std::io::Cursor;
+
+    #[test]
+    fn metadata_written_and_read_back() {
+        let samples = test_samples(20, true);
+        let buf = mux(&samples);
+        let size = buf.len() as u64;
+        let mut source = Cursor::new(buf);
+        let mut trees = reader::read_mp4_box(&mut source, size, &ReadOptions::default()).unwrap();
+
+        let metadata = Metadata {
+            title: Some(String::from("Title")),
+            track_number: Some((3, 12)),
+            disc_number: Some((1, 2)),
+            tempo: Some(120),
+            compilation: Some(true),
+            covers: vec![
+                DataBox::new(DataBox::TYPE_JPEG, vec![0xFF, 0xD8, 0xFF, 0xD9]),
+                DataBox::new(DataBox::TYPE_PNG, b"\x89PNG".to_vec()),
+            ],
+            freeform: vec![FreeformTag {
+                mean: String::from("com.apple.iTunes"),
+                name: String::from("ENCODER"),
+                value: String::from("mp4box"),
+            }],
+            ..Default::default()
+        };
+        metadata.write(&mut trees).unwrap();
+        let mut out = Vec::new();
+        writer::write_mp4_box_tree_from(&trees, &mut source, &mut out).unwrap();
+
+        let size = out.len() as u64;
+        let mut reader = Mp4Reader::new(Cursor::new(out), size).unwrap();
+        assert_eq!(Metadata::read(&reader.trees).unwrap(), metadata);
+        assert_samples(&mut reader, &samples);
+
+        // the data atoms have the widths and types iTunes writes
+        let moov = reader
+            .trees
+            .iter()
+            .find(|t| t.node.header.typ == BoxType::Moov)
+            .unwrap();
+        let meta = moov.find_path(&[BoxType::Udta, BoxType::Meta]).unwrap();
+        let BoxData::Hdlr(hdlr) = &meta.find(BoxType::Hdlr).unwrap().node.data else {
+            panic!("meta has no handler");
+        };
+        assert_eq!(hdlr.handler_type, Metadata::HANDLER_TYPE);
+        let ilst: &IlstBox = meta.find(BoxType::Ilst).unwrap().try_into().unwrap();
+        let data = |key: FourCC| ilst.get(key).unwrap().data[0].clone();
+        assert_eq!(
+            data(IlstBox::KEY_TRACK_NUMBER).value,
+            [0, 0, 0, 3, 0, 12, 0, 0]
+        );
+        assert_eq!(data(IlstBox::KEY_DISC_NUMBER).value, [0, 0, 0, 1, 0, 2]);
+        assert_eq!(
+            data(IlstBox::KEY_TEMPO),
+            DataBox::new(DataBox::TYPE_BE_SIGNED, vec![0, 120])
+        );
+        assert_eq!(
+            data(IlstBox::KEY_COMPILATION),
+            DataBox::new(DataBox::TYPE_BE_SIGNED, vec![1])
+        );
+        assert_eq!(ilst.get(IlstBox::KEY_COVER).unwrap().data.len(), 2);
+        let freeform = ilst.get_freeform("com.apple.iTunes", "ENCODER").unwrap();
+        assert_eq!(freeform.data[0].as_str().as_deref(), Some("mp4box"));
+    }
+
+    #[test]
+    fn metadata_cleared() {
+        let mut ilst = IlstBox::default();
+        let metadata = Metadata {
+            tempo: Some(90),
+            compilation: Some(false),
+            ..Default::default()
+        };
+        metadata.apply(&mut ilst);
+        assert_eq!(Metadata::from_ilst(&ilst), metadata);
+
+        Metadata::default().apply(&mut ilst);
+        assert!(ilst.items.is_empty());
+    }
+}
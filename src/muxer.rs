@@ -0,0 +1,864 @@
+use std::io::{Seek, SeekFrom, Write};
+
+use crate::boxes::{
+    Avc1Box, AvcCBox, BoxData, Co64Box, CttsBox, CttsEntry, DinfBox, DrefBox, EdtsBox, ElstBox,
+    ElstEntry, EsdsBox, FtypBox, HdlrBox, Matrix, MdhdBox, MdiaBox, MinfBox, MoovBox, Mp4BoxTree,
+    Mp4aBox, MvhdBox, SmhdBox, StblBox, StcoBox, StscBox, StscEntry, StsdBox, StssBox, StszBox,
+    SttsBox, SttsEntry, TkhdBox, TrakBox, UrlBox, VmhdBox,
+};
+use crate::error::Error;
+use crate::track::rescale;
+use crate::types::{FixedPointI8, FixedPointU16, FixedPointU8, FourCC};
+use crate::writer;
+use crate::Result;
+
+pub(crate) const MOVIE_TIMESCALE: u32 = 1000;
+// a new chunk is started once the current one reaches this size
+pub(crate) const MAX_CHUNK_SIZE: u64 = 1024 * 1024;
+
+// a track to be muxed; sample_entry is the stsd entry with its children such as avcC
+#[derive(Debug, Clone)]
+pub struct TrackConfig {
+    pub timescale: u32,
+    pub language: String,
+    pub sample_entry: Mp4BoxTree,
+}
+
+impl TrackConfig {
+    pub fn new(sample_entry: Mp4BoxTree, timescale: u32) -> Self {
+        TrackConfig {
+            timescale,
+            language: String::from("und"),
+            sample_entry,
+        }
+    }
+
+    pub fn avc(avc1: Avc1Box, avcc: AvcCBox, timescale: u32) -> Self {
+        let avcc = Mp4BoxTree::from_data(BoxData::AvcC(avcc), Vec::new());
+        Self::new(
+            Mp4BoxTree::from_data(BoxData::Avc1(avc1), vec![avcc]),
+            timescale,
+        )
+    }
+
+    // the timescale is the output sampling frequency of the AudioSpecificConfig,
+    // as samplerate in mp4a is limited to 16 bits
+    pub fn aac(mp4a: Mp4aBox, esds: EsdsBox) -> Result<Self> {
+        let timescale = esds
+            .es_desc
+            .dec_config
+            .dec_specific
+            .as_ref()
+            .ok_or(Error::InvalidData("esds has no AudioSpecificConfig"))?
+            .audio_specific_config()?
+            .output_sampling_frequency();
+        if timescale == 0 {
+            return Err(Error::InvalidData("AAC sampling frequency is 0"));
+        }
+        let esds = Mp4BoxTree::from_data(BoxData::Esds(esds), Vec::new());
+        Ok(Self::new(
+            Mp4BoxTree::from_data(BoxData::Mp4a(mp4a), vec![esds]),
+            timescale,
+        ))
+    }
+
+    // handler type and the media header box for the sample entry
+    fn handler(&self) -> Result<(FourCC, &'static str, BoxData)> {
+        match &self.sample_entry.node.data {
+            BoxData::Avc1(_)
+            | BoxData::Hev1(_)
+            | BoxData::Hvc1(_)
+            | BoxData::Vp09(_)
+            | BoxData::Av01(_) => Ok((
+                FourCC { value: *b"vide" },
+                "VideoHandler",
+                BoxData::Vmhd(VmhdBox {
+                    version: 0,
+                    flags: 1,
+                    graphics_mode: 0,
+                    op_color: Default::default(),
+                }),
+            )),
+            BoxData::Mp4a(_) => Ok((
+                FourCC { value: *b"soun" },
+                "SoundHandler",
+                BoxData::Smhd(SmhdBox {
+                    version: 0,
+                    flags: 0,
+                    balance: FixedPointI8::new(0),
+                }),
+            )),
+            _ => Err(Error::InvalidData(
+                "sample entry is not supported by the muxer",
+            )),
+        }
+    }
+
+    pub(crate) fn is_video(&self) -> bool {
+        matches!(self.handler(), Ok((handler_type, _, _)) if handler_type.value == *b"vide")
+    }
+
+    fn visual_size(&self) -> (u16, u16) {
+        match &self.sample_entry.node.data {
+            BoxData::Avc1(b) => (b.width, b.height),
+            BoxData::Hev1(b) => (b.width, b.height),
+            BoxData::Hvc1(b) => (b.width, b.height),
+            BoxData::Vp09(b) => (b.width, b.height),
+            BoxData::Av01(b) => (b.width, b.height),
+            _ => (0, 0),
+        }
+    }
+}
+
+// a sample handed to the muxer; times are in the timescale of its track
+#[derive(Debug, Clone, Copy)]
+pub struct InputSample<'a> {
+    pub dts: u64,
+    pub cts: u64,
+    pub is_sync: bool,
+    pub data: &'a [u8],
+}
+
+// samples of a track as they are laid out in mdat, turned into stbl at the end;
+// the duration of a sample is the distance to the next dts and the last sample
+// repeats the duration of the one before it unless last_duration is set
+#[derive(Debug, Clone, Default)]
+pub(crate) struct SampleTableBuilder {
+    sizes: Vec<u32>,
+    dts: Vec<u64>,
+    composition_offsets: Vec<i64>,
+    sync_samples: Vec<u32>,
+    // offset, sample count and size of each chunk
+    chunks: Vec<(u64, u32, u64)>,
+    pub(crate) last_duration: Option<u32>,
+}
+
+impl SampleTableBuilder {
+    pub(crate) fn start_chunk(&mut self, offset: u64) {
+        self.chunks.push((offset, 0, 0));
+    }
+
+    // appends a sample to the current chunk; the composition offset is cts - dts
+    // and may be negative
+    pub(crate) fn push(
+        &mut self,
+        size: u32,
+        dts: u64,
+        composition_offset: i64,
+        is_sync: bool,
+    ) -> Result<()> {
+        if self.dts.last().is_some_and(|last| dts < *last) {
+            return Err(Error::InvalidData("sample dts must not decrease"));
+        }
+        let chunk = self
+            .chunks
+            .last_mut()
+            .ok_or(Error::InvalidData("sample is pushed before a chunk"))?;
+        chunk.1 += 1;
+        chunk.2 += size as u64;
+
+        self.sizes.push(size);
+        self.dts.push(dts);
+        self.composition_offsets.push(composition_offset);
+        if is_sync {
+            self.sync_samples.push(self.sizes.len() as u32);
+        }
+        Ok(())
+    }
+
+    pub(crate) fn sample_count(&self) -> u32 {
+        self.sizes.len() as u32
+    }
+
+    pub(crate) fn chunk_size(&self) -> Option<u64> {
+        self.chunks.last().map(|c| c.2)
+    }
+
+    fn durations(&self) -> Result<Vec<u32>> {
+        let mut durations = self
+            .dts
+            .windows(2)
+            .map(|w| u32::try_from(w[1] - w[0]))
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(|_| Error::InvalidData("sample duration does not fit in stts"))?;
+        if !self.dts.is_empty() {
+            let last = self
+                .last_duration
+                .or(durations.last().copied())
+                .unwrap_or(0);
+            durations.push(last);
+        }
+        Ok(durations)
+    }
+
+    pub(crate) fn duration(&self) -> Result<u64> {
+        Ok(self.durations()?.iter().map(|d| *d as u64).sum())
+    }
+
+    // dts of the first sample and the earliest cts, which is where presentation starts
+    pub(crate) fn first_dts(&self) -> u64 {
+        self.dts.first().copied().unwrap_or(0)
+    }
+
+    pub(crate) fn earliest_cts(&self) -> u64 {
+        self.dts
+            .iter()
+            .zip(self.composition_offsets.iter())
+            .map(|(dts, offset)| dts.saturating_add_signed(*offset))
+            .min()
+            .unwrap_or(0)
+    }
+
+    pub(crate) fn build(&self, sample_entry: &Mp4BoxTree) -> Result<Mp4BoxTree> {
+        let sample_count = self.sample_count();
+        let stsd =
+            Mp4BoxTree::from_data(BoxData::Stsd(StsdBox::new(1)), vec![sample_entry.clone()]);
+
+        let mut stts = SttsBox {
+            version: 0,
+            flags: 0,
+            entries: Vec::new(),
+        };
+        for duration in self.durations()? {
+            match stts.entries.last_mut() {
+                Some(e) if e.sample_delta == duration => e.sample_count += 1,
+                _ => stts.entries.push(SttsEntry {
+                    sample_count: 1,
+                    sample_delta: duration,
+                }),
+            }
+        }
+        let mut children = vec![stsd, Mp4BoxTree::from_data(BoxData::Stts(stts), Vec::new())];
+
+        if self.composition_offsets.iter().any(|o| *o != 0) {
+            let mut ctts = CttsBox {
+                version: 0,
+                flags: 0,
+                entries: Vec::new(),
+            };
+            for offset in self.composition_offsets.iter() {
+                let offset = i32::try_from(*offset)
+                    .map_err(|_| Error::InvalidData("composition offset does not fit in ctts"))?;
+                if offset < 0 {
+                    ctts.version = 1;
+                }
+                match ctts.entries.last_mut() {
+                    Some(e) if e.sample_offset == offset => e.sample_count += 1,
+                    _ => ctts.entries.push(CttsEntry {
+                        sample_count: 1,
+                        sample_offset: offset,
+                    }),
+                }
+            }
+            children.push(Mp4BoxTree::from_data(BoxData::Ctts(ctts), Vec::new()));
+        }
+
+        // every sample is a sync sample when stss is absent
+        if self.sync_samples.len() as u32 != sample_count {
+            let stss = StssBox {
+                version: 0,
+                flags: 0,
+                entries: self.sync_samples.clone(),
+            };
+            children.push(Mp4BoxTree::from_data(BoxData::Stss(stss), Vec::new()));
+        }
+
+        let mut stsc = StscBox {
+            version: 0,
+            flags: 0,
+            entries: Vec::new(),
+        };
+        let mut first_sample = 1;
+        for (i, (_, count, _)) in self.chunks.iter().enumerate() {
+            if stsc.entries.last().map(|e| e.sample_per_chunk) != Some(*count) {
+                stsc.entries.push(StscEntry {
+                    first_chunk: i as u32 + 1,
+                    sample_per_chunk: *count,
+                    sample_description_index: 1,
+                    first_sample,
+                });
+            }
+            first_sample += count;
+        }
+        children.push(Mp4BoxTree::from_data(BoxData::Stsc(stsc), Vec::new()));
+
+        let uniform = self
+            .sizes
+            .first()
+            .filter(|s| self.sizes.iter().all(|e| e == *s));
+        let stsz = StszBox {
+            version: 0,
+            flags: 0,
+            sample_size: uniform.copied().unwrap_or(0),
+            sample_count,
+            sample_sizes: match uniform {
+                Some(_) => Vec::new(),
+                None => self.sizes.clone(),
+            },
+        };
+        children.push(Mp4BoxTree::from_data(BoxData::Stsz(stsz), Vec::new()));
+
+        let offsets: Vec<u64> = self.chunks.iter().map(|c| c.0).collect();
+        children.push(chunk_offset_box(offsets));
+
+        Ok(Mp4BoxTree::from_data(BoxData::Stbl(StblBox), children))
+    }
+}
+
+// stco, or co64 once an offset does not fit in 32 bits
+pub(crate) fn chunk_offset_box(offsets: Vec<u64>) -> Mp4BoxTree {
+    let data = if offsets.iter().any(|o| *o > u32::MAX as u64) {
+        BoxData::Co64(Co64Box {
+            version: 0,
+            flags: 0,
+            chunk_offsets: offsets,
+        })
+    } else {
+        BoxData::Stco(StcoBox {
+            version: 0,
+            flags: 0,
+            chunk_offsets: offsets.iter().map(|o| *o as u32 as i32).collect(),
+        })
+    };
+    Mp4BoxTree::from_data(data, Vec::new())
+}
+
+pub(crate) fn mvhd(timescale: u32, duration: u64, next_track_id: u32) -> Mp4BoxTree {
+    let mvhd = MvhdBox {
+        version: if duration > u32::MAX as u64 { 1 } else { 0 },
+        flags: 0,
+        creation_time: 0,
+        modification_time: 0,
+        timescale,
+        duration,
+        rate: FixedPointU16::new(1),
+        volume: FixedPointU8::new(1),
+        matrix: Matrix::identity(),
+        next_track_id,
+    };
+    Mp4BoxTree::from_data(BoxData::Mvhd(mvhd), Vec::new())
+}
+
+// trak with the given stbl; duration is in the movie timescale and
+// media_duration in the timescale of the track
+pub(crate) fn trak(
+    config: &TrackConfig,
+    track_id: u32,
+    duration: u64,
+    edits: Vec<ElstEntry>,
+    media_duration: u64,
+    stbl: Mp4BoxTree,
+) -> Result<Mp4BoxTree> {
+    let (handler_type, handler_name, media_header) = config.handler()?;
+    let (width, height) = config.visual_size();
+
+    let tkhd = TkhdBox {
+        version: if duration > u32::MAX as u64 { 1 } else { 0 },
+        flags: 0x3, // enabled and in movie
+        creation_time: 0,
+        modification_time: 0,
+        track_id,
+        duration,
+        layer: 0,
+        alternate_group: 0,
+        volume: match media_header {
+            BoxData::Smhd(_) => FixedPointU8::new(1),
+            _ => FixedPointU8::new(0),
+        },
+        matrix: Matrix::identity(),
+        width: FixedPointU16::new(width),
+        height: FixedPointU16::new(height),
+    };
+    let mut children = vec![Mp4BoxTree::from_data(BoxData::Tkhd(tkhd), Vec::new())];
+
+    if !edits.is_empty() {
+        children.push(edts(edits));
+    }
+
+    let mdhd = MdhdBox {
+        version: if media_duration > u32::MAX as u64 {
+            1
+        } else {
+            0
+        },
+        flags: 0,
+        creation_time: 0,
+        modification_time: 0,
+        timescale: config.timescale,
+        duration: media_duration,
+        language: config.language.clone(),
+    };
+    let hdlr = HdlrBox {
+        version: 0,
+        flags: 0,
+        pre_defined: 0,
+        handler_type,
+        reserved: [0; 12],
+        name: String::from(handler_name),
+        raw_name: None,
+    };
+    // a single self-contained data reference
+    let url = UrlBox {
+        version: 0,
+        flags: 1,
+        location: String::new(),
+    };
+    let dref = Mp4BoxTree::from_data(
+        BoxData::Dref(DrefBox::new(1)),
+        vec![Mp4BoxTree::from_data(BoxData::Url(url), Vec::new())],
+    );
+    let minf = Mp4BoxTree::from_data(
+        BoxData::Minf(MinfBox),
+        vec![
+            Mp4BoxTree::from_data(media_header, Vec::new()),
+            Mp4BoxTree::from_data(BoxData::Dinf(DinfBox), vec![dref]),
+            stbl,
+        ],
+    );
+    let mdia = Mp4BoxTree::from_data(
+        BoxData::Mdia(MdiaBox),
+        vec![
+            Mp4BoxTree::from_data(BoxData::Mdhd(mdhd), Vec::new()),
+            Mp4BoxTree::from_data(BoxData::Hdlr(hdlr), Vec::new()),
+            minf,
+        ],
+    );
+    children.push(mdia);
+
+    Ok(Mp4BoxTree::from_data(BoxData::Trak(TrakBox), children))
+}
+
+pub(crate) fn edts(edits: Vec<ElstEntry>) -> Mp4BoxTree {
+    let large = edits
+        .iter()
+        .any(|e| e.segment_duration > u32::MAX as u64 || e.media_time > i32::MAX as i64);
+    let elst = ElstBox {
+        version: if large { 1 } else { 0 },
+        flags: 0,
+        entries: edits,
+    };
+    let elst = Mp4BoxTree::from_data(BoxData::Elst(elst), Vec::new());
+    Mp4BoxTree::from_data(BoxData::Edts(EdtsBox), vec![elst])
+}
+
+pub(crate) fn default_ftyp() -> FtypBox {
+    FtypBox {
+        major_brand: FourCC { value: *b"isom" },
+        minor_version: 512,
+        compatible_brands: vec![
+            FourCC { value: *b"isom" },
+            FourCC { value: *b"iso2" },
+            FourCC { value: *b"avc1" },
+            FourCC { value: *b"mp41" },
+        ],
+    }
+}
+
+#[derive(Debug, Clone)]
+struct MuxTrack {
+    track_id: u32,
+    config: TrackConfig,
+    samples: SampleTableBuilder,
+}
+
+// progressive muxer; samples are written to mdat as they come and moov is
+// written after mdat by finalize
+pub struct Mp4Writer<W> {
+    writer: W,
+    position: u64,
+    // start of the 16 bytes reserved for the mdat header
+    mdat_offset: u64,
+    tracks: Vec<MuxTrack>,
+    // index of the track the last sample was written for
+    current: Option<usize>,
+}
+
+impl<W: Write + Seek> Mp4Writer<W> {
+    pub fn new(writer: W) -> Result<Self> {
+        Self::with_ftyp(writer, default_ftyp())
+    }
+
+    pub fn with_ftyp(mut writer: W, ftyp: FtypBox) -> Result<Self> {
+        let ftyp = Mp4BoxTree::from_data(BoxData::Ftyp(ftyp), Vec::new());
+        let mdat_offset = writer::write_mp4_box_tree(&[ftyp], &mut writer)?;
+
+        // wide is turned into a large mdat header when mdat exceeds 4GB
+        writer.write_all(&8u32.to_be_bytes())?;
+        writer.write_all(b"wide")?;
+        writer.write_all(&0u32.to_be_bytes())?;
+        writer.write_all(b"mdat")?;
+
+        Ok(Mp4Writer {
+            writer,
+            position: mdat_offset + 16,
+            mdat_offset,
+            tracks: Vec::new(),
+            current: None,
+        })
+    }
+
+    // returns the track_id of the new track
+    pub fn add_track(&mut self, config: TrackConfig) -> Result<u32> {
+        config.handler()?;
+        if config.timescale == 0 {
+            return Err(Error::InvalidData("timescale must not be zero"));
+        }
+        let track_id = self.tracks.len() as u32 + 1;
+        self.tracks.push(MuxTrack {
+            track_id,
+            config,
+            samples: SampleTableBuilder::default(),
+        });
+        Ok(track_id)
+    }
+
+    pub fn write_sample(&mut self, track_id: u32, sample: &InputSample) -> Result<()> {
+        let i = self
+            .tracks
+            .iter()
+            .position(|t| t.track_id == track_id)
+            .ok_or(Error::TrakNotFound(track_id))?;
+        let size = u32::try_from(sample.data.len())
+            .map_err(|_| Error::InvalidData("sample is too large"))?;
+
+        let samples = &mut self.tracks[i].samples;
+        let continues = self.current == Some(i)
+            && samples
+                .chunk_size()
+                .is_some_and(|s| s + size as u64 <= MAX_CHUNK_SIZE);
+        if !continues {
+            samples.start_chunk(self.position);
+        }
+        samples.push(
+            size,
+            sample.dts,
+            sample.cts as i64 - sample.dts as i64,
+            sample.is_sync,
+        )?;
+
+        self.writer.write_all(sample.data)?;
+        self.position += size as u64;
+        self.current = Some(i);
+        Ok(())
+    }
+
+    // completes mdat and writes moov; the writer is returned positioned at the end
+    pub fn finalize(mut self) -> Result<W> {
+        let mdat_size = self.position - self.mdat_offset - 8;
+        if mdat_size > u32::MAX as u64 {
+            self.writer.seek(SeekFrom::Start(self.mdat_offset))?;
+            self.writer.write_all(&1u32.to_be_bytes())?;
+            self.writer.write_all(b"mdat")?;
+            self.writer.write_all(&(mdat_size + 8).to_be_bytes())?;
+        } else {
+            self.writer.seek(SeekFrom::Start(self.mdat_offset + 8))?;
+            self.writer.write_all(&(mdat_size as u32).to_be_bytes())?;
+        }
+        self.writer.seek(SeekFrom::Start(self.position))?;
+
+        let moov = self.moov()?;
+        writer::write_mp4_box_tree(&[moov], &mut self.writer)?;
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+
+    fn moov(&self) -> Result<Mp4BoxTree> {
+        // tracks starting later than the earliest one get an empty edit
+        let start = self
+            .tracks
+            .iter()
+            .filter(|t| t.samples.sample_count() > 0)
+            .map(|t| {
+                rescale(
+                    t.samples.earliest_cts(),
+                    t.config.timescale,
+                    MOVIE_TIMESCALE,
+                )
+            })
+            .min()
+            .unwrap_or(0);
+
+        let mut traks = Vec::with_capacity(self.tracks.len());
+        let mut movie_duration = 0;
+        for t in self.tracks.iter() {
+            let timescale = t.config.timescale;
+            let media_duration = t.samples.duration()?;
+            let earliest_cts = t.samples.earliest_cts();
+            let media_time = earliest_cts.saturating_sub(t.samples.first_dts());
+            let delay = rescale(earliest_cts, timescale, MOVIE_TIMESCALE).saturating_sub(start);
+
+            let mut edits = Vec::new();
+            if delay > 0 {
+                edits.push(ElstEntry {
+                    segment_duration: delay,
+                    media_time: -1,
+                    media_rate_integer: 1,
+                    media_rate_fraction: 0,
+                });
+            }
+            if delay > 0 || media_time > 0 {
+                edits.push(ElstEntry {
+                    segment_duration: rescale(
+                        media_duration.saturating_sub(media_time),
+                        timescale,
+                        MOVIE_TIMESCALE,
+                    ),
+                    media_time: media_time as i64,
+                    media_rate_integer: 1,
+                    media_rate_fraction: 0,
+                });
+            }
+            let duration = if edits.is_empty() {
+                rescale(media_duration, timescale, MOVIE_TIMESCALE)
+            } else {
+                edits.iter().map(|e| e.segment_duration).sum()
+            };
+            movie_duration = movie_duration.max(duration);
+
+            let stbl = t.samples.build(&t.config.sample_entry)?;
+            traks.push(trak(
+                &t.config,
+                t.track_id,
+                duration,
+                edits,
+                media_duration,
+                stbl,
+            )?);
+        }
+
+        let mut children = vec![mvhd(
+            MOVIE_TIMESCALE,
+            movie_duration,
+            self.tracks.len() as u32 + 1,
+        )];
+        children.extend(traks);
+        Ok(Mp4BoxTree::from_data(BoxData::Moov(MoovBox), children))
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::boxes::{
+        DecoderConfigDescriptor, DecoderSpecificInfo, EsDescriptor, SlConfigDescriptor,
+    };
+    use crate::reader::Mp4Reader;
+    use std::io::Cursor;
+
+    pub(crate) const VIDEO_TIMESCALE: u32 = 12800;
+    pub(crate) const AUDIO_TIMESCALE: u32 = 44100;
+
+    #[derive(Debug, Clone)]
+    pub(crate) struct TestSample {
+        pub track_id: u32,
+        pub dts: u64,
+        pub cts: u64,
+        pub is_sync: bool,
+        pub data: Vec<u8>,
+    }
+
+    pub(crate) fn video_config() -> TrackConfig {
+        let avc1 = Avc1Box {
+            data_reference_index: 1,
+            width: 320,
+            height: 240,
+            horizresolution: FixedPointU16::new(72),
+            vertresolution: FixedPointU16::new(72),
+            frame_count: 1,
+            compressorname: [0; 32],
+            depth: 0x18,
+            pre_defined: [0; 16],
+            reserved: 0,
+            color_table_id: -1,
+        };
+        TrackConfig::new(
+            Mp4BoxTree::from_data(BoxData::Avc1(avc1), Vec::new()),
+            VIDEO_TIMESCALE,
+        )
+    }
+
+    pub(crate) fn audio_config() -> TrackConfig {
+        let mp4a = Mp4aBox {
+            data_reference_index: 1,
+            version: 0,
+            channelcount: 2,
+            samplesize: 16,
+            samplerate: FixedPointU16::new(AUDIO_TIMESCALE as u16),
+            ext: Vec::new(),
+        };
+        TrackConfig::new(
+            Mp4BoxTree::from_data(BoxData::Mp4a(mp4a), Vec::new()),
+            AUDIO_TIMESCALE,
+        )
+    }
+
+    // 25 fps video as track 1 with a keyframe every 10 frames, and when audio
+    // is set 1024-sample AAC frames as track 2, interleaved in decode order.
+    // odd frames are presented before the even frame decoded ahead of them
+    pub(crate) fn test_samples(frames: u64, audio: bool) -> Vec<TestSample> {
+        let mut samples = Vec::new();
+        let mut audio_frame = 0;
+        for i in 0..frames {
+            let dts = i * 512;
+            samples.push(TestSample {
+                track_id: 1,
+                dts,
+                cts: if i % 2 == 0 { dts + 1024 } else { dts },
+                is_sync: i % 10 == 0,
+                data: test_data(samples.len(), 1000 + (i % 7) as usize * 100),
+            });
+            while audio
+                && audio_frame * 1024 * (VIDEO_TIMESCALE as u64)
+                    < (dts + 512) * AUDIO_TIMESCALE as u64
+            {
+                samples.push(TestSample {
+                    track_id: 2,
+                    dts: audio_frame * 1024,
+                    cts: audio_frame * 1024,
+                    is_sync: true,
+                    data: test_data(samples.len(), 200 + (audio_frame % 3) as usize),
+                });
+                audio_frame += 1;
+            }
+        }
+        samples
+    }
+
+    // bytes which differ between samples, so a wrong offset is noticed
+    fn test_data(seed: usize, size: usize) -> Vec<u8> {
+        (0..size).map(|i| (seed * 7 + i) as u8).collect()
+    }
+
+    pub(crate) fn mux(samples: &[TestSample]) -> Vec<u8> {
+        let mut writer = Mp4Writer::new(Cursor::new(Vec::new())).unwrap();
+        writer.add_track(video_config()).unwrap();
+        if samples.iter().any(|s| s.track_id == 2) {
+            writer.add_track(audio_config()).unwrap();
+        }
+        for s in samples.iter() {
+            let sample = InputSample {
+                dts: s.dts,
+                cts: s.cts,
+                is_sync: s.is_sync,
+                data: &s.data,
+            };
+            writer.write_sample(s.track_id, &sample).unwrap();
+        }
+        writer.finalize().unwrap().into_inner()
+    }
+
+    // the samples of each track read back match what was written
+    pub(crate) fn assert_samples<R: std::io::Read + Seek>(
+        reader: &mut Mp4Reader<R>,
+        expected: &[TestSample],
+    ) {
+        for track_id in [1, 2] {
+            let expected: Vec<_> = expected.iter().filter(|s| s.track_id == track_id).collect();
+            if expected.is_empty() {
+                continue;
+            }
+            let track = reader.track(track_id).unwrap().clone();
+            assert_eq!(track.sample_count() as usize, expected.len());
+            for (sample, e) in track.samples().zip(expected.iter()) {
+                let sample = sample.unwrap();
+                assert_eq!(
+                    (sample.dts, sample.cts, sample.is_sync),
+                    (e.dts, e.cts, e.is_sync),
+                    "track {} sample {}",
+                    track_id,
+                    sample.index
+                );
+                let data = reader.read_sample(track_id, sample.index).unwrap();
+                assert_eq!(&data[..], &e.data[..]);
+            }
+        }
+    }
+
+    fn aac_config(asc: &[u8]) -> Result<TrackConfig> {
+        let BoxData::Mp4a(mp4a) = audio_config().sample_entry.node.data else {
+            unreachable!();
+        };
+        let esds = EsdsBox {
+            version: 0,
+            flags: 0,
+            es_desc: EsDescriptor {
+                es_id: 2,
+                stream_priority: 0,
+                depends_on_es_id: None,
+                url: None,
+                ocr_es_id: None,
+                dec_config: DecoderConfigDescriptor {
+                    object_type_indication: 0x40,
+                    stream_type: 5,
+                    up_stream: false,
+                    buffer_size_db: 0,
+                    max_bitrate: 0,
+                    avg_bitrate: 0,
+                    dec_specific: Some(DecoderSpecificInfo {
+                        bytes: asc.to_vec(),
+                    }),
+                    ext: Vec::new(),
+                },
+                sl_config: Some(SlConfigDescriptor {
+                    predefined: 2,
+                    ext: Vec::new(),
+                }),
+                ext: Vec::new(),
+            },
+            compact_size: false,
+        };
+        TrackConfig::aac(mp4a, esds)
+    }
+
+    #[test]
+    fn aac_timescale_from_audio_specific_config() {
+        // HE-AAC at 48000 Hz over a 24000 Hz core
+        assert_eq!(
+            aac_config(&[0x2B, 0x11, 0x88, 0x00]).unwrap().timescale,
+            48000
+        );
+        // 96000 Hz does not fit in the samplerate of mp4a
+        assert_eq!(aac_config(&[0x10, 0x10]).unwrap().timescale, 96000);
+        // an explicit sampling frequency of 0
+        assert!(aac_config(&[0x17, 0x80, 0x00, 0x00, 0x10]).is_err());
+    }
+
+    #[test]
+    fn mux_read_back() {
+        let samples = test_samples(50, true);
+        let buf = mux(&samples);
+
+        // samples follow the ftyp, the reserved wide and the mdat header in the
+        // order they were written
+        let mut offset =
+            Mp4BoxTree::from_data(BoxData::Ftyp(default_ftyp()), Vec::new()).size() + 16;
+        let mut offsets = Vec::new();
+        for s in samples.iter() {
+            offsets.push(offset);
+            offset += s.data.len() as u64;
+        }
+
+        let size = buf.len() as u64;
+        let mut reader = Mp4Reader::new(Cursor::new(buf), size).unwrap();
+        for track_id in [1, 2] {
+            let track = reader.track(track_id).unwrap();
+            let expected = samples
+                .iter()
+                .zip(offsets.iter())
+                .filter(|(s, _)| s.track_id == track_id);
+            for (sample, (e, offset)) in track.samples().zip(expected) {
+                let sample = sample.unwrap();
+                assert_eq!(sample.offset, *offset);
+                assert_eq!(sample.size as usize, e.data.len());
+            }
+        }
+        assert_samples(&mut reader, &samples);
+
+        // video is presented from its earliest cts, 40 ms after audio starts
+        let track = reader.track(1).unwrap();
+        assert_eq!(track.presentation_time(512), Some(512));
+        assert_eq!(track.presentation_time(0), None);
+        let track = reader.track(2).unwrap();
+        assert_eq!(track.presentation_time(0), Some(0));
+    }
+}
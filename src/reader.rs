@@ -1,34 +1,69 @@
-use std::io::{Read, Seek};
+use std::io::{Cursor, Read, Seek, SeekFrom};
 
-use crate::boxes::{self, BoxData, BoxHeader, BoxType, Mp4Box, Mp4BoxTree, PhtmBox, ReadBox};
+use crate::boxes::{
+    self, BoxData, BoxHeader, BoxType, Mp4Box, Mp4BoxTree, PhtmBox, ReadBox, TextSample, UnknownBox,
+};
 use crate::error::Error;
+use crate::track::{self, Track};
+use crate::types::Bytes;
 use crate::{Result, Scanning};
 
+#[derive(Debug, Clone, Copy)]
+pub struct ReadOptions {
+    // payloads of unknown boxes larger than this are not kept in memory
+    pub max_unknown_data_size: u64,
+}
+
+impl Default for ReadOptions {
+    fn default() -> Self {
+        ReadOptions {
+            max_unknown_data_size: 64 * 1024,
+        }
+    }
+}
+
 macro_rules! dispatch {
-    ( $self:expr, $reader:expr, $header:expr, $callback:expr; $( $field:ident => $value:ident, )* ) => {
-        dispatch!( $self, $reader, $header, $callback; $( $field => $value ),* )
+    ( $self:expr, $reader:expr, $header:expr, $callback:expr, $options:expr; $( $field:ident => $value:ident, )* ) => {
+        dispatch!( $self, $reader, $header, $callback, $options; $( $field => $value ),* )
     };
-    ( $self:expr, $reader:expr, $header:expr, $callback:expr; $( $field:ident => $value:ident ),* ) => {
+    ( $self:expr, $reader:expr, $header:expr, $callback:expr, $options:expr; $( $field:ident => $value:ident ),* ) => {
         match $header.typ {
             $(
                 BoxType::$field => {
-                    let b = boxes::$value::read($reader, &$header)?;
-                    let data = BoxData::$field(b);
+                    let data = match boxes::$value::read($reader, &$header) {
+                        Ok(b) => BoxData::$field(b),
+                        // a version which is not parsed is kept as an opaque box
+                        Err(Error::UnsupportedBoxVersion(..)) => {
+                            boxes::abs_skip($reader, $header.offset + $header.header_size())?;
+                            let b = UnknownBox::read($reader, &$header, $options.max_unknown_data_size)?;
+                            BoxData::Unknown(b)
+                        }
+                        Err(e) => return Err(e),
+                    };
 
                     let mut scanning = Scanning::Continue;
                     if let Some(c) = $callback {
                         scanning = c(&data)
                     }
                     match scanning {
-                        Scanning::Continue => $self.traverse($reader, $header, data, $callback)?,
+                        Scanning::Continue => $self.traverse($reader, $header, data, $callback, $options)?,
                         _ => ()
                     }
                     scanning
                 }
             )*
             _ => {
+                let b = UnknownBox::read($reader, &$header, $options.max_unknown_data_size)?;
+                let data = BoxData::Unknown(b);
+
+                let mut scanning = Scanning::Continue;
+                if let Some(c) = $callback {
+                    scanning = c(&data)
+                } else {
+                    $self.children.push(Self::new(Mp4Box::new($header, data)));
+                }
                 $header.skip_box($reader)?;
-                Scanning::Continue
+                scanning
             },
         }
     };
@@ -48,6 +83,7 @@ impl Mp4BoxTree {
         header: BoxHeader,
         data: BoxData,
         callback: &mut Option<&mut dyn FnMut(&BoxData) -> Scanning>,
+        options: &ReadOptions,
     ) -> Result<()> {
         let skip_size = data.effective_size();
         let box_start = header.box_start() + skip_size;
@@ -56,20 +92,13 @@ impl Mp4BoxTree {
         let mp4 = Mp4Box::new(header, data);
         let mut tree = Self::new(mp4);
         let remain = header.size - skip_size;
-        if remain > boxes::HEADER_SIZE {
-            let new_tree = tree.read(reader, box_start + remain, callback)?;
+        if remain > 0 {
+            let new_tree = tree.read(reader, box_start + remain, callback, options)?;
             if let Some(t) = new_tree {
                 self.children.push(t);
             }
-        } else {
-            if remain > 0 {
-                // skip because readable data is left but it is less than header size
-                boxes::rel_skip(reader, remain)?;
-            }
-            match callback {
-                None => self.children.push(tree),
-                _ => (),
-            }
+        } else if callback.is_none() {
+            self.children.push(tree);
         }
         Ok(())
     }
@@ -79,12 +108,25 @@ impl Mp4BoxTree {
         reader: &mut R,
         size: u64,
         callback: &mut Option<&mut dyn FnMut(&BoxData) -> Scanning>,
+        options: &ReadOptions,
     ) -> Result<Option<Self>> {
         let start = reader.stream_position()?;
 
         let mut current = start;
         while current < size {
-            let header = boxes::read_box_header(reader, current)?;
+            if size - current < boxes::HEADER_SIZE {
+                // too few bytes are left for a box, they are kept as an opaque tail
+                let header = BoxHeader::new(BoxType::from(0), size - current, current);
+                let b = UnknownBox::read(reader, &header, options.max_unknown_data_size)?;
+                if callback.is_none() {
+                    self.children
+                        .push(Self::new(Mp4Box::new(header, BoxData::Unknown(b))));
+                }
+                boxes::abs_skip(reader, size)?;
+                break;
+            }
+
+            let mut header = boxes::read_box_header(reader, current)?;
             if header.size > size {
                 return Err(Error::InvalidData(
                     "file contains a box with a larger size than it",
@@ -92,17 +134,23 @@ impl Mp4BoxTree {
             }
 
             if header.size == 0 {
-                break;
+                // box extends to the end of its container
+                header.size = size - current;
             }
 
             let scanning = dispatch! {
-                self, reader, header, callback;
+                self, reader, header, callback, options;
                 Ftyp => FtypBox,
+                Styp => StypBox,
                 Moov => MoovBox,
                 Mvhd => MvhdBox,
+                Mvex => MvexBox,
+                Mehd => MehdBox,
+                Trex => TrexBox,
                 Trak => TrakBox,
                 Tkhd => TkhdBox,
                 Edts => EdtsBox,
+                Elst => ElstBox,
                 Mdia => MdiaBox,
                 Mdhd => MdhdBox,
                 Hdlr => HdlrBox,
@@ -116,6 +164,25 @@ impl Mp4BoxTree {
                 Stsd => StsdBox,
                 Avc1 => Avc1Box,
                 AvcC => AvcCBox,
+                Hev1 => Hev1Box,
+                Hvc1 => Hvc1Box,
+                HvcC => HvcCBox,
+                Mp4a => Mp4aBox,
+                Esds => EsdsBox,
+                Vp09 => Vp09Box,
+                Vpcc => VpccBox,
+                Av01 => Av01Box,
+                Av1C => Av1CBox,
+                Tx3g => Tx3gBox,
+                Ftab => FtabBox,
+                Udta => UdtaBox,
+                Meta => MetaBox,
+                Ilst => IlstBox,
+                Emsg => EmsgBox,
+                Sidx => SidxBox,
+                Mfra => MfraBox,
+                Tfra => TfraBox,
+                Mfro => MfroBox,
                 Pasp => PaspBox,
                 Stts => SttsBox,
                 Ctts => CttsBox,
@@ -123,11 +190,17 @@ impl Mp4BoxTree {
                 Stsc => StscBox,
                 Stsz => StszBox,
                 Stco => StcoBox,
+                Co64 => Co64Box,
+                Moof => MoofBox,
+                Mfhd => MfhdBox,
+                Traf => TrafBox,
+                Tfhd => TfhdBox,
+                Tfdt => TfdtBox,
+                Trun => TrunBox,
             };
 
-            match scanning {
-                Scanning::Stop => break,
-                _ => (),
+            if let Scanning::Stop = scanning {
+                break;
             }
 
             current = reader.stream_position()?;
@@ -146,6 +219,24 @@ impl Mp4BoxTree {
     pub fn node_data_ref(&self) -> &BoxData {
         &self.node.data
     }
+
+    pub fn find(&self, typ: BoxType) -> Option<&Mp4BoxTree> {
+        self.children.iter().find(|c| c.node.header.typ == typ)
+    }
+
+    pub fn find_mut(&mut self, typ: BoxType) -> Option<&mut Mp4BoxTree> {
+        self.children.iter_mut().find(|c| c.node.header.typ == typ)
+    }
+
+    pub fn find_all(&self, typ: BoxType) -> impl Iterator<Item = &Mp4BoxTree> {
+        self.children
+            .iter()
+            .filter(move |c| c.node.header.typ == typ)
+    }
+
+    pub fn find_path(&self, path: &[BoxType]) -> Option<&Mp4BoxTree> {
+        path.iter().try_fold(self, |tree, typ| tree.find(*typ))
+    }
 }
 
 impl Mp4Box {
@@ -159,17 +250,23 @@ impl Mp4Box {
                 typ: BoxType::Phtm,
                 size: 0,
                 offset: 0,
+                largesize: false,
+                to_end: false,
             },
             data: BoxData::Phtm(PhtmBox),
         }
     }
 }
 
-pub fn read_mp4_box<R: Read + Seek>(reader: &mut R, size: u64) -> Result<Vec<Mp4BoxTree>> {
+pub fn read_mp4_box<R: Read + Seek>(
+    reader: &mut R,
+    size: u64,
+    options: &ReadOptions,
+) -> Result<Vec<Mp4BoxTree>> {
     let phtm = Mp4Box::phtm();
     let mut tree = Mp4BoxTree::new(phtm);
     let mut c = None as Option<&mut dyn FnMut(&BoxData) -> Scanning>;
-    let root = tree.read(reader, size, &mut c)?.unwrap();
+    let root = tree.read(reader, size, &mut c, options)?.unwrap();
     Ok(root.children)
 }
 
@@ -180,6 +277,194 @@ pub fn scan_mp4_box<R: Read + Seek, C: FnMut(&BoxData) -> Scanning>(
 ) -> Result<()> {
     let phtm = Mp4Box::phtm();
     let mut tree = Mp4BoxTree::new(phtm);
-    _ = tree.read(reader, size, &mut Option::Some(callback))?;
+    let options = ReadOptions::default();
+    _ = tree.read(reader, size, &mut Option::Some(callback), &options)?;
     Ok(())
 }
+
+// finds mfra through the mfro at the end of the file so that the boxes before
+// it are not read; None if the file does not end with mfro
+pub fn read_mfra<R: Read + Seek>(
+    reader: &mut R,
+    size: u64,
+    options: &ReadOptions,
+) -> Result<Option<Mp4BoxTree>> {
+    let mfro_size = boxes::HEADER_SIZE + 8;
+    if size < mfro_size {
+        return Ok(None);
+    }
+    let mfro_start = size - mfro_size;
+    boxes::abs_skip(reader, mfro_start)?;
+    let header = boxes::read_box_header(reader, mfro_start)?;
+    if header.typ != BoxType::Mfro || header.size != mfro_size {
+        return Ok(None);
+    }
+    let mfro = boxes::MfroBox::read(reader, &header)?;
+
+    let mfra_start = size
+        .checked_sub(mfro.size as u64)
+        .ok_or(Error::InvalidData("mfro size is larger than the file"))?;
+    boxes::abs_skip(reader, mfra_start)?;
+    let header = boxes::read_box_header(reader, mfra_start)?;
+    if header.typ != BoxType::Mfra || header.size != mfro.size as u64 {
+        return Err(Error::InvalidData("mfro does not point to mfra"));
+    }
+
+    boxes::abs_skip(reader, mfra_start)?;
+    let mut tree = Mp4BoxTree::new(Mp4Box::phtm());
+    let mut c = None as Option<&mut dyn FnMut(&BoxData) -> Scanning>;
+    let root = tree.read(reader, size, &mut c, options)?.unwrap();
+    Ok(root.children.into_iter().next())
+}
+
+pub struct Mp4Reader<R> {
+    reader: R,
+    data: Option<Bytes>,
+    pub trees: Vec<Mp4BoxTree>,
+    pub tracks: Vec<Track>,
+}
+
+impl<R: Read + Seek> Mp4Reader<R> {
+    pub fn new(mut reader: R, size: u64) -> Result<Self> {
+        let trees = read_mp4_box(&mut reader, size, &ReadOptions::default())?;
+        let tracks = track::tracks(&trees)?;
+        Ok(Mp4Reader {
+            reader,
+            data: None,
+            trees,
+            tracks,
+        })
+    }
+
+    // reads a standalone media segment; trex and stsd of the init segment are
+    // used to resolve its samples
+    pub fn with_init_segment(mut reader: R, size: u64, init: &[Mp4BoxTree]) -> Result<Self> {
+        let trees = read_mp4_box(&mut reader, size, &ReadOptions::default())?;
+        let tracks = track::segment_tracks(init, &trees)?;
+        Ok(Mp4Reader {
+            reader,
+            data: None,
+            trees,
+            tracks,
+        })
+    }
+
+    pub fn track(&self, track_id: u32) -> Result<&Track> {
+        self.tracks
+            .iter()
+            .find(|t| t.track_id == track_id)
+            .ok_or(Error::TrakNotFound(track_id))
+    }
+
+    pub fn read_sample(&mut self, track_id: u32, sample_index: u32) -> Result<Bytes> {
+        let track = self.track(track_id)?;
+        let sample = track.sample(sample_index)?;
+        if !track.is_self_contained(sample.description_index) {
+            return Err(Error::DataReferenceNotSelfContained(
+                track_id,
+                track.data_reference_index(sample.description_index),
+            ));
+        }
+
+        let start = sample.offset;
+        let end = start + sample.size as u64;
+        if let Some(data) = &self.data {
+            if end > data.len() as u64 {
+                return Err(Error::InvalidData("sample is out of the source"));
+            }
+            return Ok(data.slice(start as usize..end as usize));
+        }
+
+        self.reader.seek(SeekFrom::Start(start))?;
+        let mut buf = vec![0u8; sample.size as usize];
+        self.reader.read_exact(&mut buf)?;
+        Ok(Bytes::from(buf))
+    }
+
+    // decodes a sample of a tx3g timed text track
+    pub fn read_text_sample(&mut self, track_id: u32, sample_index: u32) -> Result<TextSample> {
+        let data = self.read_sample(track_id, sample_index)?;
+        TextSample::read(&data)
+    }
+}
+
+impl Mp4Reader<Cursor<Bytes>> {
+    // samples are returned as zero-copy slices of the given bytes
+    pub fn from_bytes(data: Bytes) -> Result<Self> {
+        let size = data.len() as u64;
+        let mut reader = Self::new(Cursor::new(data.clone()), size)?;
+        reader.data = Some(data);
+        Ok(reader)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::boxes::TfhdBox;
+    use crate::faststart::MDAT;
+    use crate::fragmenter::tests::fragment;
+    use crate::fragmenter::FragmentOptions;
+    use crate::muxer::tests::{assert_samples, test_samples};
+    use crate::writer;
+    use std::io::Cursor;
+
+    #[test]
+    fn media_segment_with_init_segment() {
+        let samples = test_samples(50, false);
+        let buf = fragment(&samples, FragmentOptions::default());
+        let size = buf.len() as u64;
+        let trees = read_mp4_box(&mut Cursor::new(buf), size, &ReadOptions::default()).unwrap();
+        let (mut media, mut init): (Vec<_>, Vec<_>) = trees.into_iter().partition(|t| {
+            t.node.header.typ == BoxType::Moof || u32::from(t.node.header.typ) == MDAT
+        });
+
+        // the video samples all last 512 and all but the first are not sync
+        // samples; these defaults move from tfhd to trex, which leaves each
+        // moof 8 bytes shorter
+        let moov = init
+            .iter_mut()
+            .find(|t| t.node.header.typ == BoxType::Moov)
+            .unwrap();
+        let trex = moov
+            .find_mut(BoxType::Mvex)
+            .unwrap()
+            .find_mut(BoxType::Trex)
+            .unwrap();
+        let BoxData::Trex(trex) = &mut trex.node.data else {
+            panic!("mvex has no trex");
+        };
+        assert_eq!(trex.track_id, 1);
+        trex.default_sample_duration = 512;
+        trex.default_sample_flags = 0x0101_0000;
+        for moof in media
+            .iter_mut()
+            .filter(|t| t.node.header.typ == BoxType::Moof)
+        {
+            let traf = moof.find_mut(BoxType::Traf).unwrap();
+            let tfhd = traf.find_mut(BoxType::Tfhd).unwrap();
+            let BoxData::Tfhd(tfhd) = &mut tfhd.node.data else {
+                panic!("traf has no tfhd");
+            };
+            assert_eq!(tfhd.default_sample_duration, Some(512));
+            assert_eq!(tfhd.default_sample_flags, Some(0x0101_0000));
+            tfhd.flags &=
+                !(TfhdBox::FLAG_DEFAULT_SAMPLE_DURATION | TfhdBox::FLAG_DEFAULT_SAMPLE_FLAGS);
+            tfhd.default_sample_duration = None;
+            tfhd.default_sample_flags = None;
+            let trun = traf.find_mut(BoxType::Trun).unwrap();
+            if let BoxData::Trun(trun) = &mut trun.node.data {
+                trun.data_offset = trun.data_offset.map(|o| o - 8);
+            }
+        }
+
+        // the media segment starts with its first moof
+        let mut segment = Vec::new();
+        writer::write_mp4_box_tree(&media, &mut segment).unwrap();
+        let size = segment.len() as u64;
+        let mut reader = Mp4Reader::with_init_segment(Cursor::new(segment), size, &init).unwrap();
+        let track = reader.track(1).unwrap();
+        assert_eq!(track.runs.len(), 5);
+        assert_samples(&mut reader, &samples);
+    }
+}
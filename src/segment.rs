@@ -0,0 +1,505 @@
+use serde::Serialize;
+
+use crate::boxes::{
+    BoxData, BoxType, Mp4BoxTree, SidxBox, SidxRange, SidxReference, TfhdBox, TfraBox, TfraEntry,
+};
+use crate::error::Error;
+use crate::track::{find_box, tracks};
+use crate::Result;
+
+// random access points of a track taken from tfra, ordered by time
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FragmentIndex {
+    pub track_id: u32,
+    pub entries: Vec<TfraEntry>,
+}
+
+impl FragmentIndex {
+    pub fn from_mfra(mfra: &Mp4BoxTree) -> Result<Vec<Self>> {
+        mfra.find_all(BoxType::Tfra)
+            .map(|tfra| {
+                let tfra: &TfraBox = tfra.try_into()?;
+                let mut entries = tfra.entries.clone();
+                entries.sort_by_key(|e| e.time);
+                Ok(FragmentIndex {
+                    track_id: tfra.track_id,
+                    entries,
+                })
+            })
+            .collect()
+    }
+
+    // the last random access point at or before the time in the timescale of the track
+    pub fn entry_at(&self, time: u64) -> Option<&TfraEntry> {
+        let i = self.entries.partition_point(|e| e.time <= time);
+        i.checked_sub(1).map(|i| &self.entries[i])
+    }
+
+    pub fn moof_offset(&self, time: u64) -> Option<u64> {
+        self.entry_at(time).map(|e| e.moof_offset)
+    }
+}
+
+// resolves the sidx of a track into the media ranges it indexes, following
+// references to other sidx boxes in the file
+pub fn segment_ranges(trees: &[Mp4BoxTree], track_id: u32) -> Result<Vec<SidxRange>> {
+    let root = trees
+        .iter()
+        .find(|t| matches!(&t.node.data, BoxData::Sidx(s) if s.reference_id == track_id))
+        .ok_or(Error::BoxNotFound(BoxType::Sidx))?;
+
+    let mut ranges = Vec::new();
+    let mut stack = vec![root];
+    while let Some(tree) = stack.pop() {
+        let sidx: &SidxBox = tree.try_into()?;
+        let anchor = tree.node.header.offset + tree.node.header.size;
+        // references are pushed in reverse so ranges come out in file order
+        for range in sidx.resolve(anchor).into_iter().rev() {
+            if !range.reference_type {
+                ranges.push(range);
+                continue;
+            }
+            let child = trees
+                .iter()
+                .find(|t| {
+                    t.node.header.typ == BoxType::Sidx && t.node.header.offset == range.offset
+                })
+                .ok_or(Error::InvalidData("sidx references a missing sidx"))?;
+            if child.node.header.offset < anchor {
+                return Err(Error::InvalidData("sidx references a preceding box"));
+            }
+            stack.push(child);
+        }
+    }
+    ranges.reverse();
+    Ok(ranges)
+}
+
+// computes a sidx for a fragmented file which is meant to be placed right
+// before the first moof; each moof starts a subsegment which ends at the next
+// moof, or at mfra for the last one
+pub fn build_sidx(trees: &[Mp4BoxTree], track_id: u32) -> Result<SidxBox> {
+    let track = tracks(trees)?
+        .into_iter()
+        .find(|t| t.track_id == track_id)
+        .ok_or(Error::TrakNotFound(track_id))?;
+
+    let offsets = box_offsets(trees);
+    let moofs: Vec<usize> = (0..trees.len())
+        .filter(|i| trees[*i].node.header.typ == BoxType::Moof)
+        .collect();
+    if moofs.is_empty() {
+        return Err(Error::BoxNotFound(BoxType::Moof));
+    }
+
+    // (start, size, earliest presentation time, end of presentation, starts with sap)
+    let mut subsegments = Vec::with_capacity(moofs.len());
+    let mut runs = track.runs.iter();
+    for (k, i) in moofs.iter().enumerate() {
+        let end = match moofs.get(k + 1) {
+            Some(n) => offsets[*n],
+            None => (*i..trees.len())
+                .find(|n| trees[*n].node.header.typ == BoxType::Mfra)
+                .map(|n| offsets[n])
+                .unwrap_or(offsets[trees.len()]),
+        };
+
+        let run_count: usize = trees[*i]
+            .find_all(BoxType::Traf)
+            .filter(|traf| {
+                find_box::<TfhdBox>(traf, &[BoxType::Tfhd]).map(|t| t.track_id) == Some(track_id)
+            })
+            .map(|traf| traf.find_all(BoxType::Trun).count())
+            .sum();
+        let runs: Vec<_> = runs.by_ref().take(run_count).collect();
+        let sample_count: usize = runs.iter().map(|r| r.trun.entries.len()).sum();
+
+        let mut earliest = u64::MAX;
+        let mut latest = 0;
+        let mut starts_with_sap = false;
+        if let Some(run) = runs.first() {
+            for (n, sample) in track
+                .samples_from(run.first_sample)
+                .take(sample_count)
+                .enumerate()
+            {
+                let sample = sample?;
+                if n == 0 {
+                    starts_with_sap = sample.is_sync;
+                }
+                // times are on the presentation timeline, where samples
+                // hidden by the edit list are not presented
+                if let Some(time) = track.presentation_time(sample.cts) {
+                    earliest = earliest.min(time);
+                    latest = latest.max(time + sample.duration as u64);
+                }
+            }
+        }
+        subsegments.push((
+            offsets[*i],
+            end - offsets[*i],
+            earliest,
+            latest,
+            starts_with_sap,
+        ));
+    }
+
+    // a subsegment without samples of the track takes the time of its neighbours
+    let mut next_earliest = subsegments
+        .last()
+        .map(|s| if s.2 == u64::MAX { 0 } else { s.3 })
+        .unwrap_or(0);
+    for s in subsegments.iter_mut().rev() {
+        if s.2 == u64::MAX {
+            s.2 = next_earliest;
+            s.3 = next_earliest;
+        }
+        next_earliest = s.2;
+    }
+
+    let mut references = Vec::with_capacity(subsegments.len());
+    for (k, s) in subsegments.iter().enumerate() {
+        let end_time = subsegments.get(k + 1).map(|n| n.2).unwrap_or(s.3);
+        references.push(SidxReference {
+            reference_type: false,
+            referenced_size: u32::try_from(s.1)
+                .ok()
+                .filter(|size| *size <= 0x7FFF_FFFF)
+                .ok_or(Error::InvalidData("subsegment is too large for sidx"))?,
+            subsegment_duration: u32::try_from(end_time.saturating_sub(s.2))
+                .map_err(|_| Error::InvalidData("subsegment is too long for sidx"))?,
+            starts_with_sap: s.4,
+            sap_type: if s.4 { 1 } else { 0 },
+            sap_delta_time: 0,
+        });
+    }
+
+    let earliest_presentation_time = subsegments[0].2;
+    Ok(SidxBox {
+        version: if earliest_presentation_time > u32::MAX as u64 {
+            1
+        } else {
+            0
+        },
+        flags: 0,
+        reference_id: track_id,
+        timescale: track.timescale,
+        earliest_presentation_time,
+        first_offset: 0,
+        references,
+    })
+}
+
+// replaces the top-level sidx boxes with one computed for the track;
+// explicit base_data_offset values in tfhd and moof offsets in tfra are
+// shifted to follow their moof
+pub fn insert_sidx(trees: &mut Vec<Mp4BoxTree>, track_id: u32) -> Result<()> {
+    let mut old_offsets = box_offsets(trees);
+    old_offsets.pop();
+    let mut i = 0;
+    trees.retain(|t| {
+        let keep = t.node.header.typ != BoxType::Sidx;
+        if !keep {
+            old_offsets.remove(i);
+        } else {
+            i += 1;
+        }
+        keep
+    });
+
+    let sidx = build_sidx(trees, track_id)?;
+    let position = trees
+        .iter()
+        .position(|t| t.node.header.typ == BoxType::Moof)
+        .ok_or(Error::BoxNotFound(BoxType::Moof))?;
+    trees.insert(
+        position,
+        Mp4BoxTree::from_data(BoxData::Sidx(sidx), Vec::new()),
+    );
+    old_offsets.insert(position, 0);
+
+    let new_offsets = box_offsets(trees);
+    let mut moved = Vec::new();
+    for (i, tree) in trees.iter_mut().enumerate() {
+        if tree.node.header.typ == BoxType::Moof {
+            shift_base_data_offsets(tree, new_offsets[i] as i64 - old_offsets[i] as i64);
+            moved.push((old_offsets[i], new_offsets[i]));
+        }
+    }
+
+    let tfras = trees
+        .iter_mut()
+        .filter(|t| t.node.header.typ == BoxType::Mfra)
+        .flat_map(|mfra| mfra.children.iter_mut());
+    for tfra in tfras {
+        if let BoxData::Tfra(tfra) = &mut tfra.node.data {
+            for e in tfra.entries.iter_mut() {
+                if let Some((_, offset)) = moved.iter().find(|(old, _)| *old == e.moof_offset) {
+                    e.moof_offset = *offset;
+                }
+            }
+            if tfra.entries.iter().any(|e| e.moof_offset > u32::MAX as u64) {
+                tfra.version = 1;
+            }
+        }
+    }
+    Ok(())
+}
+
+// offsets of the top-level boxes as they will be written, followed by the file size
+fn box_offsets(trees: &[Mp4BoxTree]) -> Vec<u64> {
+    let mut offsets = Vec::with_capacity(trees.len() + 1);
+    let mut offset = 0;
+    for tree in trees.iter() {
+        offsets.push(offset);
+        offset += tree.size();
+    }
+    offsets.push(offset);
+    offsets
+}
+
+fn shift_base_data_offsets(moof: &mut Mp4BoxTree, delta: i64) {
+    if delta == 0 {
+        return;
+    }
+    for traf in moof
+        .children
+        .iter_mut()
+        .filter(|c| c.node.header.typ == BoxType::Traf)
+    {
+        if let Some(tfhd) = traf.find_mut(BoxType::Tfhd) {
+            if let BoxData::Tfhd(tfhd) = &mut tfhd.node.data {
+                if let Some(offset) = tfhd.base_data_offset.as_mut() {
+                    *offset = offset.saturating_add_signed(delta);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::boxes::{ElstEntry, MfraBox, MfroBox, UnknownBox};
+    use crate::fragmenter::tests::fragment;
+    use crate::fragmenter::FragmentOptions;
+    use crate::muxer::{self, tests::test_samples};
+    use crate::reader::{self, ReadOptions};
+    use crate::types::{Bytes, FourCC};
+    use crate::writer;
+    use std::io::Cursor;
+
+    fn write_and_read(trees: &[Mp4BoxTree]) -> Vec<Mp4BoxTree> {
+        let mut buf = Vec::new();
+        writer::write_mp4_box_tree(trees, &mut buf).unwrap();
+        let size = buf.len() as u64;
+        reader::read_mp4_box(&mut Cursor::new(buf), size, &ReadOptions::default()).unwrap()
+    }
+
+    fn reference(reference_type: bool, referenced_size: u32, duration: u32) -> SidxReference {
+        SidxReference {
+            reference_type,
+            referenced_size,
+            subsegment_duration: duration,
+            starts_with_sap: !reference_type,
+            sap_type: if reference_type { 0 } else { 1 },
+            sap_delta_time: 0,
+        }
+    }
+
+    fn sidx_box(earliest_presentation_time: u64, references: Vec<SidxReference>) -> SidxBox {
+        SidxBox {
+            version: 0,
+            flags: 0,
+            reference_id: 1,
+            timescale: 1000,
+            earliest_presentation_time,
+            first_offset: 0,
+            references,
+        }
+    }
+
+    fn sidx(earliest_presentation_time: u64, references: Vec<SidxReference>) -> Mp4BoxTree {
+        let sidx = sidx_box(earliest_presentation_time, references);
+        Mp4BoxTree::from_data(BoxData::Sidx(sidx), Vec::new())
+    }
+
+    #[test]
+    fn sidx_ranges_through_a_child_sidx() {
+        // the root sidx indexes a child sidx, which indexes 60 and 40 bytes of media
+        let child = sidx(
+            100,
+            vec![reference(false, 60, 500), reference(false, 40, 400)],
+        );
+        let root = sidx(100, vec![reference(true, child.size() as u32, 900)]);
+        let media = UnknownBox {
+            box_type: FourCC::from(*b"free"),
+            offset: 0,
+            size: 100,
+            data_offset: 8,
+            data: Some(Bytes::from(vec![0u8; 92])),
+        };
+        let media = Mp4BoxTree::from_data(BoxData::Unknown(media), Vec::new());
+        let media_start = root.size() + child.size();
+        let trees = write_and_read(&[root, child, media]);
+
+        let ranges = segment_ranges(&trees, 1).unwrap();
+        assert_eq!(ranges.len(), 2);
+        assert_eq!(
+            (
+                ranges[0].offset,
+                ranges[0].size,
+                ranges[0].presentation_time
+            ),
+            (media_start, 60, 100)
+        );
+        assert_eq!(
+            (
+                ranges[1].offset,
+                ranges[1].size,
+                ranges[1].presentation_time
+            ),
+            (media_start + 60, 40, 600)
+        );
+        assert_eq!(ranges[1].last_byte(), Some(media_start + 99));
+        assert!(segment_ranges(&trees, 2).is_err());
+    }
+
+    #[test]
+    fn sidx_range_without_bytes() {
+        let sidx = sidx_box(0, vec![reference(false, 0, 0)]);
+        let ranges = sidx.resolve(16);
+        assert_eq!(ranges[0].offset, 16);
+        assert_eq!(ranges[0].last_byte(), None);
+    }
+
+    #[test]
+    fn insert_sidx_on_the_presentation_timeline() {
+        // video is presented from cts 512 through an edit, as the muxer does
+        let samples = test_samples(50, false);
+        let buf = fragment(&samples, FragmentOptions::default());
+        let size = buf.len() as u64;
+        let mut trees =
+            reader::read_mp4_box(&mut Cursor::new(buf), size, &ReadOptions::default()).unwrap();
+        let moov = trees
+            .iter_mut()
+            .find(|t| t.node.header.typ == BoxType::Moov)
+            .unwrap();
+        let trak = moov.find_mut(BoxType::Trak).unwrap();
+        let edit = ElstEntry {
+            segment_duration: 0,
+            media_time: 512,
+            media_rate_integer: 1,
+            media_rate_fraction: 0,
+        };
+        trak.children.insert(1, muxer::edts(vec![edit]));
+
+        insert_sidx(&mut trees, 1).unwrap();
+        let trees = write_and_read(&trees);
+        let sidx: &SidxBox = trees
+            .iter()
+            .find(|t| t.node.header.typ == BoxType::Sidx)
+            .unwrap()
+            .try_into()
+            .unwrap();
+        assert_eq!(sidx.timescale, 12800);
+        assert_eq!(sidx.earliest_presentation_time, 0);
+
+        // a subsegment per fragment of 10 frames
+        let moofs: Vec<u64> = trees
+            .iter()
+            .filter(|t| t.node.header.typ == BoxType::Moof)
+            .map(|t| t.node.header.offset)
+            .collect();
+        let ranges = segment_ranges(&trees, 1).unwrap();
+        assert_eq!(ranges.len(), moofs.len());
+        for (k, (range, moof)) in ranges.iter().zip(moofs.iter()).enumerate() {
+            assert_eq!(range.offset, *moof);
+            assert_eq!(range.presentation_time, k as u64 * 5120);
+            assert_eq!(range.duration, 5120);
+            assert!(range.starts_with_sap);
+        }
+        let end = *box_offsets(&trees).last().unwrap();
+        assert_eq!(ranges.last().unwrap().last_byte(), Some(end - 1));
+    }
+
+    #[test]
+    fn fragment_index_from_mfra_at_the_end() {
+        let buf = fragment(&test_samples(50, false), FragmentOptions::default());
+        let size = buf.len() as u64;
+        let mut trees =
+            reader::read_mp4_box(&mut Cursor::new(buf), size, &ReadOptions::default()).unwrap();
+        let moofs: Vec<u64> = trees
+            .iter()
+            .filter(|t| t.node.header.typ == BoxType::Moof)
+            .map(|t| t.node.header.offset)
+            .collect();
+
+        // entries are listed out of order, and are sorted by time when read
+        let entries = moofs
+            .iter()
+            .enumerate()
+            .rev()
+            .map(|(k, moof)| TfraEntry {
+                time: k as u64 * 5120,
+                moof_offset: *moof,
+                traf_number: 1,
+                trun_number: 1,
+                sample_number: 1,
+            })
+            .collect();
+        let tfra = TfraBox {
+            version: 0,
+            flags: 0,
+            track_id: 1,
+            length_size_of_traf_num: 0,
+            length_size_of_trun_num: 0,
+            length_size_of_sample_num: 0,
+            entries,
+        };
+        let mut mfro = MfroBox {
+            version: 0,
+            flags: 0,
+            size: 0,
+        };
+        let children = |mfro: &MfroBox| {
+            vec![
+                Mp4BoxTree::from_data(BoxData::Tfra(tfra.clone()), Vec::new()),
+                Mp4BoxTree::from_data(BoxData::Mfro(mfro.clone()), Vec::new()),
+            ]
+        };
+        mfro.size = Mp4BoxTree::from_data(BoxData::Mfra(MfraBox), children(&mfro)).size() as u32;
+        trees.push(Mp4BoxTree::from_data(
+            BoxData::Mfra(MfraBox),
+            children(&mfro),
+        ));
+        let mut buf = Vec::new();
+        writer::write_mp4_box_tree(&trees, &mut buf).unwrap();
+
+        // the boxes before mfra are not read
+        buf[..4].copy_from_slice(&u32::MAX.to_be_bytes());
+        let size = buf.len() as u64;
+        let mfra = reader::read_mfra(&mut Cursor::new(&buf), size, &ReadOptions::default())
+            .unwrap()
+            .unwrap();
+        let index = FragmentIndex::from_mfra(&mfra).unwrap();
+        assert_eq!(index.len(), 1);
+        assert_eq!(index[0].track_id, 1);
+        assert!(index[0].entries.windows(2).all(|e| e[0].time < e[1].time));
+        assert_eq!(index[0].moof_offset(0), Some(moofs[0]));
+        assert_eq!(index[0].moof_offset(6000), Some(moofs[1]));
+        assert_eq!(index[0].moof_offset(u64::MAX), moofs.last().copied());
+
+        let path = std::env::temp_dir().join(format!("mp4box-mfra-{}.mp4", std::process::id()));
+        std::fs::write(&path, &buf).unwrap();
+        let from_file = crate::read_fragment_index(std::fs::File::open(&path).unwrap());
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(from_file.unwrap(), index);
+
+        // a file which does not end with mfro has no index
+        let buf = fragment(&test_samples(20, false), FragmentOptions::default());
+        let size = buf.len() as u64;
+        let mfra = reader::read_mfra(&mut Cursor::new(buf), size, &ReadOptions::default());
+        assert!(mfra.unwrap().is_none());
+    }
+}
@@ -0,0 +1,731 @@
+use serde::Serialize;
+use std::time::Duration;
+
+use crate::boxes::{
+    BoxData, BoxType, Co64Box, CttsBox, ElstBox, ElstEntry, MdhdBox, MfhdBox, Mp4BoxTree, MvhdBox,
+    StcoBox, StscBox, StscEntry, StssBox, StszBox, SttsBox, TfdtBox, TfhdBox, TkhdBox, TrexBox,
+    TrunBox,
+};
+use crate::error::Error;
+use crate::types::Ratio;
+use crate::Result;
+
+const SAMPLE_IS_NON_SYNC_SAMPLE: u32 = 0x0001_0000;
+
+#[derive(Debug, Clone)]
+pub struct Track {
+    pub track_id: u32,
+    pub movie_timescale: u32,
+    pub timescale: u32,
+    pub duration: u64,
+    pub edits: Vec<ElstEntry>,
+    pub chunk_offsets: Vec<u64>,
+    pub trex: Option<TrexBox>,
+    pub runs: Vec<TrackRun>,
+    // data_reference_index and its self-contained flag per sample description
+    pub data_references: Vec<(u16, bool)>,
+    chunk_offset_type: BoxType,
+    sample_table: SampleTable,
+}
+
+// sample numbers are 1-based as in stss and stsc
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Sample {
+    pub index: u32,
+    pub offset: u64,
+    pub size: u32,
+    pub dts: u64,
+    pub cts: u64,
+    pub duration: u32,
+    pub is_sync: bool,
+    pub description_index: u32,
+}
+
+// a trun resolved against its moof, tfhd, tfdt and the trex defaults
+#[derive(Debug, Clone)]
+pub struct TrackRun {
+    pub sequence_number: u32,
+    pub first_sample: u32,
+    pub data_offset: u64,
+    pub base_media_decode_time: u64,
+    pub sample_description_index: u32,
+    pub default_sample_duration: u32,
+    pub default_sample_size: u32,
+    pub default_sample_flags: u32,
+    pub trun: TrunBox,
+    // decode time of each sample relative to base_media_decode_time
+    sample_times: Vec<u64>,
+    // positions of the sync samples within the run
+    sync_samples: Vec<u32>,
+}
+
+impl TrackRun {
+    fn build_index(&mut self) {
+        let mut time = 0;
+        self.sample_times = (0..self.trun.entries.len())
+            .map(|i| {
+                let start = time;
+                time += self.sample_duration(i) as u64;
+                start
+            })
+            .collect();
+        self.sync_samples = (0..self.trun.entries.len())
+            .filter(|i| self.sample_flags(*i) & SAMPLE_IS_NON_SYNC_SAMPLE == 0)
+            .map(|i| i as u32)
+            .collect();
+    }
+
+    fn sample_count(&self) -> u32 {
+        self.trun.entries.len() as u32
+    }
+
+    fn sample_duration(&self, i: usize) -> u32 {
+        self.trun.entries[i]
+            .sample_duration
+            .unwrap_or(self.default_sample_duration)
+    }
+
+    fn sample_size(&self, i: usize) -> u32 {
+        self.trun.entries[i]
+            .sample_size
+            .unwrap_or(self.default_sample_size)
+    }
+
+    fn sample_flags(&self, i: usize) -> u32 {
+        match (
+            self.trun.entries[i].sample_flags,
+            self.trun.first_sample_flags,
+        ) {
+            (Some(flags), _) => flags,
+            (None, Some(flags)) if i == 0 => flags,
+            _ => self.default_sample_flags,
+        }
+    }
+
+    fn data_size(&self) -> u64 {
+        (0..self.trun.entries.len())
+            .map(|i| self.sample_size(i) as u64)
+            .sum()
+    }
+
+    fn duration(&self) -> u64 {
+        (0..self.trun.entries.len())
+            .map(|i| self.sample_duration(i) as u64)
+            .sum()
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+struct SampleTable {
+    sample_count: u32,
+    sample_size: u32,
+    sample_sizes: Vec<u32>,
+    time_to_sample: Vec<TimeToSample>,
+    total_duration: u64,
+    composition_offsets: Vec<CompositionOffset>,
+    sync_samples: Option<Vec<u32>>,
+    sample_to_chunk: Vec<StscEntry>,
+}
+
+#[derive(Debug, Clone)]
+struct TimeToSample {
+    first_sample: u32,
+    first_dts: u64,
+    sample_delta: u32,
+}
+
+#[derive(Debug, Clone)]
+struct CompositionOffset {
+    first_sample: u32,
+    sample_offset: i32,
+}
+
+impl SampleTable {
+    fn new(stbl: &Mp4BoxTree, track_id: u32) -> Result<Self> {
+        let stts: &SttsBox = find_box(stbl, &[BoxType::Stts])
+            .ok_or(Error::BoxInStblNotFound(track_id, BoxType::Stts))?;
+        let stsc: &StscBox = find_box(stbl, &[BoxType::Stsc])
+            .ok_or(Error::BoxInStblNotFound(track_id, BoxType::Stsc))?;
+        let stsz: &StszBox = find_box(stbl, &[BoxType::Stsz])
+            .ok_or(Error::BoxInStblNotFound(track_id, BoxType::Stsz))?;
+
+        let mut time_to_sample = Vec::with_capacity(stts.entries.len());
+        let (mut first_sample, mut first_dts) = (1, 0);
+        for entry in stts.entries.iter() {
+            time_to_sample.push(TimeToSample {
+                first_sample,
+                first_dts,
+                sample_delta: entry.sample_delta,
+            });
+            first_sample += entry.sample_count;
+            first_dts += entry.sample_count as u64 * entry.sample_delta as u64;
+        }
+
+        let mut composition_offsets = Vec::new();
+        if let Some(ctts) = find_box::<CttsBox>(stbl, &[BoxType::Ctts]) {
+            let mut first_sample = 1;
+            for entry in ctts.entries.iter() {
+                composition_offsets.push(CompositionOffset {
+                    first_sample,
+                    sample_offset: entry.sample_offset,
+                });
+                first_sample += entry.sample_count;
+            }
+        }
+
+        let sync_samples =
+            find_box::<StssBox>(stbl, &[BoxType::Stss]).map(|stss| stss.entries.clone());
+
+        Ok(SampleTable {
+            sample_count: stsz.sample_count,
+            sample_size: stsz.sample_size,
+            sample_sizes: stsz.sample_sizes.clone(),
+            time_to_sample,
+            total_duration: first_dts,
+            composition_offsets,
+            sync_samples,
+            sample_to_chunk: stsc.entries.clone(),
+        })
+    }
+
+    // the sample being decoded at dts, or the last sample if dts is past the end
+    fn sample_at_dts(&self, dts: u64) -> Option<u32> {
+        let i = self.time_to_sample.partition_point(|e| e.first_dts <= dts);
+        let entry = &self.time_to_sample[i.checked_sub(1)?];
+        let n = match dts.checked_sub(entry.first_dts) {
+            Some(d) if entry.sample_delta > 0 => d / entry.sample_delta as u64,
+            _ => 0,
+        };
+        let index = entry.first_sample as u64 + n;
+        let last = match self.time_to_sample.get(i) {
+            Some(next) => next.first_sample - 1,
+            None => self.sample_count,
+        };
+        Some(index.min(last as u64) as u32)
+    }
+
+    fn sync_sample_at_or_before(&self, index: u32) -> u32 {
+        match &self.sync_samples {
+            Some(entries) => {
+                let i = entries.partition_point(|n| *n <= index);
+                match i.checked_sub(1) {
+                    Some(i) => entries[i],
+                    // no sync sample precedes it, decoding starts from the first sample
+                    None => 1,
+                }
+            }
+            None => index,
+        }
+    }
+}
+
+impl Track {
+    pub fn new(trak: &Mp4BoxTree, movie_timescale: u32) -> Result<Track> {
+        let tkhd: &TkhdBox =
+            find_box(trak, &[BoxType::Tkhd]).ok_or(Error::BoxNotFound(BoxType::Tkhd))?;
+        let track_id = tkhd.track_id;
+
+        let mdhd: &MdhdBox = find_box(trak, &[BoxType::Mdia, BoxType::Mdhd])
+            .ok_or(Error::BoxInTrakNotFound(track_id, BoxType::Mdhd))?;
+        let edits = find_box::<ElstBox>(trak, &[BoxType::Edts, BoxType::Elst])
+            .map(|elst| elst.entries.clone())
+            .unwrap_or_default();
+
+        let stbl = trak
+            .find_path(&[BoxType::Mdia, BoxType::Minf, BoxType::Stbl])
+            .ok_or(Error::BoxInTrakNotFound(track_id, BoxType::Stbl))?;
+        let (chunk_offsets, chunk_offset_type) =
+            if let Some(stco) = find_box::<StcoBox>(stbl, &[BoxType::Stco]) {
+                let offsets = stco
+                    .chunk_offsets
+                    .iter()
+                    .map(|o| *o as u32 as u64)
+                    .collect();
+                (offsets, BoxType::Stco)
+            } else if let Some(co64) = find_box::<Co64Box>(stbl, &[BoxType::Co64]) {
+                (co64.chunk_offsets.clone(), BoxType::Co64)
+            } else {
+                return Err(Error::Box2NotFound(BoxType::Stco, BoxType::Co64));
+            };
+        let sample_table = SampleTable::new(stbl, track_id)?;
+        let data_references = data_references(trak, stbl);
+
+        Ok(Track {
+            track_id,
+            movie_timescale,
+            timescale: mdhd.timescale,
+            duration: mdhd.duration,
+            edits,
+            chunk_offsets,
+            trex: None,
+            runs: Vec::new(),
+            data_references,
+            chunk_offset_type,
+            sample_table,
+        })
+    }
+
+    // chunk index is 1-based as in stsc
+    pub fn chunk_offset(&self, chunk: u32) -> Result<u64> {
+        chunk
+            .checked_sub(1)
+            .and_then(|i| self.chunk_offsets.get(i as usize))
+            .copied()
+            .ok_or(Error::EntryInStblNotFound(
+                self.track_id,
+                self.chunk_offset_type,
+                chunk,
+            ))
+    }
+
+    pub fn is_self_contained(&self, description_index: u32) -> bool {
+        description_index
+            .checked_sub(1)
+            .and_then(|i| self.data_references.get(i as usize))
+            .map(|(_, self_contained)| *self_contained)
+            .unwrap_or(true)
+    }
+
+    pub fn data_reference_index(&self, description_index: u32) -> u16 {
+        description_index
+            .checked_sub(1)
+            .and_then(|i| self.data_references.get(i as usize))
+            .map(|(index, _)| *index)
+            .unwrap_or(1)
+    }
+
+    // runs are numbered on from the sample table, so the last run ends the track
+    pub fn sample_count(&self) -> u32 {
+        match self.runs.last() {
+            Some(run) => run.first_sample - 1 + run.sample_count(),
+            None => self.sample_table.sample_count,
+        }
+    }
+
+    pub fn sample(&self, index: u32) -> Result<Sample> {
+        self.sample_at(index, None)
+    }
+
+    pub fn samples(&self) -> Samples<'_> {
+        self.samples_from(1)
+    }
+
+    pub fn samples_from(&self, index: u32) -> Samples<'_> {
+        Samples {
+            track: self,
+            index: index.max(1),
+            count: self.sample_count(),
+            prev: None,
+        }
+    }
+
+    fn sample_at(&self, index: u32, prev: Option<&Sample>) -> Result<Sample> {
+        // the previous sample lets sequential access skip summing sizes and durations
+        let prev = prev.filter(|p| p.index + 1 == index);
+        if index == 0 || (self.runs.is_empty() && index > self.sample_table.sample_count) {
+            Err(Error::EntryInStblNotFound(
+                self.track_id,
+                BoxType::Stsz,
+                index,
+            ))
+        } else if index <= self.sample_table.sample_count {
+            self.stbl_sample(index, prev)
+        } else {
+            self.run_sample(index, prev)
+        }
+    }
+
+    fn stbl_sample_size(&self, index: u32) -> Result<u32> {
+        let table = &self.sample_table;
+        if table.sample_size != 0 {
+            return Ok(table.sample_size);
+        }
+        table
+            .sample_sizes
+            .get(index as usize - 1)
+            .copied()
+            .ok_or(Error::EntryInStblNotFound(
+                self.track_id,
+                BoxType::Stsz,
+                index,
+            ))
+    }
+
+    fn stbl_sample(&self, index: u32, prev: Option<&Sample>) -> Result<Sample> {
+        let table = &self.sample_table;
+
+        let i = table
+            .sample_to_chunk
+            .partition_point(|e| e.first_sample <= index);
+        let entry = match i.checked_sub(1).map(|i| &table.sample_to_chunk[i]) {
+            Some(e) if e.sample_per_chunk > 0 => e,
+            _ => {
+                return Err(Error::EntryInStblNotFound(
+                    self.track_id,
+                    BoxType::Stsc,
+                    index,
+                ))
+            }
+        };
+        let chunk = entry.first_chunk + (index - entry.first_sample) / entry.sample_per_chunk;
+        let first_in_chunk =
+            entry.first_sample + (chunk - entry.first_chunk) * entry.sample_per_chunk;
+        let offset = match prev {
+            Some(p) if first_in_chunk < index => p.offset + p.size as u64,
+            _ => {
+                let mut offset = self.chunk_offset(chunk)?;
+                for n in first_in_chunk..index {
+                    offset += self.stbl_sample_size(n)? as u64;
+                }
+                offset
+            }
+        };
+
+        let i = table
+            .time_to_sample
+            .partition_point(|e| e.first_sample <= index);
+        let time = i.checked_sub(1).map(|i| &table.time_to_sample[i]).ok_or(
+            Error::EntryInStblNotFound(self.track_id, BoxType::Stts, index),
+        )?;
+        let dts = time.first_dts + (index - time.first_sample) as u64 * time.sample_delta as u64;
+
+        let i = table
+            .composition_offsets
+            .partition_point(|e| e.first_sample <= index);
+        let composition_offset = i
+            .checked_sub(1)
+            .map(|i| table.composition_offsets[i].sample_offset)
+            .unwrap_or(0);
+
+        let is_sync = match &table.sync_samples {
+            Some(entries) => entries.binary_search(&index).is_ok(),
+            None => true,
+        };
+
+        Ok(Sample {
+            index,
+            offset,
+            size: self.stbl_sample_size(index)?,
+            dts,
+            cts: dts.saturating_add_signed(composition_offset as i64),
+            duration: time.sample_delta,
+            is_sync,
+            description_index: entry.sample_description_index,
+        })
+    }
+
+    fn run_sample(&self, index: u32, prev: Option<&Sample>) -> Result<Sample> {
+        let i = self.runs.partition_point(|r| r.first_sample <= index);
+        let run = match i.checked_sub(1).map(|i| &self.runs[i]) {
+            Some(r) if index < r.first_sample + r.sample_count() => r,
+            _ => {
+                return Err(Error::EntryInTrunNotFound(
+                    self.track_id,
+                    BoxType::Trun,
+                    index,
+                ))
+            }
+        };
+
+        let n = (index - run.first_sample) as usize;
+        let (offset, dts) = match prev {
+            Some(p) if n > 0 => (p.offset + p.size as u64, p.dts + p.duration as u64),
+            _ => (
+                run.data_offset + (0..n).map(|i| run.sample_size(i) as u64).sum::<u64>(),
+                run.base_media_decode_time
+                    + (0..n).map(|i| run.sample_duration(i) as u64).sum::<u64>(),
+            ),
+        };
+        let composition_offset = run.trun.entries[n]
+            .sample_composition_time_offset
+            .unwrap_or(0);
+
+        Ok(Sample {
+            index,
+            offset,
+            size: run.sample_size(n),
+            dts,
+            cts: dts.saturating_add_signed(composition_offset),
+            duration: run.sample_duration(n),
+            is_sync: run.sample_flags(n) & SAMPLE_IS_NON_SYNC_SAMPLE == 0,
+            description_index: run.sample_description_index,
+        })
+    }
+
+    pub fn seek_duration(&self, time: Duration) -> Result<Sample> {
+        self.seek(Ratio::new(time.as_nanos() as u64, 1_000_000_000))
+    }
+
+    // time is in seconds. returns the sync sample at or before the sample decoded at that time
+    pub fn seek(&self, time: Ratio<u64>) -> Result<Sample> {
+        let dts = (time * self.timescale as u64).to_integer();
+        let table = &self.sample_table;
+
+        if self.runs.is_empty() || (table.sample_count > 0 && dts < table.total_duration) {
+            let index = table.sample_at_dts(dts).ok_or(Error::EntryInStblNotFound(
+                self.track_id,
+                BoxType::Stts,
+                0,
+            ))?;
+            return self.sample(table.sync_sample_at_or_before(index));
+        }
+
+        let i = self
+            .runs
+            .partition_point(|r| r.base_media_decode_time <= dts)
+            .max(1)
+            - 1;
+        let run = &self.runs[i];
+        let n = run
+            .sample_times
+            .partition_point(|t| run.base_media_decode_time + t <= dts)
+            .max(1)
+            - 1;
+
+        // the preceding sync sample, fragments usually start with one
+        let k = run.sync_samples.partition_point(|s| *s as usize <= n);
+        let index = match k.checked_sub(1) {
+            Some(k) => run.first_sample + run.sync_samples[k],
+            None => match self.runs[..i]
+                .iter()
+                .rev()
+                .find_map(|r| r.sync_samples.last().map(|s| r.first_sample + s))
+            {
+                Some(index) => index,
+                None if table.sample_count > 0 => {
+                    table.sync_sample_at_or_before(table.sample_count)
+                }
+                None => 1,
+            },
+        };
+        self.sample(index)
+    }
+
+    // maps a media timestamp (e.g. a composition time) to the presentation timeline,
+    // both in the media timescale. None is returned if the edit list hides the timestamp.
+    pub fn presentation_time(&self, media_time: u64) -> Option<u64> {
+        if self.edits.is_empty() {
+            return Some(media_time);
+        }
+
+        let mut start = 0;
+        for (i, edit) in self.edits.iter().enumerate() {
+            let duration = rescale(edit.segment_duration, self.movie_timescale, self.timescale);
+            if edit.is_empty_edit() || edit.is_dwell() {
+                start += duration;
+                continue;
+            }
+
+            // a zero duration on the last edit means the edit lasts until the end of the media
+            let unbounded = edit.segment_duration == 0 && i == self.edits.len() - 1;
+            let media_start = edit.media_time as u64;
+            if media_time >= media_start && (unbounded || media_time < media_start + duration) {
+                return Some(start + media_time - media_start);
+            }
+            start += duration;
+        }
+        None
+    }
+}
+
+pub struct Samples<'a> {
+    track: &'a Track,
+    index: u32,
+    count: u32,
+    prev: Option<Sample>,
+}
+
+impl Iterator for Samples<'_> {
+    type Item = Result<Sample>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.index > self.count {
+            return None;
+        }
+
+        let sample = self.track.sample_at(self.index, self.prev.as_ref());
+        match sample {
+            Ok(s) => {
+                self.index += 1;
+                self.prev = Some(s);
+            }
+            // stop after reporting the error
+            Err(_) => self.index = u32::MAX,
+        }
+        Some(sample)
+    }
+}
+
+pub fn tracks(trees: &[Mp4BoxTree]) -> Result<Vec<Track>> {
+    let moov = trees
+        .iter()
+        .find(|t| t.node.header.typ == BoxType::Moov)
+        .ok_or(Error::BoxNotFound(BoxType::Moov))?;
+    let mvhd: &MvhdBox =
+        find_box(moov, &[BoxType::Mvhd]).ok_or(Error::BoxNotFound(BoxType::Mvhd))?;
+
+    let mut tracks = moov
+        .find_all(BoxType::Trak)
+        .map(|trak| Track::new(trak, mvhd.timescale))
+        .collect::<Result<Vec<_>>>()?;
+
+    if let Some(mvex) = moov.find(BoxType::Mvex) {
+        for trex in mvex.find_all(BoxType::Trex) {
+            let trex: &TrexBox = trex.try_into()?;
+            if let Some(track) = tracks.iter_mut().find(|t| t.track_id == trex.track_id) {
+                track.trex = Some(trex.clone());
+            }
+        }
+    }
+
+    for moof in trees.iter().filter(|t| t.node.header.typ == BoxType::Moof) {
+        add_fragment(&mut tracks, moof)?;
+    }
+    Ok(tracks)
+}
+
+// tracks of an init segment with the fragments of a separate media segment;
+// sample offsets are relative to the start of the media segment
+pub fn segment_tracks(init: &[Mp4BoxTree], segment: &[Mp4BoxTree]) -> Result<Vec<Track>> {
+    let mut tracks = tracks(init)?;
+    for track in tracks.iter_mut() {
+        track.runs.clear();
+    }
+    for moof in segment
+        .iter()
+        .filter(|t| t.node.header.typ == BoxType::Moof)
+    {
+        add_fragment(&mut tracks, moof)?;
+    }
+    Ok(tracks)
+}
+
+pub fn add_fragment(tracks: &mut [Track], moof: &Mp4BoxTree) -> Result<()> {
+    let moof_offset = moof.node.header.offset;
+    let sequence_number = find_box::<MfhdBox>(moof, &[BoxType::Mfhd])
+        .map(|mfhd| mfhd.sequence_number)
+        .unwrap_or(0);
+
+    // end of the data of the previous traf, used when neither base_data_offset
+    // nor default-base-is-moof is given
+    let mut prev_end = moof_offset;
+    for traf in moof.find_all(BoxType::Traf) {
+        let tfhd: &TfhdBox = find_box(traf, &[BoxType::Tfhd])
+            .ok_or(Error::BoxInTrafNotFound(sequence_number, BoxType::Tfhd))?;
+        let track = tracks
+            .iter_mut()
+            .find(|t| t.track_id == tfhd.track_id)
+            .ok_or(Error::TrakNotFound(tfhd.track_id))?;
+        let trex = track
+            .trex
+            .as_ref()
+            .ok_or(Error::BoxInTrakNotFound(track.track_id, BoxType::Trex))?;
+
+        let base_data_offset = match tfhd.base_data_offset {
+            Some(offset) => offset,
+            None if tfhd.flags & TfhdBox::FLAG_DEFAULT_BASE_IS_MOOF != 0 => moof_offset,
+            None => prev_end,
+        };
+        let mut base_media_decode_time = match find_box::<TfdtBox>(traf, &[BoxType::Tfdt]) {
+            Some(tfdt) => tfdt.base_media_decode_time,
+            None => track
+                .runs
+                .last()
+                .map(|r| r.base_media_decode_time + r.duration())
+                .unwrap_or(0),
+        };
+
+        let mut data_offset = base_data_offset;
+        for trun in traf.find_all(BoxType::Trun) {
+            let trun: &TrunBox = trun.try_into()?;
+            if let Some(offset) = trun.data_offset {
+                data_offset = base_data_offset.saturating_add_signed(offset as i64);
+            }
+
+            let mut run = TrackRun {
+                sequence_number,
+                first_sample: track.sample_count() + 1,
+                data_offset,
+                base_media_decode_time,
+                sample_description_index: tfhd
+                    .sample_description_index
+                    .unwrap_or(trex.default_sample_description_index),
+                default_sample_duration: tfhd
+                    .default_sample_duration
+                    .unwrap_or(trex.default_sample_duration),
+                default_sample_size: tfhd.default_sample_size.unwrap_or(trex.default_sample_size),
+                default_sample_flags: tfhd
+                    .default_sample_flags
+                    .unwrap_or(trex.default_sample_flags),
+                trun: trun.clone(),
+                sample_times: Vec::new(),
+                sync_samples: Vec::new(),
+            };
+            run.build_index();
+            data_offset += run.data_size();
+            base_media_decode_time += run.duration();
+            track.runs.push(run);
+        }
+        prev_end = data_offset;
+    }
+    Ok(())
+}
+
+fn data_references(trak: &Mp4BoxTree, stbl: &Mp4BoxTree) -> Vec<(u16, bool)> {
+    let dref = trak.find_path(&[BoxType::Mdia, BoxType::Minf, BoxType::Dinf, BoxType::Dref]);
+    let stsd = match stbl.find(BoxType::Stsd) {
+        Some(stsd) => stsd,
+        None => return Vec::new(),
+    };
+
+    stsd.children
+        .iter()
+        .map(|entry| {
+            let index = sample_entry_data_reference_index(&entry.node.data);
+            // index 0 refers to no data reference, so the data can not be located
+            let self_contained = match index.checked_sub(1) {
+                Some(i) => match dref.and_then(|d| d.children.get(i as usize)) {
+                    Some(e) => match &e.node.data {
+                        BoxData::Url(url) => url.flags & 0x1 != 0,
+                        _ => false,
+                    },
+                    None => dref.is_none(),
+                },
+                None => false,
+            };
+            (index, self_contained)
+        })
+        .collect()
+}
+
+fn sample_entry_data_reference_index(data: &BoxData) -> u16 {
+    match data {
+        BoxData::Avc1(avc1) => avc1.data_reference_index,
+        BoxData::Hev1(hev1) => hev1.data_reference_index,
+        BoxData::Hvc1(hvc1) => hvc1.data_reference_index,
+        BoxData::Mp4a(mp4a) => mp4a.data_reference_index,
+        BoxData::Vp09(vp09) => vp09.data_reference_index,
+        BoxData::Av01(av01) => av01.data_reference_index,
+        BoxData::Tx3g(tx3g) => tx3g.data_reference_index,
+        // every SampleEntry starts with reserved(6) and data_reference_index(2)
+        BoxData::Unknown(b) => match &b.data {
+            Some(d) if d.len() >= 8 => u16::from_be_bytes([d[6], d[7]]),
+            _ => 1,
+        },
+        _ => 1,
+    }
+}
+
+pub(crate) fn find_box<'a, T>(tree: &'a Mp4BoxTree, path: &[BoxType]) -> Option<&'a T>
+where
+    &'a T: TryFrom<&'a Mp4BoxTree>,
+{
+    tree.find_path(path).and_then(|t| t.try_into().ok())
+}
+
+pub(crate) fn rescale(value: u64, from: u32, to: u32) -> u64 {
+    if from == 0 {
+        return value;
+    }
+    (value as u128 * to as u128 / from as u128) as u64
+}
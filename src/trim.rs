@@ -0,0 +1,544 @@
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::time::Duration;
+
+use crate::boxes::{
+    self, BoxData, BoxHeader, BoxType, ElstEntry, MoovBox, Mp4BoxTree, MvhdBox, TkhdBox, UnknownBox,
+};
+use crate::error::Error;
+use crate::faststart::{chunk_offset_boxes, chunk_offsets, set_chunk_offsets, MDAT};
+use crate::muxer::{self, SampleTableBuilder, MAX_CHUNK_SIZE};
+use crate::reader::{self, ReadOptions};
+use crate::track::{self, find_box, rescale, Sample, Track};
+use crate::types::{Bytes, FourCC};
+use crate::writer;
+use crate::Result;
+
+const TREF: u32 = 0x74_72_65_66;
+
+// the samples of a track kept by the trim, from the sync sample at or before
+// the start to the last sample presented before the end
+struct TrimmedTrack {
+    track_id: u32,
+    timescale: u32,
+    samples: Vec<Sample>,
+    edits: Vec<ElstEntry>,
+}
+
+// writes ftyp, moov and mdat with the samples presented in [start, end);
+// tracks without samples in the range are dropped
+pub(crate) fn trim<R: Read + Seek, W: Write>(
+    reader: &mut R,
+    size: u64,
+    writer: &mut W,
+    start: Duration,
+    end: Duration,
+) -> Result<u64> {
+    if start >= end {
+        return Err(Error::InvalidData("trim start must be before its end"));
+    }
+    let trees = reader::read_mp4_box(reader, size, &ReadOptions::default())?;
+    let moov = trees
+        .iter()
+        .find(|t| t.node.header.typ == BoxType::Moov)
+        .ok_or(Error::BoxNotFound(BoxType::Moov))?;
+    let tracks = track::tracks(&trees)?;
+
+    let mut selected = Vec::new();
+    for track in tracks.iter() {
+        if let Some(t) = select_samples(track, start, end)? {
+            selected.push(t);
+        }
+    }
+    if selected.is_empty() {
+        return Err(Error::InvalidData("no samples in the trimmed range"));
+    }
+
+    // samples are interleaved by decode time; chunk offsets are relative to
+    // the mdat payload until the size of moov is known
+    let mut builders: Vec<SampleTableBuilder> = selected
+        .iter()
+        .map(|t| {
+            let mut builder = SampleTableBuilder::default();
+            builder.last_duration = t.samples.last().map(|s| s.duration);
+            builder
+        })
+        .collect();
+    let mut next = vec![0; selected.len()];
+    let mut layout: Vec<(u64, u64)> = Vec::new();
+    let mut current = None;
+    let mut position = 0;
+    while let Some(i) = (0..selected.len())
+        .filter(|i| next[*i] < selected[*i].samples.len())
+        .min_by_key(|i| {
+            let t = &selected[*i];
+            let dts = t.samples[next[*i]].dts - t.samples[0].dts;
+            dts as u128 * 1_000_000_000 / t.timescale as u128
+        })
+    {
+        let t = &selected[i];
+        let sample = &t.samples[next[i]];
+        let size = sample.size as u64;
+        let builder = &mut builders[i];
+        let continues = current == Some(i)
+            && builder
+                .chunk_size()
+                .is_some_and(|s| s + size <= MAX_CHUNK_SIZE);
+        if !continues {
+            builder.start_chunk(position);
+        }
+        let first_dts = t.samples[0].dts;
+        builder.push(
+            sample.size,
+            sample.dts - first_dts,
+            sample.cts as i64 - sample.dts as i64,
+            sample.is_sync,
+        )?;
+
+        // adjacent samples are copied with a single read
+        match layout.last_mut() {
+            Some((offset, len)) if *offset + *len == sample.offset => *len += size,
+            _ => layout.push((sample.offset, size)),
+        }
+        position += size;
+        current = Some(i);
+        next[i] += 1;
+    }
+
+    let kept: Vec<u32> = selected.iter().map(|t| t.track_id).collect();
+    let mut movie_duration = 0;
+    let mut children = Vec::new();
+    for child in moov.children.iter() {
+        match child.node.header.typ {
+            BoxType::Mvhd | BoxType::Mvex => {}
+            BoxType::Trak => {
+                let track_id = find_box::<TkhdBox>(child, &[BoxType::Tkhd])
+                    .ok_or(Error::BoxNotFound(BoxType::Tkhd))?
+                    .track_id;
+                if let Some(i) = selected.iter().position(|t| t.track_id == track_id) {
+                    let duration = selected[i].edits.iter().map(|e| e.segment_duration).sum();
+                    movie_duration = u64::max(movie_duration, duration);
+                    let mut trak = trim_trak(child, &selected[i], &builders[i], duration)?;
+                    trim_tref(&mut trak, &kept)?;
+                    children.push(trak);
+                }
+            }
+            _ => children.push(child.clone()),
+        }
+    }
+    let mut mvhd: MvhdBox = find_box::<MvhdBox>(moov, &[BoxType::Mvhd])
+        .ok_or(Error::BoxNotFound(BoxType::Mvhd))?
+        .clone();
+    mvhd.duration = movie_duration;
+    if movie_duration > u32::MAX as u64 {
+        mvhd.version = 1;
+    }
+    children.insert(0, Mp4BoxTree::from_data(BoxData::Mvhd(mvhd), Vec::new()));
+    let mut moov = Mp4BoxTree::from_data(BoxData::Moov(MoovBox), children);
+
+    let ftyp = match trees.iter().find(|t| t.node.header.typ == BoxType::Ftyp) {
+        Some(ftyp) => ftyp.clone(),
+        None => Mp4BoxTree::from_data(BoxData::Ftyp(muxer::default_ftyp()), Vec::new()),
+    };
+    let mdat = BoxHeader::new(BoxType::from(MDAT), position + boxes::HEADER_SIZE, 0);
+    let mdat = if mdat.size > u32::MAX as u64 {
+        BoxHeader::new(mdat.typ, position + boxes::HEADER_SIZE_LARGE, 0)
+    } else {
+        mdat
+    };
+
+    // moov grows when stco becomes co64, which moves mdat again
+    let relative: Vec<Vec<u64>> = chunk_offset_boxes(&mut moov)
+        .iter()
+        .map(|b| chunk_offsets(b))
+        .collect();
+    loop {
+        let moov_size = moov.size();
+        let base = ftyp.size() + moov_size + mdat.size - position;
+        let boxes = chunk_offset_boxes(&mut moov);
+        for (b, offsets) in boxes.into_iter().zip(relative.iter()) {
+            set_chunk_offsets(b, offsets.iter().map(|o| o + base).collect());
+        }
+        if moov.size() == moov_size {
+            break;
+        }
+    }
+
+    let mut written = writer::write_mp4_box_tree_from(&[ftyp, moov], reader, writer)?;
+    written += boxes::write_box_header(&mdat, writer)?;
+    for (offset, len) in layout {
+        reader.seek(SeekFrom::Start(offset))?;
+        let copied = io::copy(&mut reader.take(len), writer)?;
+        if copied != len {
+            return Err(Error::InvalidData("sample data is outside of the file"));
+        }
+        written += copied;
+    }
+    Ok(written)
+}
+
+fn select_samples(track: &Track, start: Duration, end: Duration) -> Result<Option<TrimmedTrack>> {
+    let timescale = track.timescale;
+    let ticks = |time: Duration| (time.as_nanos() * timescale as u128 / 1_000_000_000) as u64;
+    let (start, end) = (ticks(start), ticks(end));
+    let samples = track.samples().collect::<Result<Vec<_>>>()?;
+
+    // decode order range of the samples presented within [start, end), and
+    // the presentation interval they cover along with the media time of its start
+    let mut range = None;
+    let mut earliest = None;
+    let mut latest = 0;
+    for (i, sample) in samples.iter().enumerate() {
+        let pt = match track.presentation_time(sample.cts) {
+            Some(pt) => pt,
+            None => continue,
+        };
+        let pt_end = pt + sample.duration as u64;
+        if pt >= end || pt_end <= start {
+            continue;
+        }
+        range = match range {
+            Some((first, _)) => Some((first, i)),
+            None => Some((i, i)),
+        };
+        if earliest.is_none_or(|(e, _)| pt < e) {
+            earliest = Some((pt, sample.cts));
+        }
+        latest = latest.max(pt_end);
+    }
+    let ((mut first, last), (earliest, earliest_cts)) = match (range, earliest) {
+        (Some(range), Some(earliest)) => (range, earliest),
+        _ => return Ok(None),
+    };
+    while first > 0 && !samples[first].is_sync {
+        first -= 1;
+    }
+    let samples = samples[first..=last].to_vec();
+
+    let description_index = samples[0].description_index;
+    if samples
+        .iter()
+        .any(|s| s.description_index != description_index)
+    {
+        return Err(Error::InvalidData(
+            "trimmed samples must share one sample description",
+        ));
+    }
+    if !track.is_self_contained(description_index) {
+        return Err(Error::InvalidData("sample data is in another file"));
+    }
+
+    // the pre-roll from the sync sample up to start is hidden by the edit
+    let presented = start.max(earliest);
+    // the media timeline of the output starts at the dts of the sync sample
+    let media_time = (earliest_cts + presented - earliest)
+        .checked_sub(samples[0].dts)
+        .ok_or(Error::InvalidData(
+            "trimmed range is presented before its first sync sample is decoded",
+        ))?;
+    let mut edits = Vec::new();
+    if presented > start {
+        edits.push(ElstEntry {
+            segment_duration: rescale(presented - start, timescale, track.movie_timescale),
+            media_time: -1,
+            media_rate_integer: 1,
+            media_rate_fraction: 0,
+        });
+    }
+    edits.push(ElstEntry {
+        segment_duration: rescale(
+            end.min(latest) - presented,
+            timescale,
+            track.movie_timescale,
+        ),
+        media_time: media_time as i64,
+        media_rate_integer: 1,
+        media_rate_fraction: 0,
+    });
+
+    Ok(Some(TrimmedTrack {
+        track_id: track.track_id,
+        timescale,
+        samples,
+        edits,
+    }))
+}
+
+// the original trak with new edits, durations and sample table; other boxes
+// such as the sample entry, handler and matrix are kept
+fn trim_trak(
+    trak: &Mp4BoxTree,
+    track: &TrimmedTrack,
+    samples: &SampleTableBuilder,
+    duration: u64,
+) -> Result<Mp4BoxTree> {
+    let track_id = track.track_id;
+    let stsd = trak
+        .find_path(&[BoxType::Mdia, BoxType::Minf, BoxType::Stbl, BoxType::Stsd])
+        .ok_or(Error::BoxInTrakNotFound(track_id, BoxType::Stsd))?;
+    let entry = stsd
+        .children
+        .get(track.samples[0].description_index as usize - 1)
+        .ok_or(Error::EntryInStblNotFound(
+            track_id,
+            BoxType::Stsd,
+            track.samples[0].description_index,
+        ))?;
+    let stbl = samples.build(entry)?;
+    let media_duration = samples.duration()?;
+
+    let mut trak = trak.clone();
+    trak.children.retain(|c| c.node.header.typ != BoxType::Edts);
+    let tkhd = trak
+        .children
+        .iter()
+        .position(|c| c.node.header.typ == BoxType::Tkhd)
+        .ok_or(Error::BoxInTrakNotFound(track_id, BoxType::Tkhd))?;
+    if let BoxData::Tkhd(tkhd) = &mut trak.children[tkhd].node.data {
+        tkhd.duration = duration;
+        if duration > u32::MAX as u64 {
+            tkhd.version = 1;
+        }
+    }
+    trak.children
+        .insert(tkhd + 1, muxer::edts(track.edits.clone()));
+
+    let mdia = trak
+        .find_mut(BoxType::Mdia)
+        .ok_or(Error::BoxInTrakNotFound(track_id, BoxType::Mdia))?;
+    if let Some(BoxData::Mdhd(mdhd)) = mdia.find_mut(BoxType::Mdhd).map(|m| &mut m.node.data) {
+        mdhd.duration = media_duration;
+        if media_duration > u32::MAX as u64 {
+            mdhd.version = 1;
+        }
+    }
+    if let Some(old) = mdia
+        .find_mut(BoxType::Minf)
+        .and_then(|minf| minf.find_mut(BoxType::Stbl))
+    {
+        *old = stbl;
+    }
+    Ok(trak)
+}
+
+// drops references to tracks which were removed, and reference types or the
+// whole tref box which are left without any track
+fn trim_tref(trak: &mut Mp4BoxTree, kept: &[u32]) -> Result<()> {
+    let Some(i) = trak
+        .children
+        .iter()
+        .position(|c| u32::from(c.node.header.typ) == TREF)
+    else {
+        return Ok(());
+    };
+    let BoxData::Unknown(tref) = &trak.children[i].node.data else {
+        return Ok(());
+    };
+    let data = tref
+        .data
+        .as_ref()
+        .ok_or(Error::InvalidData("tref data is not loaded"))?;
+
+    let mut out = Vec::new();
+    let mut rest = &data[..];
+    while rest.len() >= 8 {
+        let size = u32::from_be_bytes(rest[..4].try_into().unwrap()) as usize;
+        if size < 8 || size > rest.len() || size & 3 != 0 {
+            return Err(Error::InvalidData("invalid track reference box"));
+        }
+        let ids: Vec<&[u8]> = rest[8..size]
+            .chunks(4)
+            .filter(|id| kept.contains(&u32::from_be_bytes((*id).try_into().unwrap())))
+            .collect();
+        if !ids.is_empty() {
+            out.extend_from_slice(&(8 + ids.len() as u32 * 4).to_be_bytes());
+            out.extend_from_slice(&rest[4..8]);
+            ids.iter().for_each(|id| out.extend_from_slice(id));
+        }
+        rest = &rest[size..];
+    }
+
+    if out.is_empty() {
+        trak.children.remove(i);
+    } else {
+        let tref = UnknownBox {
+            box_type: FourCC::from(TREF),
+            offset: 0,
+            size: boxes::HEADER_SIZE + out.len() as u64,
+            data_offset: boxes::HEADER_SIZE,
+            data: Some(Bytes::from(out)),
+        };
+        trak.children[i] = Mp4BoxTree::from_data(BoxData::Unknown(tref), Vec::new());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::boxes::CttsBox;
+    use crate::muxer::tests::{mux, test_samples, TestSample};
+    use crate::reader::Mp4Reader;
+    use std::io::Cursor;
+
+    fn trim_samples(
+        samples: &[TestSample],
+        start: Duration,
+        end: Duration,
+    ) -> Mp4Reader<Cursor<Vec<u8>>> {
+        let buf = mux(samples);
+        let size = buf.len() as u64;
+        let mut out = Vec::new();
+        trim(&mut Cursor::new(buf), size, &mut out, start, end).unwrap();
+        let size = out.len() as u64;
+        Mp4Reader::new(Cursor::new(out), size).unwrap()
+    }
+
+    // the kept samples are rebased to the dts of the first one
+    fn assert_kept(reader: &mut Mp4Reader<Cursor<Vec<u8>>>, kept: &[TestSample]) {
+        let track = reader.track(1).unwrap().clone();
+        assert_eq!(track.sample_count() as usize, kept.len());
+        let first_dts = kept[0].dts;
+        for (sample, e) in track.samples().zip(kept.iter()) {
+            let sample = sample.unwrap();
+            assert_eq!(sample.dts, e.dts - first_dts);
+            assert_eq!(sample.cts, e.cts - first_dts);
+            assert_eq!(sample.is_sync, e.is_sync);
+            assert_eq!(
+                &reader.read_sample(1, sample.index).unwrap()[..],
+                &e.data[..]
+            );
+        }
+    }
+
+    #[test]
+    fn trim_from_preceding_keyframe() {
+        // frames are presented at cts - 512 through the edit of the muxer
+        let samples = test_samples(50, false);
+        let mut reader = trim_samples(
+            &samples,
+            Duration::from_millis(500),
+            Duration::from_millis(1500),
+        );
+
+        // frame 12 is the first in decode order presented after 0.5 s, so
+        // decoding starts at the keyframe 10; the edit skips to cts 6912
+        // which is presented at 0.5 s
+        let track = reader.track(1).unwrap();
+        assert_eq!(track.edits.len(), 1);
+        assert_eq!(track.edits[0].media_time, 6912 - 5120);
+        assert_eq!(track.edits[0].segment_duration, 1000);
+        // frame 37 is the last presented before 1.5 s
+        assert_kept(&mut reader, &samples[10..=37]);
+    }
+
+    #[test]
+    fn trim_keeps_negative_composition_offsets() {
+        // odd frames are presented before the even frame decoded ahead of them
+        let mut samples = test_samples(50, false);
+        for (i, s) in samples.iter_mut().enumerate() {
+            s.cts = if i % 2 == 0 { s.dts + 512 } else { s.dts - 512 };
+        }
+        let mut reader = trim_samples(
+            &samples,
+            Duration::from_millis(400),
+            Duration::from_millis(800),
+        );
+
+        let trak = reader.trees[1].find(BoxType::Trak).unwrap();
+        let ctts = find_box::<CttsBox>(
+            trak,
+            &[BoxType::Mdia, BoxType::Minf, BoxType::Stbl, BoxType::Ctts],
+        )
+        .unwrap();
+        assert_eq!(ctts.version, 1);
+        assert_eq!(ctts.entries[1].sample_offset, -512);
+        // the presentation starts at cts 0 of frame 11
+        let track = reader.track(1).unwrap();
+        assert_eq!(track.edits[0].media_time, 0);
+        assert_kept(&mut reader, &samples[10..20]);
+    }
+
+    #[test]
+    fn trim_keeps_large_boxes_and_drops_references_to_removed_tracks() {
+        // the audio track ends before the trimmed range and is dropped
+        let samples: Vec<TestSample> = test_samples(50, true)
+            .into_iter()
+            .filter(|s| s.track_id == 1 || s.dts < 13 * 1024)
+            .collect();
+        let buf = mux(&samples);
+        let size = buf.len() as u64;
+        let mut source = Cursor::new(buf);
+        let mut trees = reader::read_mp4_box(&mut source, size, &ReadOptions::default()).unwrap();
+
+        // moov follows mdat, so boxes added to it do not move the samples
+        let mut tref = Vec::new();
+        for (typ, ids) in [(b"cdsc", &[2u32][..]), (b"sync", &[1, 2][..])] {
+            tref.extend_from_slice(&(8 + ids.len() as u32 * 4).to_be_bytes());
+            tref.extend_from_slice(typ);
+            ids.iter()
+                .for_each(|id| tref.extend_from_slice(&id.to_be_bytes()));
+        }
+        let opaque = |typ: u32, data: Vec<u8>| {
+            let b = UnknownBox {
+                box_type: FourCC::from(typ),
+                offset: 0,
+                size: boxes::HEADER_SIZE + data.len() as u64,
+                data_offset: boxes::HEADER_SIZE,
+                data: Some(Bytes::from(data)),
+            };
+            Mp4BoxTree::from_data(BoxData::Unknown(b), Vec::new())
+        };
+        let moov = trees
+            .iter_mut()
+            .find(|t| t.node.header.typ == BoxType::Moov)
+            .unwrap();
+        moov.children
+            .push(opaque(u32::from_be_bytes(*b"uuid"), vec![7; 100_000]));
+        moov.find_mut(BoxType::Trak)
+            .unwrap()
+            .children
+            .push(opaque(TREF, tref));
+        let mut edited = Vec::new();
+        writer::write_mp4_box_tree_from(&trees, &mut source, &mut edited).unwrap();
+
+        // the uuid box is read back without its payload, which is copied
+        let size = edited.len() as u64;
+        let mut out = Vec::new();
+        trim(
+            &mut Cursor::new(edited),
+            size,
+            &mut out,
+            Duration::from_millis(500),
+            Duration::from_millis(1000),
+        )
+        .unwrap();
+        let size = out.len() as u64;
+        let reader = Mp4Reader::new(Cursor::new(out), size).unwrap();
+        assert!(reader.track(2).is_err());
+
+        let moov = reader
+            .trees
+            .iter()
+            .find(|t| t.node.header.typ == BoxType::Moov)
+            .unwrap();
+        let uuid = moov
+            .children
+            .iter()
+            .find(|c| u32::from(c.node.header.typ) == u32::from_be_bytes(*b"uuid"))
+            .unwrap();
+        assert_eq!(uuid.node.header.size, 100_008);
+        let trak = moov.find(BoxType::Trak).unwrap();
+        let tref = trak
+            .children
+            .iter()
+            .find(|c| u32::from(c.node.header.typ) == TREF)
+            .unwrap();
+        let BoxData::Unknown(tref) = &tref.node.data else {
+            panic!("tref is not kept opaque");
+        };
+        assert_eq!(
+            &tref.data.as_ref().unwrap()[..],
+            &[0, 0, 0, 12, b's', b'y', b'n', b'c', 0, 0, 0, 1]
+        );
+    }
+}
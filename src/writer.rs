@@ -0,0 +1,179 @@
+use std::io::{self, Read, Seek, SeekFrom, Write};
+
+use crate::boxes::{self, BoxData, BoxHeader, Ibox, Mp4Box, Mp4BoxTree};
+use crate::error::Error;
+use crate::Result;
+
+impl Mp4BoxTree {
+    // builds a node to be written; offsets are assigned when the tree is written
+    pub fn from_data(data: BoxData, children: Vec<Mp4BoxTree>) -> Self {
+        let mut tree = Mp4BoxTree {
+            node: Mp4Box {
+                header: BoxHeader::new(data.typ(), 0, 0),
+                data,
+            },
+            children,
+        };
+        tree.node.header.size = tree.size();
+        tree
+    }
+
+    pub fn size(&self) -> u64 {
+        if let BoxData::Phtm(_) = self.node.data {
+            return self.children.iter().map(|c| c.size()).sum();
+        }
+
+        let size =
+            self.node.data.effective_size() + self.children.iter().map(|c| c.size()).sum::<u64>();
+        match &self.node.data {
+            // opaque boxes already count the header they were read with
+            BoxData::Unknown(_) => size,
+            _ if self.node.header.largesize || size > u32::MAX as u64 => {
+                size + boxes::HEADER_SIZE_LARGE - boxes::HEADER_SIZE
+            }
+            _ => size,
+        }
+    }
+
+    // the header as written at offset; a largesize header is kept, and a size
+    // of 0 is kept for the last box of its container when its size is unchanged
+    pub(crate) fn write_header(&self, offset: u64, last: bool) -> BoxHeader {
+        let size = self.size();
+        let read = &self.node.header;
+        let mut header = BoxHeader::new(read.typ, size, offset);
+        header.largesize = read.largesize;
+        header.to_end = read.to_end && last && size == read.size;
+        header
+    }
+
+    fn write<W: Write, R: Read + Seek>(
+        &self,
+        writer: &mut W,
+        offset: u64,
+        source: &mut Option<&mut R>,
+        last: bool,
+    ) -> Result<u64> {
+        let mut current = offset;
+        if let BoxData::Phtm(_) = self.node.data {
+            return write_trees(&self.children, writer, offset, source);
+        }
+
+        let size = self.size();
+        if !matches!(&self.node.data, BoxData::Unknown(b) if b.is_tail()) {
+            current += boxes::write_box_header(&self.write_header(offset, last), writer)?;
+        }
+        current += match (&self.node.data, source.as_mut()) {
+            (BoxData::Unknown(b), Some(reader)) if b.data.is_none() => {
+                // copy the payload which was too large to be kept in the tree
+                reader.seek(SeekFrom::Start(b.data_offset))?;
+                let data_size = b.data_size();
+                let copied = io::copy(&mut reader.take(data_size), writer)?;
+                if copied != data_size {
+                    return Err(Error::InvalidData("source is shorter than box payload"));
+                }
+                copied
+            }
+            (data, _) => data.write(writer, current)?,
+        };
+        current += write_trees(&self.children, writer, current, source)?;
+
+        if current - offset != size {
+            return Err(Error::InvalidData(
+                "written box size differs from computed size",
+            ));
+        }
+        Ok(size)
+    }
+}
+
+fn write_trees<W: Write, R: Read + Seek>(
+    trees: &[Mp4BoxTree],
+    writer: &mut W,
+    offset: u64,
+    source: &mut Option<&mut R>,
+) -> Result<u64> {
+    let mut current = offset;
+    for (i, tree) in trees.iter().enumerate() {
+        current += tree.write(writer, current, source, i + 1 == trees.len())?;
+    }
+    Ok(current - offset)
+}
+
+pub fn write_mp4_box_tree<W: Write>(trees: &[Mp4BoxTree], writer: &mut W) -> Result<u64> {
+    let mut source = None as Option<&mut io::Empty>;
+    write_trees(trees, writer, 0, &mut source)
+}
+
+pub fn write_mp4_box_tree_from<R: Read + Seek, W: Write>(
+    trees: &[Mp4BoxTree],
+    reader: &mut R,
+    writer: &mut W,
+) -> Result<u64> {
+    let mut source = Some(reader);
+    write_trees(trees, writer, 0, &mut source)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::muxer::tests::{mux, test_samples};
+    use crate::reader::{self, ReadOptions};
+    use std::io::Cursor;
+
+    // a muxed file whose mdat has a large header under 4 GiB, followed by a
+    // box of size 0 which extends to the end of the file
+    fn test_file() -> Vec<u8> {
+        let mut buf = mux(&test_samples(20, true));
+        let ftyp_size = u32::from_be_bytes(buf[0..4].try_into().unwrap()) as usize;
+        let header = &mut buf[ftyp_size..ftyp_size + 16];
+        let mdat_size = u32::from_be_bytes(header[8..12].try_into().unwrap()) as u64 + 8;
+        header[0..4].copy_from_slice(&1u32.to_be_bytes());
+        header[4..8].copy_from_slice(b"mdat");
+        header[8..16].copy_from_slice(&mdat_size.to_be_bytes());
+        buf.extend_from_slice(&0u32.to_be_bytes());
+        buf.extend_from_slice(b"free");
+        buf.extend_from_slice(&[1; 20]);
+        buf
+    }
+
+    fn read(buf: &[u8], options: &ReadOptions) -> Vec<Mp4BoxTree> {
+        reader::read_mp4_box(&mut Cursor::new(buf), buf.len() as u64, options).unwrap()
+    }
+
+    #[test]
+    fn round_trip_identical_bytes() {
+        let buf = test_file();
+        let trees = read(&buf, &ReadOptions::default());
+        assert!(trees[1].node.header.largesize);
+        assert!(trees.last().unwrap().node.header.to_end);
+
+        let mut out = Vec::new();
+        write_mp4_box_tree(&trees, &mut out).unwrap();
+        assert_eq!(out, buf);
+
+        // payloads which are not kept in the tree are copied from the source
+        let options = ReadOptions {
+            max_unknown_data_size: 0,
+        };
+        let trees = read(&buf, &options);
+        let mut out = Vec::new();
+        write_mp4_box_tree_from(&trees, &mut Cursor::new(&buf), &mut out).unwrap();
+        assert_eq!(out, buf);
+    }
+
+    #[test]
+    fn size_zero_box_is_sized_when_not_last() {
+        let buf = test_file();
+        let mut trees = read(&buf, &ReadOptions::default());
+        let free = trees.last().unwrap().clone();
+        trees.push(trees[0].clone());
+
+        let mut out = Vec::new();
+        write_mp4_box_tree(&trees, &mut out).unwrap();
+        let trees = read(&out, &ReadOptions::default());
+        let header = trees[trees.len() - 2].node.header;
+        assert_eq!(header.typ, free.node.header.typ);
+        assert_eq!(header.size, free.node.header.size);
+        assert!(!header.to_end);
+    }
+}